@@ -6,6 +6,7 @@
 #include <stdlib.h>
 #include <string.h>
 #include <stdbool.h>
+#include <stdint.h>
 
 void _printf(char *msg)
 {
@@ -27,6 +28,25 @@ int _parse_int(char *s)
     return atoi(s);
 }
 
+long _parse_long(char *s)
+{
+    return strtol(s, NULL, 10);
+}
+
+char *_int_to_str(long n)
+{
+    char *buf = malloc(32);
+    snprintf(buf, 32, "%ld", n);
+    return buf;
+}
+
+char *_uint_to_str(unsigned long n)
+{
+    char *buf = malloc(32);
+    snprintf(buf, 32, "%lu", n);
+    return buf;
+}
+
 char *_read_line()
 {
     size_t capacity = 256;
@@ -41,4 +61,28 @@ char *_read_line()
     return buf;
 }
 
+/* Copies an array literal to the heap, so it outlives the function it is
+ * created in */
+void *_heap_array(void *values, size_t size)
+{
+    return memcpy(malloc(size), values, size);
+}
+
+/* Signed division that wraps around instead of trapping when the smallest
+ * value is divided by -1 */
+int64_t _div_i64(int64_t a, int64_t b)
+{
+    return b == -1 ? (int64_t)(0 - (uint64_t)a) : a / b;
+}
+
+int64_t _rem_i64(int64_t a, int64_t b)
+{
+    return b == -1 ? 0 : a % b;
+}
+
+int64_t _div_assign_i64(int64_t *a, int64_t b)
+{
+    return *a = _div_i64(*a, b);
+}
+
 /* END builtins */
@@ -14,16 +14,25 @@ function _strlen(s) {
 }
 
 function _parse_int(s) {
-  return parseInt(s, 10);
+  // Mirrors atoi(): strings without leading digits become 0 instead of NaN
+  return parseInt(s, 10) || 0;
+}
+
+function _parse_long(s) {
+  // Like _parse_int, but without losing precision above 2^53
+  const digits = /^\s*([+-]?\d+)/.exec(s);
+  return digits ? BigInt(digits[1]) : 0n;
 }
 
 function _int_to_str(n) {
-  // Note: JS numbers lose precision above Number.MAX_SAFE_INTEGER (2^53-1),
-  // so for |n| >= 2^53 this diverges from the QBE C runtime's snprintf("%ld").
-  // Acceptable because every i64 op in the JS backend has the same ceiling.
+  // 64-bit integers are BigInts, which String() formats without the "n"
   return String(n);
 }
 
+function _str_split(s, sep) {
+  return s.split(sep);
+}
+
 function _read_line() {
   const fs = require("fs");
   const buf = Buffer.alloc(256);
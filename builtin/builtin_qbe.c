@@ -7,6 +7,7 @@
  *
  *   _str_concat  — heap-allocates a new string from two inputs
  *   _int_to_str  — formats a long integer into a heap-allocated string
+ *   _uint_to_str — formats an unsigned long integer into a heap-allocated string
  *   _read_line   — reads one line from stdin into a heap-allocated buffer
  *
  * _printf, _exit, _strlen, and _parse_int are implemented directly in QBE IL
@@ -30,6 +31,13 @@ char *_int_to_str(long n)
     return buf;
 }
 
+char *_uint_to_str(unsigned long n)
+{
+    char *buf = malloc(32);
+    snprintf(buf, 32, "%lu", n);
+    return buf;
+}
+
 char *_read_line()
 {
     size_t capacity = 256;
@@ -62,6 +70,36 @@ char *_str_substr(char *s, long start, long len)
     return buf;
 }
 
+/* _str_split(s, sep) — split s at every occurrence of sep into a new array.
+ * The result uses the array layout of the QBE backend: the length, followed
+ * by one pointer per element. An empty separator splits into characters. */
+long *_str_split(char *s, char *sep)
+{
+    size_t s_len = strlen(s);
+    size_t sep_len = strlen(sep);
+    long count = 1;
+    if (sep_len == 0) {
+        count = (long)s_len;
+    } else {
+        for (char *p = strstr(s, sep); p; p = strstr(p + sep_len, sep)) count++;
+    }
+
+    long *arr = malloc(sizeof(long) * (count + 1));
+    arr[0] = count;
+    char *start = s;
+    for (long i = 0; i < count; i++) {
+        char *end = sep_len == 0 ? start + 1 : strstr(start, sep);
+        if (!end) end = s + s_len;
+        size_t n = end - start;
+        char *part = malloc(n + 1);
+        memcpy(part, start, n);
+        part[n] = '\0';
+        arr[i + 1] = (long)part;
+        start = end + sep_len;
+    }
+    return arr;
+}
+
 /* _fread_all(fp) — read entire file into a malloc'd string, return it */
 char *_fread_all(FILE *fp)
 {
@@ -16,8 +16,9 @@
 use crate::lexer::*;
 use core::convert::TryFrom;
 use std::collections::HashMap;
-use std::collections::HashSet;
+use std::fmt;
 
+use super::span::Span;
 use super::types::Type;
 
 /// Table that contains all symbol and its types
@@ -28,9 +29,11 @@ pub type SymbolTable = HashMap<String, Option<Type>>;
 /// directly map to simple backend constructs
 #[derive(Debug, Clone)]
 pub struct HModule {
-    pub imports: HashSet<String>,
+    /// Paths of the imported modules, with the span of their `import`
+    pub imports: HashMap<String, Span>,
     pub func: Vec<HFunction>,
     pub structs: Vec<HStructDef>,
+    pub types: Vec<HTypeDef>,
     pub globals: Vec<String>,
 }
 
@@ -38,6 +41,7 @@ impl HModule {
     pub fn merge_with(&mut self, mut other: HModule) {
         self.func.append(&mut other.func);
         self.structs.append(&mut other.structs);
+        self.types.append(&mut other.types);
         self.globals.append(&mut other.globals)
     }
 
@@ -64,9 +68,13 @@ impl HModule {
 #[derive(Debug, Clone)]
 pub struct HFunction {
     pub name: String,
-    pub arguments: Vec<HVariable>,
+    pub arguments: Vec<HArgument>,
     pub body: HStatement,
     pub ret_type: Option<Type>,
+    /// Whether a method takes `mut self`, which allows it to assign to the
+    /// fields of the struct it is called on
+    pub mutable_self: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -74,12 +82,39 @@ pub struct HStructDef {
     pub name: String,
     pub fields: Vec<HVariable>,
     pub methods: Vec<HFunction>,
+    /// Fields that embed another struct. An embedded struct is stored in a
+    /// field named after it, and its fields and methods are promoted.
+    pub embeds: Vec<String>,
+    pub span: Span,
+}
+
+/// A `type Name = <type>` alias or a `newtype Name = <type>` declaration
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HTypeDef {
+    pub name: String,
+    pub ty: Type,
+    pub newtype: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct HVariable {
     pub name: String,
     pub ty: Option<Type>,
+    pub span: Span,
+}
+
+/// A function parameter. A parameter with a default value may be left out
+/// at call sites, e.g. `mode` in `fn file_open(path: string, mode: string = "r")`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HArgument {
+    pub name: String,
+    pub ty: Option<Type>,
+    pub default: Option<HExpression>,
+    /// `args: ...T` takes the remaining arguments of a call, which are
+    /// collected into an array. `ty` is the array type `T[]`.
+    pub variadic: bool,
+    pub span: Span,
 }
 
 impl AsRef<HVariable> for HVariable {
@@ -91,15 +126,29 @@ impl AsRef<HVariable> for HVariable {
 /// High-level statements include constructs like match that will be
 /// lowered to simpler constructs in the LLAST
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub enum HStatement {
+pub struct HStatement {
+    pub kind: HStatementKind,
+    pub span: Span,
+}
+
+impl HStatement {
+    pub fn new(kind: HStatementKind, span: Span) -> Self {
+        HStatement { kind, span }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum HStatementKind {
     /// (Statements, Scoped variables)
     Block {
         statements: Vec<HStatement>,
         scope: Vec<HVariable>,
     },
+    /// `let` declares an immutable variable, `let mut` and `var` a mutable one
     Declare {
         variable: HVariable,
         value: Option<HExpression>,
+        mutable: bool,
     },
     Assign {
         lhs: Box<HExpression>,
@@ -131,7 +180,34 @@ pub enum HStatement {
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub enum HExpression {
+pub struct HExpression {
+    pub kind: HExpressionKind,
+    pub span: Span,
+}
+
+impl HExpression {
+    pub fn new(kind: HExpressionKind, span: Span) -> Self {
+        HExpression { kind, span }
+    }
+}
+
+/// Trees that are built by hand in tests have no source location
+#[cfg(test)]
+impl From<HExpressionKind> for HExpression {
+    fn from(kind: HExpressionKind) -> Self {
+        HExpression::new(kind, Span::default())
+    }
+}
+
+#[cfg(test)]
+impl From<HStatementKind> for HStatement {
+    fn from(kind: HStatementKind) -> Self {
+        HStatement::new(kind, Span::default())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum HExpressionKind {
     Int(usize),
     Str(String),
     Bool(bool),
@@ -144,16 +220,22 @@ pub enum HExpression {
     FunctionCall {
         fn_name: String,
         args: Vec<HExpression>,
+        /// Arguments passed by name, e.g. `file_open(path, mode: "w")`. Type
+        /// inference moves them into `args`, together with missing defaults.
+        named: Vec<(String, HExpression)>,
     },
     Variable(String),
+    /// Indexing into an arbitrary expression, e.g. `grid[i][j]` or `user.tags[0]`
     ArrayAccess {
-        name: String,
+        expr: Box<HExpression>,
         index: Box<HExpression>,
     },
     BinOp {
         lhs: Box<HExpression>,
         op: HBinOp,
         rhs: Box<HExpression>,
+        /// Integer type of an arithmetic result, filled in during type inference
+        ty: Option<Type>,
     },
     StructInitialization {
         name: String,
@@ -163,27 +245,34 @@ pub enum HExpression {
         expr: Box<HExpression>,
         field: Box<HExpression>,
     },
+    /// Explicit conversion, e.g. `x as string` or `Meters(5)`.
+    /// The type of the operand is filled in during type inference.
+    Cast {
+        expr: Box<HExpression>,
+        from: Option<Type>,
+        to: Type,
+    },
 }
 
-impl TryFrom<Token> for HExpression {
+impl TryFrom<Token> for HExpressionKind {
     type Error = String;
 
     fn try_from(token: Token) -> std::result::Result<Self, String> {
         let kind = token.kind;
         match kind {
-            TokenKind::Identifier(val) => Ok(HExpression::Variable(val)),
-            TokenKind::Literal(Value::Int) => Ok(HExpression::Int(
+            TokenKind::Identifier(val) => Ok(HExpressionKind::Variable(val)),
+            TokenKind::Literal(Value::Int) => Ok(HExpressionKind::Int(
                 token
                     .raw
                     .parse()
                     .map_err(|_| "Int value could not be parsed")?,
             )),
             TokenKind::Keyword(Keyword::Boolean) => match token.raw.as_ref() {
-                "true" => Ok(HExpression::Bool(true)),
-                "false" => Ok(HExpression::Bool(false)),
+                "true" => Ok(HExpressionKind::Bool(true)),
+                "false" => Ok(HExpressionKind::Bool(false)),
                 _ => Err("Boolean value could not be parsed".into()),
             },
-            TokenKind::Literal(Value::Str(string)) => Ok(HExpression::Str(string)),
+            TokenKind::Literal(Value::Str(string)) => Ok(HExpressionKind::Str(string)),
             _ => Err("Value could not be parsed".into()),
         }
     }
@@ -236,6 +325,47 @@ impl HBinOp {
             HBinOp::Multiplication | HBinOp::Division | HBinOp::Modulus => 7,
         }
     }
+
+    /// Returns true for operators that compute a number, including the
+    /// compound assignments
+    pub fn is_arithmetic(&self) -> bool {
+        !matches!(
+            self,
+            HBinOp::LessThan
+                | HBinOp::LessThanOrEqual
+                | HBinOp::GreaterThan
+                | HBinOp::GreaterThanOrEqual
+                | HBinOp::Equal
+                | HBinOp::NotEqual
+                | HBinOp::And
+                | HBinOp::Or
+        )
+    }
+}
+
+impl fmt::Display for HBinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            HBinOp::Addition => "+",
+            HBinOp::Subtraction => "-",
+            HBinOp::Multiplication => "*",
+            HBinOp::Division => "/",
+            HBinOp::Modulus => "%",
+            HBinOp::LessThan => "<",
+            HBinOp::LessThanOrEqual => "<=",
+            HBinOp::GreaterThan => ">",
+            HBinOp::GreaterThanOrEqual => ">=",
+            HBinOp::Equal => "==",
+            HBinOp::NotEqual => "!=",
+            HBinOp::And => "&&",
+            HBinOp::Or => "||",
+            HBinOp::AddAssign => "+=",
+            HBinOp::SubtractAssign => "-=",
+            HBinOp::MultiplyAssign => "*=",
+            HBinOp::DivideAssign => "/=",
+        };
+        write!(f, "{}", symbol)
+    }
 }
 
 impl TryFrom<TokenKind> for HBinOp {
@@ -17,6 +17,7 @@ use crate::lexer::*;
 use core::convert::TryFrom;
 use std::collections::HashMap;
 
+use super::span::Span;
 use super::types::Type;
 
 /// Table that contains all symbol and its types
@@ -39,6 +40,10 @@ pub struct Function {
     pub arguments: Vec<Variable>,
     pub body: Statement,
     pub ret_type: Option<Type>,
+    /// Whether a method takes `mut self`, which allows it to assign to the
+    /// fields of the struct it is called on
+    pub mutable_self: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -46,12 +51,15 @@ pub struct StructDef {
     pub name: String,
     pub fields: Vec<Variable>,
     pub methods: Vec<Function>,
+    #[allow(dead_code)]
+    pub span: Span,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Variable {
     pub name: String,
     pub ty: Option<Type>,
+    pub span: Span,
 }
 
 impl AsRef<Variable> for Variable {
@@ -64,7 +72,19 @@ impl AsRef<Variable> for Variable {
 /// to backend code generation. Complex constructs like match are lowered
 /// to simpler if-else chains
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Statement { kind, span }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum StatementKind {
     /// (Statements, Scoped variables)
     Block {
         statements: Vec<Statement>,
@@ -99,7 +119,34 @@ pub enum Statement {
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Expression { kind, span }
+    }
+}
+
+/// Trees that are built by hand in tests have no source location
+#[cfg(test)]
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Expression::new(kind, Span::default())
+    }
+}
+
+#[cfg(test)]
+impl From<StatementKind> for Statement {
+    fn from(kind: StatementKind) -> Self {
+        Statement::new(kind, Span::default())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ExpressionKind {
     Int(usize),
     Str(String),
     Bool(bool),
@@ -114,14 +161,17 @@ pub enum Expression {
         args: Vec<Expression>,
     },
     Variable(String),
+    /// Indexing into an arbitrary expression, e.g. `grid[i][j]` or `user.tags[0]`
     ArrayAccess {
-        name: String,
+        expr: Box<Expression>,
         index: Box<Expression>,
     },
     BinOp {
         lhs: Box<Expression>,
         op: BinOp,
         rhs: Box<Expression>,
+        /// Integer type of an arithmetic result, if known
+        ty: Option<Type>,
     },
     StructInitialization {
         name: String,
@@ -131,27 +181,33 @@ pub enum Expression {
         expr: Box<Expression>,
         field: Box<Expression>,
     },
+    /// Conversion between two primitive types, e.g. `5 as string`
+    Cast {
+        expr: Box<Expression>,
+        from: Type,
+        to: Type,
+    },
 }
 
-impl TryFrom<Token> for Expression {
+impl TryFrom<Token> for ExpressionKind {
     type Error = String;
 
     fn try_from(token: Token) -> std::result::Result<Self, String> {
         let kind = token.kind;
         match kind {
-            TokenKind::Identifier(val) => Ok(Expression::Variable(val)),
-            TokenKind::Literal(Value::Int) => Ok(Expression::Int(
+            TokenKind::Identifier(val) => Ok(ExpressionKind::Variable(val)),
+            TokenKind::Literal(Value::Int) => Ok(ExpressionKind::Int(
                 token
                     .raw
                     .parse()
                     .map_err(|_| "Int value could not be parsed")?,
             )),
             TokenKind::Keyword(Keyword::Boolean) => match token.raw.as_ref() {
-                "true" => Ok(Expression::Bool(true)),
-                "false" => Ok(Expression::Bool(false)),
+                "true" => Ok(ExpressionKind::Bool(true)),
+                "false" => Ok(ExpressionKind::Bool(false)),
                 _ => Err("Boolean value could not be parsed".into()),
             },
-            TokenKind::Literal(Value::Str(string)) => Ok(Expression::Str(string)),
+            TokenKind::Literal(Value::Str(string)) => Ok(ExpressionKind::Str(string)),
             _ => Err("Value could not be parsed".into()),
         }
     }
@@ -1,5 +1,6 @@
 pub mod hast;
 pub mod last;
+pub mod span;
 pub mod transform;
 /**
  * Copyright 2021 Garrit Franke
@@ -19,4 +20,8 @@ pub mod transform;
 pub mod types;
 
 // Re-export the unified AST types from llast
-pub use last::{BinOp, Expression, Function, Module, Statement, StructDef, SymbolTable, Variable};
+pub use last::{
+    BinOp, Expression, ExpressionKind, Function, Module, Statement, StatementKind, StructDef,
+    SymbolTable, Variable,
+};
+pub use span::Span;
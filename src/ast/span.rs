@@ -0,0 +1,61 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+/// Identifies a source file. The builder numbers files in the order it
+/// reads them, the standard library included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(pub usize);
+
+/// The location of a node in the source, as a range of byte offsets into
+/// its file. Nodes that are created by the compiler itself keep the span of
+/// the code they stand in for, e.g. the `if` chain a `match` is lowered to
+/// has the span of the `match`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Span { file, start, end }
+    }
+
+    /// The first `len` bytes of the span, e.g. the name of a function call
+    pub fn prefix(self, len: usize) -> Span {
+        Span {
+            end: (self.start + len).min(self.end),
+            ..self
+        }
+    }
+
+    /// The last `len` bytes of the span, e.g. the closing brace of a block
+    pub fn suffix(self, len: usize) -> Span {
+        Span {
+            start: self.end.saturating_sub(len).max(self.start),
+            ..self
+        }
+    }
+
+    /// The smallest span that covers both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
@@ -15,6 +15,9 @@
  */
 use super::hast::*;
 use super::last::*;
+use super::types::Type;
+use super::Span;
+use crate::diagnostic::{codes, Diagnostic};
 use std::collections::HashMap;
 
 /// Transforms high-level AST to low-level AST
@@ -23,7 +26,7 @@ use std::collections::HashMap;
 pub struct AstTransformer;
 
 impl AstTransformer {
-    pub fn transform_module(hmodule: HModule) -> Result<Module, String> {
+    pub fn transform_module(hmodule: HModule) -> Result<Module, Diagnostic> {
         let mut func = Vec::new();
         let mut structs = Vec::new();
 
@@ -37,26 +40,33 @@ impl AstTransformer {
 
         Ok(Module {
             func,
-            structs,
+            structs: Self::order_structs(structs),
             globals: hmodule.globals,
         })
     }
 
-    fn transform_function(hfunc: HFunction) -> Result<Function, String> {
+    fn transform_function(hfunc: HFunction) -> Result<Function, Diagnostic> {
         let mut arguments = Vec::new();
+        // Defaults have been filled in at every call site during type inference
         for harg in hfunc.arguments {
-            arguments.push(Self::transform_variable(harg));
+            arguments.push(Self::transform_variable(HVariable {
+                name: harg.name,
+                ty: harg.ty,
+                span: harg.span,
+            }));
         }
 
         Ok(Function {
             name: hfunc.name,
             arguments,
             body: Self::transform_statement(hfunc.body)?,
-            ret_type: hfunc.ret_type,
+            ret_type: hfunc.ret_type.map(Type::erased),
+            mutable_self: hfunc.mutable_self,
+            span: hfunc.span,
         })
     }
 
-    fn transform_struct_def(hstruct: HStructDef) -> Result<StructDef, String> {
+    fn transform_struct_def(hstruct: HStructDef) -> Result<StructDef, Diagnostic> {
         let mut fields = Vec::new();
         let mut methods = Vec::new();
 
@@ -72,19 +82,58 @@ impl AstTransformer {
             name: hstruct.name,
             fields,
             methods,
+            span: hstruct.span,
         })
     }
 
+    /// Moves every struct behind the structs it holds by value, so that
+    /// backends laying out a struct already know the size of its fields
+    fn order_structs(structs: Vec<StructDef>) -> Vec<StructDef> {
+        fn visit(
+            index: usize,
+            structs: &[StructDef],
+            visited: &mut Vec<bool>,
+            order: &mut Vec<usize>,
+        ) {
+            if visited[index] {
+                return;
+            }
+            visited[index] = true;
+            for field in &structs[index].fields {
+                if let Some(Type::Struct(name)) = &field.ty {
+                    if let Some(inner) = structs.iter().position(|s| &s.name == name) {
+                        visit(inner, structs, visited, order);
+                    }
+                }
+            }
+            order.push(index);
+        }
+
+        let mut visited = vec![false; structs.len()];
+        let mut order = Vec::new();
+        for index in 0..structs.len() {
+            visit(index, &structs, &mut visited, &mut order);
+        }
+        let mut slots: Vec<Option<StructDef>> = structs.into_iter().map(Some).collect();
+        order
+            .into_iter()
+            .filter_map(|index| slots[index].take())
+            .collect()
+    }
+
     fn transform_variable(hvar: HVariable) -> Variable {
         Variable {
             name: hvar.name,
-            ty: hvar.ty,
+            // Newtypes only exist for type checking, generators see the underlying type
+            ty: hvar.ty.map(Type::erased),
+            span: hvar.span,
         }
     }
 
-    fn transform_statement(hstmt: HStatement) -> Result<Statement, String> {
-        match hstmt {
-            HStatement::Block { statements, scope } => {
+    fn transform_statement(hstmt: HStatement) -> Result<Statement, Diagnostic> {
+        let span = hstmt.span;
+        let kind = match hstmt.kind {
+            HStatementKind::Block { statements, scope } => {
                 let mut lstmts = Vec::new();
                 let mut lscope = Vec::new();
 
@@ -96,35 +145,37 @@ impl AstTransformer {
                     lscope.push(Self::transform_variable(hvar));
                 }
 
-                Ok(Statement::Block {
+                StatementKind::Block {
                     statements: lstmts,
                     scope: lscope,
-                })
+                }
             }
-            HStatement::Declare { variable, value } => {
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
                 let lvar = Self::transform_variable(variable);
                 let lvalue = match value {
                     Some(hexpr) => Some(Self::transform_expression(hexpr)?),
                     None => None,
                 };
 
-                Ok(Statement::Declare {
+                StatementKind::Declare {
                     variable: lvar,
                     value: lvalue,
-                })
+                }
             }
-            HStatement::Assign { lhs, rhs } => Ok(Statement::Assign {
+            HStatementKind::Assign { lhs, rhs } => StatementKind::Assign {
                 lhs: Box::new(Self::transform_expression(*lhs)?),
                 rhs: Box::new(Self::transform_expression(*rhs)?),
-            }),
-            HStatement::Return(hexpr) => {
+            },
+            HStatementKind::Return(hexpr) => {
                 let lexpr = match hexpr {
                     Some(expr) => Some(Self::transform_expression(expr)?),
                     None => None,
                 };
-                Ok(Statement::Return(lexpr))
+                StatementKind::Return(lexpr)
             }
-            HStatement::If {
+            HStatementKind::If {
                 condition,
                 body,
                 else_branch,
@@ -136,27 +187,30 @@ impl AstTransformer {
                     None => None,
                 };
 
-                Ok(Statement::If {
+                StatementKind::If {
                     condition: lcond,
                     body: lbody,
                     else_branch: lelse,
-                })
+                }
             }
-            HStatement::While { condition, body } => Ok(Statement::While {
+            HStatementKind::While { condition, body } => StatementKind::While {
                 condition: Self::transform_expression(condition)?,
                 body: Box::new(Self::transform_statement(*body)?),
-            }),
-            HStatement::For { ident, expr, body } => Ok(Statement::For {
+            },
+            HStatementKind::For { ident, expr, body } => StatementKind::For {
                 ident: Self::transform_variable(ident),
                 expr: Self::transform_expression(expr)?,
                 body: Box::new(Self::transform_statement(*body)?),
-            }),
+            },
             // This is the key transformation: match -> if-else chain
-            HStatement::Match { subject, arms } => Self::transform_match_to_if_else(subject, arms),
-            HStatement::Break => Ok(Statement::Break),
-            HStatement::Continue => Ok(Statement::Continue),
-            HStatement::Exp(hexpr) => Ok(Statement::Exp(Self::transform_expression(hexpr)?)),
-        }
+            HStatementKind::Match { subject, arms } => {
+                return Self::transform_match_to_if_else(subject, arms, span)
+            }
+            HStatementKind::Break => StatementKind::Break,
+            HStatementKind::Continue => StatementKind::Continue,
+            HStatementKind::Exp(hexpr) => StatementKind::Exp(Self::transform_expression(hexpr)?),
+        };
+        Ok(Statement::new(kind, span))
     }
 
     /// Transforms a match statement into a chain of if-else statements
@@ -165,9 +219,14 @@ impl AstTransformer {
     fn transform_match_to_if_else(
         subject: HExpression,
         arms: Vec<HMatchArm>,
-    ) -> Result<Statement, String> {
+        span: Span,
+    ) -> Result<Statement, Diagnostic> {
         if arms.is_empty() {
-            return Err("Match statement must have at least one arm".to_string());
+            return Err(
+                Diagnostic::error("Match statement must have at least one arm")
+                    .with_code(codes::SYNTAX)
+                    .with_label(span, ""),
+            );
         }
 
         let lsubject = Self::transform_expression(subject)?;
@@ -175,6 +234,21 @@ impl AstTransformer {
         // Build if-else chain from match arms
         let mut current_stmt: Option<Statement> = None;
 
+        // Wrap single statements in blocks for generator compatibility
+        let block = |lbody: Statement| match lbody.kind {
+            StatementKind::Block { .. } => lbody,
+            _ => {
+                let span = lbody.span;
+                Statement::new(
+                    StatementKind::Block {
+                        statements: vec![lbody],
+                        scope: vec![],
+                    },
+                    span,
+                )
+            }
+        };
+
         // Process arms in reverse order to build nested if-else structure
         for arm in arms.into_iter().rev() {
             match arm {
@@ -183,82 +257,95 @@ impl AstTransformer {
                     let lbody = Self::transform_statement(body)?;
 
                     // Create equality check: subject == pattern
-                    let condition = Expression::BinOp {
-                        lhs: Box::new(lsubject.clone()),
-                        op: BinOp::Equal,
-                        rhs: Box::new(lpattern),
-                    };
-
-                    // Wrap single statements in blocks for generator compatibility
-                    let body_block = match lbody {
-                        Statement::Block { .. } => lbody,
-                        other => Statement::Block {
-                            statements: vec![other],
-                            scope: vec![],
+                    let pattern_span = lpattern.span;
+                    let condition = Expression::new(
+                        ExpressionKind::BinOp {
+                            lhs: Box::new(lsubject.clone()),
+                            op: BinOp::Equal,
+                            rhs: Box::new(lpattern),
+                            ty: None,
                         },
-                    };
+                        pattern_span,
+                    );
 
-                    current_stmt = Some(Statement::If {
-                        condition,
-                        body: Box::new(body_block),
-                        else_branch: current_stmt.map(Box::new),
-                    });
+                    current_stmt = Some(Statement::new(
+                        StatementKind::If {
+                            condition,
+                            body: Box::new(block(lbody)),
+                            else_branch: current_stmt.map(Box::new),
+                        },
+                        span,
+                    ));
                 }
                 HMatchArm::Else(body) => {
                     let lbody = Self::transform_statement(body)?;
-                    // Wrap single statements in blocks for generator compatibility
-                    let body_block = match lbody {
-                        Statement::Block { .. } => lbody,
-                        other => Statement::Block {
-                            statements: vec![other],
-                            scope: vec![],
-                        },
-                    };
-                    current_stmt = Some(body_block);
+                    current_stmt = Some(block(lbody));
                 }
             }
         }
 
-        current_stmt.ok_or_else(|| "Failed to transform match statement".to_string())
+        current_stmt.ok_or_else(|| Diagnostic::error("Failed to transform match statement"))
     }
 
-    fn transform_expression(hexpr: HExpression) -> Result<Expression, String> {
-        match hexpr {
-            HExpression::Int(val) => Ok(Expression::Int(val)),
-            HExpression::Str(val) => Ok(Expression::Str(val)),
-            HExpression::Bool(val) => Ok(Expression::Bool(val)),
-            HExpression::Selff => Ok(Expression::Selff),
-            HExpression::Array { capacity, elements } => {
+    fn transform_expression(hexpr: HExpression) -> Result<Expression, Diagnostic> {
+        let span = hexpr.span;
+        let kind = match hexpr.kind {
+            HExpressionKind::Int(val) if val > i32::MAX as usize => {
+                return Err(
+                    Diagnostic::error(format!("Literal `{}` does not fit into `int`", val))
+                        .with_code(codes::LITERAL)
+                        .with_label(span, "")
+                        .with_help(format!("use a suffix such as `{}i64`", val)),
+                )
+            }
+            HExpressionKind::Int(val) => ExpressionKind::Int(val),
+            HExpressionKind::Str(val) => ExpressionKind::Str(val),
+            HExpressionKind::Bool(val) => ExpressionKind::Bool(val),
+            HExpressionKind::Selff => ExpressionKind::Selff,
+            HExpressionKind::Array { capacity, elements } => {
                 let mut lelements = Vec::new();
                 for helement in elements {
                     lelements.push(Self::transform_expression(helement)?);
                 }
-                Ok(Expression::Array {
+                ExpressionKind::Array {
                     capacity,
                     elements: lelements,
-                })
+                }
             }
-            HExpression::FunctionCall { fn_name, args } => {
+            HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            } => {
+                if let Some((name, _)) = named.first() {
+                    return Err(Diagnostic::error(format!(
+                        "Named argument `{}` of `{}` could not be resolved",
+                        name, fn_name
+                    ))
+                    .with_code(codes::ARGUMENTS)
+                    .with_label(span, ""));
+                }
                 let mut largs = Vec::new();
                 for harg in args {
                     largs.push(Self::transform_expression(harg)?);
                 }
-                Ok(Expression::FunctionCall {
+                ExpressionKind::FunctionCall {
                     fn_name,
                     args: largs,
-                })
+                }
             }
-            HExpression::Variable(name) => Ok(Expression::Variable(name)),
-            HExpression::ArrayAccess { name, index } => Ok(Expression::ArrayAccess {
-                name,
+            HExpressionKind::Variable(name) => ExpressionKind::Variable(name),
+            HExpressionKind::ArrayAccess { expr, index } => ExpressionKind::ArrayAccess {
+                expr: Box::new(Self::transform_expression(*expr)?),
                 index: Box::new(Self::transform_expression(*index)?),
-            }),
-            HExpression::BinOp { lhs, op, rhs } => Ok(Expression::BinOp {
+            },
+            HExpressionKind::BinOp { lhs, op, rhs, ty } => ExpressionKind::BinOp {
                 lhs: Box::new(Self::transform_expression(*lhs)?),
                 op: Self::transform_bin_op(op),
                 rhs: Box::new(Self::transform_expression(*rhs)?),
-            }),
-            HExpression::StructInitialization { name, fields } => {
+                ty: ty.map(Type::erased),
+            },
+            HExpressionKind::StructInitialization { name, fields } => {
                 let mut lfields = HashMap::new();
                 for (field_name, field_expr) in fields {
                     lfields.insert(
@@ -266,16 +353,61 @@ impl AstTransformer {
                         Box::new(Self::transform_expression(*field_expr)?),
                     );
                 }
-                Ok(Expression::StructInitialization {
+                ExpressionKind::StructInitialization {
                     name,
                     fields: lfields,
-                })
+                }
             }
-            HExpression::FieldAccess { expr, field } => Ok(Expression::FieldAccess {
+            HExpressionKind::FieldAccess { expr, field } => ExpressionKind::FieldAccess {
                 expr: Box::new(Self::transform_expression(*expr)?),
                 field: Box::new(Self::transform_expression(*field)?),
-            }),
-        }
+            },
+            HExpressionKind::Cast { expr, from, to } => {
+                let from = from
+                    .ok_or_else(|| {
+                        Diagnostic::error(format!("Type of the value cast to `{}` is unknown", to))
+                            .with_code(codes::INFERENCE)
+                            .with_label(span, "")
+                    })?
+                    .erased();
+                let to = to.erased();
+                // Conversions between a newtype and its underlying type don't change the value
+                if from == to {
+                    return Self::transform_expression(*expr);
+                }
+                // 64-bit literals are generated as such, without passing through
+                // `int`. So are literals that only fit into their own sized type,
+                // e.g. `4000000000u32`.
+                if let HExpressionKind::Int(val) = expr.kind {
+                    let too_big = val > i32::MAX as usize && to.is_sized_integer();
+                    if to.int_bits() == Some(64) || too_big {
+                        if to.int_max().is_some_and(|max| val as u64 > max) {
+                            return Err(Diagnostic::error(format!(
+                                "Literal `{}` does not fit into `{}`",
+                                val, to
+                            ))
+                            .with_code(codes::LITERAL)
+                            .with_label(span, ""));
+                        }
+                        let literal = Expression::new(ExpressionKind::Int(val), expr.span);
+                        return Ok(Expression::new(
+                            ExpressionKind::Cast {
+                                expr: Box::new(literal),
+                                from,
+                                to,
+                            },
+                            span,
+                        ));
+                    }
+                }
+                ExpressionKind::Cast {
+                    expr: Box::new(Self::transform_expression(*expr)?),
+                    from,
+                    to,
+                }
+            }
+        };
+        Ok(Expression::new(kind, span))
     }
 
     fn transform_bin_op(hop: HBinOp) -> BinOp {
@@ -14,15 +14,75 @@
  * limitations under the License.
  */
 use std::convert::TryFrom;
+use std::fmt;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Type {
     Any,
+    /// Platform independent 32-bit signed integer, equivalent in range to `i32`
     Int,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
     Str,
     Bool,
     Array(Box<Type>, Option<usize>),
     Struct(String),
+    /// A nominal type declared with `newtype Name = <type>`.
+    /// It shares the representation of the underlying type, but can not be
+    /// mixed with it without an explicit conversion.
+    Newtype(String, Box<Type>),
+}
+
+impl Type {
+    /// Returns true for `int` and every sized integer type
+    pub fn is_integer(&self) -> bool {
+        self.int_bits().is_some()
+    }
+
+    /// Returns true for the explicitly sized integer types, e.g. `u8`
+    pub fn is_sized_integer(&self) -> bool {
+        self.is_integer() && *self != Type::Int
+    }
+
+    /// Returns true for `u8`, `u16`, `u32` and `u64`
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::U8 | Type::U16 | Type::U32 | Type::U64)
+    }
+
+    /// Width of an integer type in bits
+    pub fn int_bits(&self) -> Option<u32> {
+        match self {
+            Type::I8 | Type::U8 => Some(8),
+            Type::I16 | Type::U16 => Some(16),
+            Type::Int | Type::I32 | Type::U32 => Some(32),
+            Type::I64 | Type::U64 => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Largest value an integer type can hold
+    pub fn int_max(&self) -> Option<u64> {
+        let bits = self.int_bits()?;
+        Some(match self.is_unsigned() {
+            true => u64::MAX >> (64 - bits),
+            false => (1 << (bits - 1)) - 1,
+        })
+    }
+
+    /// Replaces every newtype with its underlying representation
+    pub fn erased(self) -> Type {
+        match self {
+            Type::Newtype(_, inner) => inner.erased(),
+            Type::Array(inner, capacity) => Type::Array(Box::new(inner.erased()), capacity),
+            ty => ty,
+        }
+    }
 }
 
 impl TryFrom<String> for Type {
@@ -33,7 +93,37 @@ impl TryFrom<String> for Type {
             "string" => Ok(Self::Str),
             "any" => Ok(Self::Any),
             "bool" => Ok(Self::Bool),
+            "i8" => Ok(Self::I8),
+            "i16" => Ok(Self::I16),
+            "i32" => Ok(Self::I32),
+            "i64" => Ok(Self::I64),
+            "u8" => Ok(Self::U8),
+            "u16" => Ok(Self::U16),
+            "u32" => Ok(Self::U32),
+            "u64" => Ok(Self::U64),
             name => Ok(Self::Struct(name.to_string())),
         }
     }
 }
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Int => write!(f, "int"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::Str => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Array(inner, Some(capacity)) => write!(f, "{}[{}]", inner, capacity),
+            Type::Array(inner, None) => write!(f, "{}[]", inner),
+            Type::Struct(name) | Type::Newtype(name, _) => write!(f, "{}", name),
+        }
+    }
+}
@@ -14,12 +14,15 @@
  * limitations under the License.
  */
 use crate::ast::hast::HModule;
+use crate::ast::span::FileId;
 use crate::ast::transform::AstTransformer;
+use crate::diagnostic::{self, Diagnostic, Severity, SourceMap};
 use crate::generator::{self, Generator, Target};
 use crate::lexer;
 use crate::parser;
 use crate::Lib;
 use crate::PathBuf;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::Read;
@@ -28,6 +31,18 @@ use std::io::Write;
 pub struct Builder {
     in_file: PathBuf,
     modules: Vec<HModule>,
+    /// Directory of the entrypoint, which the paths of modules are relative to
+    base_directory: PathBuf,
+    /// Every source file that has been read, used to render diagnostics
+    sources: SourceMap,
+    /// The files of the program, by the path they were read from. The
+    /// standard library is not part of them.
+    files: HashMap<PathBuf, FileId>,
+    /// Every `import` of the program, used to find unused ones
+    imports: Vec<parser::Import>,
+    options: diagnostic::Options,
+    /// Problems that do not stop the build
+    warnings: Vec<Diagnostic>,
 }
 
 impl Builder {
@@ -35,10 +50,21 @@ impl Builder {
         Self {
             in_file: entrypoint,
             modules: Vec::new(),
+            base_directory: PathBuf::new(),
+            sources: SourceMap::default(),
+            files: HashMap::new(),
+            imports: Vec::new(),
+            options: diagnostic::Options::default(),
+            warnings: Vec::new(),
         }
     }
 
-    fn get_base_path(&self) -> Result<PathBuf, String> {
+    pub fn with_options(mut self, options: diagnostic::Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn get_base_path(&self) -> Result<PathBuf, Diagnostic> {
         Ok(self
             .in_file
             .parent()
@@ -46,7 +72,7 @@ impl Builder {
             .to_path_buf())
     }
 
-    pub fn build(&mut self, target: &Target) -> Result<(), String> {
+    pub fn build(&mut self, target: &Target) -> Result<(), Vec<Diagnostic>> {
         let in_file = self.in_file.clone();
         // Resolve path deltas between working directory and entrypoint
         let base_directory = self.get_base_path()?;
@@ -56,14 +82,15 @@ impl Builder {
         let initial_directory = env::current_dir().expect("Current directory does not exist");
         if let Ok(resolved_delta) = in_file.strip_prefix(&base_directory) {
             // TODO: This error could probably be handled better
-            let _ = env::set_current_dir(base_directory);
+            let _ = env::set_current_dir(&base_directory);
             self.in_file = resolved_delta.to_path_buf();
+            self.base_directory = base_directory;
         }
         self.build_module(self.in_file.clone(), &mut Vec::new())?;
 
         // Append standard library
-        if matches!(target, Target::JS | Target::Qbe) {
-            self.build_stdlib()?;
+        if includes_stdlib(target) {
+            self.modules.append(&mut stdlib_modules(&mut self.sources)?);
         }
 
         // Change back to the initial directory
@@ -75,7 +102,7 @@ impl Builder {
         &mut self,
         file_path: PathBuf,
         seen: &mut Vec<String>,
-    ) -> Result<HModule, String> {
+    ) -> Result<HModule, Vec<Diagnostic>> {
         // TODO: This method can probably cleaned up quite a bit
 
         // In case the module is a directory, we have to append the filename of the entrypoint
@@ -84,21 +111,27 @@ impl Builder {
         } else {
             file_path
         };
-        let mut file = File::open(&resolved_file_path)
-            .map_err(|_| format!("Could not open file: {}", resolved_file_path.display()))?;
+        let mut file = File::open(&resolved_file_path).map_err(|_| {
+            Diagnostic::error(format!(
+                "Could not open file: {}",
+                resolved_file_path.display()
+            ))
+        })?;
         let mut contents = String::new();
 
         file.read_to_string(&mut contents)
             .expect("Could not read file");
-        let tokens = lexer::tokenize(&contents)?;
-        let module = parser::parse(tokens, Some(contents))?;
-        for import in &module.imports {
-            // Prevent circular imports
-            if seen.contains(import) {
-                continue;
-            } else {
-                seen.push(import.to_string());
-            }
+        let file_id = self.sources.add(
+            self.base_directory.join(&resolved_file_path),
+            contents.clone(),
+        );
+        self.files.insert(resolved_file_path.clone(), file_id);
+        let tokens = lexer::tokenize_file(&contents, file_id)?;
+        let (module, errors) = parser::parse_file(tokens, file_id, self.options.error_limit);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        for (import, span) in &module.imports {
             // Build module relative to the current file
             let mut import_path = resolved_file_path
                 .parent()
@@ -111,7 +144,18 @@ impl Builder {
                 import_path.set_extension("sb");
             }
 
-            self.build_module(import_path, seen)?;
+            // Prevent circular imports
+            if !seen.contains(import) {
+                seen.push(import.to_string());
+                self.build_module(import_path.clone(), seen)?;
+            }
+            if let Some(module) = self.files.get(&import_path) {
+                self.imports.push(parser::Import {
+                    path: import.clone(),
+                    span: *span,
+                    module: *module,
+                });
+            }
         }
         self.modules.push(module.clone());
         Ok(module)
@@ -121,17 +165,42 @@ impl Builder {
         &mut self,
         target: &Target,
         buffer: &mut Box<impl Write>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Vec<Diagnostic>> {
         let mut mod_iter = self.modules.iter();
 
         // TODO: We shouldn't clone here
-        let mut condensed = mod_iter.next().ok_or("No module specified")?.clone();
+        let mut condensed = mod_iter
+            .next()
+            .ok_or_else(|| Diagnostic::error("No module specified"))?
+            .clone();
         for module in mod_iter {
             condensed.merge_with(module.clone());
         }
 
-        // Infer types after all modules are merged so the full symbol table is available
-        parser::infer_types(&mut condensed);
+        // Resolve names and infer types after all modules are merged so the
+        // full symbol table is available
+        let stdlib = if includes_stdlib(target) {
+            Vec::new()
+        } else {
+            stdlib_modules(&mut SourceMap::default())?
+                .into_iter()
+                .flat_map(|module| module.func)
+                .map(|func| func.name)
+                .collect()
+        };
+        parser::check_duplicates(&condensed)?;
+        parser::resolve_names(&condensed, &stdlib)?;
+        parser::infer_types(&mut condensed)?;
+        parser::check_types(&condensed)?;
+        parser::check_flow(&condensed)?;
+
+        let files: HashSet<FileId> = self.files.values().copied().collect();
+        self.warnings = parser::lint(&condensed, &files, &self.imports, &self.options);
+        if self.warnings.iter().any(|w| w.severity == Severity::Error) {
+            return Err(std::mem::take(&mut self.warnings));
+        }
+
+        parser::rename_shadowed(&mut condensed);
 
         // Transform HAST to LLAST and then to Module for generators
         let module = AstTransformer::transform_module(condensed)?;
@@ -143,24 +212,43 @@ impl Builder {
         };
 
         buffer.write_all(output.as_bytes()).expect("write failed");
-        buffer.flush().map_err(|_| "Could not flush file".into())
+        buffer
+            .flush()
+            .map_err(|_| Diagnostic::error("Could not flush file").into())
     }
 
-    fn build_stdlib(&mut self) -> Result<(), String> {
-        let assets = Lib::iter();
-
-        for file in assets {
-            let stdlib_raw = Lib::get(&file)
-                .expect("Standard library not found. This should not occur.")
-                .data;
-            let stblib_str =
-                std::str::from_utf8(&stdlib_raw).expect("Could not interpret standard library.");
-            let stdlib_tokens = lexer::tokenize(stblib_str)?;
-            let module = parser::parse(stdlib_tokens, Some(stblib_str.into()))
-                .expect("Could not parse stdlib");
-            self.modules.push(module);
-        }
+    /// Problems found by lints that did not stop the build
+    pub(crate) fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
 
-        Ok(())
+    /// Renders diagnostics in the format of the options, with the source
+    /// code they point to
+    pub(crate) fn render(&self, diagnostics: &[Diagnostic]) -> String {
+        self.sources.report(diagnostics, self.options.format)
+    }
+}
+
+/// The C backend has no standard library yet
+fn includes_stdlib(target: &Target) -> bool {
+    matches!(target, Target::JS | Target::Qbe)
+}
+
+/// Parses the standard library. Its files are added to `sources` as `lib/<name>`.
+fn stdlib_modules(sources: &mut SourceMap) -> Result<Vec<HModule>, Diagnostic> {
+    let mut modules = Vec::new();
+    for file in Lib::iter() {
+        let stdlib_raw = Lib::get(&file)
+            .expect("Standard library not found. This should not occur.")
+            .data;
+        let stblib_str =
+            std::str::from_utf8(&stdlib_raw).expect("Could not interpret standard library.");
+        let file_id = sources.add(PathBuf::from("lib").join(file.as_ref()), stblib_str.into());
+        let stdlib_tokens = lexer::tokenize_file(stblib_str, file_id)?;
+        let (module, errors) =
+            parser::parse_file(stdlib_tokens, file_id, parser::DEFAULT_ERROR_LIMIT);
+        assert!(errors.is_empty(), "Could not parse stdlib");
+        modules.push(module);
     }
+    Ok(modules)
 }
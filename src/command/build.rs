@@ -14,34 +14,58 @@
  * limitations under the License.
  */
 use crate::builder;
+use crate::diagnostic::{self, Diagnostic, SourceMap};
 use crate::generator::Target;
 use std::fs::File;
 use std::io::stdout;
 use std::io::Write;
 use std::path::Path;
 
-pub fn build(target: &Target, in_file: &Path, out_file: &Path) -> Result<(), String> {
+pub fn build(
+    target: &Target,
+    in_file: &Path,
+    out_file: &Path,
+    options: &diagnostic::Options,
+) -> Result<(), String> {
     let mut buf = Box::<Vec<u8>>::default();
-    build_to_buffer(target, in_file, &mut buf)?;
+    build_to_buffer(target, in_file, &mut buf, options)?;
 
-    if out_file.to_str() == Some("-") {
+    let result = if out_file.to_str() == Some("-") {
         stdout()
             .write_all(&buf)
             .map_err(|e| format!("Could not write to stdout: {}", e))
     } else {
         File::create(out_file)
-            .map_err(|e| format!("Could not create output file: {}", e))?
-            .write_all(&buf)
-            .map_err(|e| format!("Could not write to file: {}", e))
-    }
+            .map_err(|e| format!("Could not create output file: {}", e))
+            .and_then(|mut file| {
+                file.write_all(&buf)
+                    .map_err(|e| format!("Could not write to file: {}", e))
+            })
+    };
+    result.map_err(|e| report(Diagnostic::error(e), options))
 }
 
+/// Builds `in_file` into `buf`. Diagnostics are returned rendered with the
+/// source code they point to.
 pub fn build_to_buffer(
     target: &Target,
     in_file: &Path,
     buf: &mut Box<impl Write>,
+    options: &diagnostic::Options,
 ) -> Result<(), String> {
-    let mut b = builder::Builder::new(in_file.to_path_buf());
-    b.build(target)?;
-    b.generate(target, buf)
+    let mut b = builder::Builder::new(in_file.to_path_buf()).with_options(options.clone());
+    match b.build(target).and_then(|_| b.generate(target, buf)) {
+        Ok(()) => {
+            if !b.warnings().is_empty() {
+                eprintln!("{}", b.render(b.warnings()));
+            }
+            Ok(())
+        }
+        Err(errors) => Err(b.render(&[b.warnings(), &errors].concat())),
+    }
+}
+
+/// Renders a diagnostic that does not point into a source file
+pub fn report(diagnostic: Diagnostic, options: &diagnostic::Options) -> String {
+    SourceMap::default().report(&[diagnostic], options.format)
 }
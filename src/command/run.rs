@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 use crate::command::build;
+use crate::diagnostic::{self, Diagnostic};
 use crate::generator::Target;
 use crate::Builtins;
 use std::fs::OpenOptions;
@@ -105,13 +106,19 @@ fn run_qbe(buf: Vec<u8>, in_file: &Path, args: &[String]) -> Result<()> {
     result
 }
 
-pub fn run(target: Target, in_file: PathBuf, args: Vec<String>) -> Result<()> {
+pub fn run(
+    target: Target,
+    in_file: PathBuf,
+    args: Vec<String>,
+    options: &diagnostic::Options,
+) -> Result<()> {
     let mut buf = Box::<Vec<u8>>::default();
-    build::build_to_buffer(&target, &in_file, &mut buf)?;
+    build::build_to_buffer(&target, &in_file, &mut buf, options)?;
 
-    match target {
+    let result = match target {
         Target::JS => run_node(&buf),
         Target::Qbe => run_qbe(*buf, &in_file, &args),
         _ => Err("Unsupported target".to_string()),
-    }
+    };
+    result.map_err(|e| build::report(Diagnostic::error(e), options))
 }
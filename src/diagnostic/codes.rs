@@ -0,0 +1,71 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+
+/// Rendered as `E` followed by four digits, e.g. `E0001`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Code(pub u16);
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{:04}", self.0)
+    }
+}
+
+/// Source that cannot be tokenized or parsed
+pub const SYNTAX: Code = Code(1);
+/// Use of a variable, function, struct or type that is not defined
+pub const UNKNOWN_NAME: Code = Code(2);
+/// Access to a field or method that a struct does not have
+pub const UNKNOWN_MEMBER: Code = Code(3);
+/// A value of one type is used where another type is required
+pub const MISMATCHED_TYPES: Code = Code(4);
+/// An operator, cast, index, loop or condition that does not apply to a type
+pub const INVALID_OPERATION: Code = Code(5);
+/// Call arguments that do not fit the parameters of a function
+pub const ARGUMENTS: Code = Code(6);
+/// Invalid definitions of structs and types
+pub const DEFINITION: Code = Code(7);
+/// A literal that does not fit into its type
+pub const LITERAL: Code = Code(8);
+/// A type that cannot be inferred
+pub const INFERENCE: Code = Code(9);
+/// A construct that the selected backend cannot generate
+pub const BACKEND: Code = Code(10);
+/// A function with a return type that can end without returning a value
+pub const MISSING_RETURN: Code = Code(11);
+/// A variable that is read before a value is assigned to it
+pub const UNASSIGNED: Code = Code(12);
+/// A function, struct, type, field or method that is defined more than once
+pub const DUPLICATE: Code = Code(13);
+/// An assignment to a variable, parameter or `self` that is not mutable
+pub const IMMUTABLE: Code = Code(14);
+/// A variable that is declared but never read
+pub const UNUSED_VARIABLE: Code = Code(15);
+/// A function parameter that is never read
+pub const UNUSED_PARAMETER: Code = Code(16);
+/// An imported module that nothing is used from
+pub const UNUSED_IMPORT: Code = Code(17);
+/// A function that is never called
+pub const UNUSED_FUNCTION: Code = Code(18);
+/// A statement after `return`, `break` or `continue`
+pub const UNREACHABLE_CODE: Code = Code(19);
+/// A variable that shadows a variable of an outer scope
+pub const SHADOWED_VARIABLE: Code = Code(20);
+/// A `match` arm that can never be chosen
+pub const UNREACHABLE_PATTERN: Code = Code(21);
+/// A `match` on a `bool` that does not cover both values
+pub const NON_EXHAUSTIVE_MATCH: Code = Code(22);
@@ -0,0 +1,136 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::{Diagnostic, Label, SourceMap};
+use crate::ast::span::Span;
+
+/// Fields of a JSON object, the values are already encoded
+type Fields = Vec<(&'static str, String)>;
+
+const NULL: &str = "null";
+
+impl SourceMap {
+    /// Formats a diagnostic as one line of JSON, for editors and CI
+    /// annotations. The location of the primary label is repeated at the
+    /// top level, see docs/developers/diagnostics.md for all fields.
+    pub fn render_json(&self, diagnostic: &Diagnostic) -> String {
+        let mut fields: Fields = vec![
+            ("severity", string(&diagnostic.severity.to_string())),
+            (
+                "code",
+                diagnostic
+                    .code
+                    .map_or(NULL.to_string(), |code| string(&code.to_string())),
+            ),
+            ("message", string(&diagnostic.message)),
+        ];
+        let primary = diagnostic
+            .primary
+            .as_ref()
+            .and_then(|label| self.range(label.span));
+        match primary {
+            Some(range) => fields.extend(range),
+            None => fields.extend(
+                ["file", "line", "column", "end_line", "end_column"]
+                    .iter()
+                    .map(|name| (*name, NULL.to_string())),
+            ),
+        }
+
+        let labels = diagnostic
+            .primary
+            .iter()
+            .map(|label| (label.as_ref(), true))
+            .chain(diagnostic.secondary.iter().map(|label| (label, false)))
+            .filter_map(|(label, primary)| self.label(label, primary))
+            .collect();
+        fields.push(("labels", array(labels)));
+        fields.push((
+            "notes",
+            array(diagnostic.notes.iter().map(|note| string(note)).collect()),
+        ));
+        fields.push((
+            "help",
+            diagnostic
+                .help
+                .as_ref()
+                .map_or(NULL.to_string(), |help| string(help)),
+        ));
+        let suggestions = diagnostic
+            .suggestion
+            .iter()
+            .filter_map(|suggestion| {
+                let mut fields = self.range(suggestion.span)?;
+                fields.push(("replacement", string(&suggestion.replacement)));
+                Some(object(fields))
+            })
+            .collect();
+        fields.push(("suggestions", array(suggestions)));
+        fields.push(("rendered", string(&self.render(diagnostic))));
+        object(fields)
+    }
+
+    fn label(&self, label: &Label, primary: bool) -> Option<String> {
+        let mut fields = self.range(label.span)?;
+        fields.push(("message", string(&label.message)));
+        fields.push(("primary", primary.to_string()));
+        Some(object(fields))
+    }
+
+    /// The file of a span and the 1-based lines and columns it starts and
+    /// ends at. The end is exclusive.
+    fn range(&self, span: Span) -> Option<Fields> {
+        let path = self.path(span.file)?;
+        let (line, column) = self.position(span.file, span.start)?;
+        let (end_line, end_column) = self.position(span.file, span.end.max(span.start))?;
+        Some(vec![
+            ("file", string(&path.display().to_string())),
+            ("line", line.to_string()),
+            ("column", column.to_string()),
+            ("end_line", end_line.to_string()),
+            ("end_column", end_column.to_string()),
+        ])
+    }
+}
+
+fn object(fields: Fields) -> String {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|(name, value)| format!("{}:{}", string(name), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn array(items: Vec<String>) -> String {
+    format!("[{}]", items.join(","))
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,248 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::ast::span::Span;
+use codes::Code;
+use std::fmt;
+use std::str::FromStr;
+
+/// Codes that identify the kind of a diagnostic. A code is never reused for
+/// a different kind of problem, see docs/developers/diagnostics.md.
+pub mod codes;
+mod json;
+mod render;
+#[cfg(test)]
+mod tests;
+
+pub use render::SourceMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A span of source code with a message that explains its part in a diagnostic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Code that fixes a problem when it replaces the source at `span`, so tools
+/// can apply the fix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A problem found in a program. Every phase of the compiler reports its
+/// errors as diagnostics, which are rendered in one place once the source
+/// files they point into are known, see `SourceMap::render`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem, see `codes`
+    pub code: Option<Code>,
+    pub message: String,
+    /// The code that caused the problem. Boxed to keep results that carry
+    /// a diagnostic small.
+    pub primary: Option<Box<Label>>,
+    /// Other code that is related to the problem, e.g. a previous definition
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    /// A suggestion how to fix the problem
+    pub help: Option<String>,
+    /// The fix from `help` as a replacement, if it is that precise
+    pub suggestion: Option<Box<Suggestion>>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            suggestion: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Points at the code that caused the problem
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.primary = Some(Box::new(Label {
+            span,
+            message: message.into(),
+        }));
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, span: Span, replacement: impl Into<String>) -> Self {
+        self.suggestion = Some(Box::new(Suggestion {
+            span,
+            replacement: replacement.into(),
+        }));
+        self
+    }
+
+    /// Points at `span` unless a more precise location is already known.
+    /// Used by callers that only know the statement or expression an error
+    /// was found in.
+    pub fn located_at(mut self, span: Span) -> Self {
+        if self.primary.is_none() {
+            self.primary = Some(Box::new(Label {
+                span,
+                message: String::new(),
+            }));
+        }
+        self
+    }
+}
+
+/// How diagnostics are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Together with the source code they point to, see `SourceMap::render`
+    Human,
+    /// One JSON object per line, see `SourceMap::render_json`
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(Format::Human),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("no error format {} found", s)),
+        }
+    }
+}
+
+/// What a lint does with the problems it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    /// Report them as errors, which stops the build
+    Deny,
+}
+
+/// Name of the group that contains every lint, e.g. `-D warnings`
+pub const ALL_LINTS: &str = "warnings";
+
+/// How problems are reported, set from the command line
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub format: Format,
+    /// Number of syntax errors after which parsing a file stops
+    pub error_limit: usize,
+    /// Levels of lints in the order they were given, e.g. by `-A` and `-D`
+    pub lints: Vec<(Level, String)>,
+}
+
+impl Options {
+    /// The level of a lint. The last level given for the lint or for all
+    /// lints wins, lints warn by default.
+    pub fn level(&self, lint: &str) -> Level {
+        self.lints
+            .iter()
+            .rev()
+            .find(|(_, name)| name == lint || name == ALL_LINTS)
+            .map_or(Level::Warn, |(level, _)| *level)
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            format: Format::Human,
+            error_limit: crate::parser::DEFAULT_ERROR_LIMIT,
+            lints: Vec::new(),
+        }
+    }
+}
+
+/// Errors that are not (yet) described in detail
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Diagnostic::error(message)
+    }
+}
+
+impl From<&str> for Diagnostic {
+    fn from(message: &str) -> Self {
+        Diagnostic::error(message)
+    }
+}
+
+/// Lets `?` report a single diagnostic from a phase that reports several
+impl From<Diagnostic> for Vec<Diagnostic> {
+    fn from(diagnostic: Diagnostic) -> Self {
+        vec![diagnostic]
+    }
+}
+
+/// Renders the diagnostic without its source code and location
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", SourceMap::default().render(self))
+    }
+}
@@ -0,0 +1,184 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::{Diagnostic, Format, Label};
+use crate::ast::span::{FileId, Span};
+use std::path::PathBuf;
+
+struct SourceFile {
+    path: PathBuf,
+    contents: String,
+}
+
+/// The source files of a build, a `FileId` is an index into them. Spans are
+/// turned into lines and columns here, when diagnostics are rendered.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+/// Where a span starts, and the source line it starts on
+struct Location<'a> {
+    path: &'a PathBuf,
+    /// 1-based line number
+    line: usize,
+    /// 1-based column, counted in characters
+    column: usize,
+    text: &'a str,
+    /// Number of characters of the line that are covered by the span
+    width: usize,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, path: PathBuf, contents: String) -> FileId {
+        self.files.push(SourceFile { path, contents });
+        FileId(self.files.len() - 1)
+    }
+
+    pub(super) fn path(&self, file: FileId) -> Option<&PathBuf> {
+        self.files.get(file.0).map(|file| &file.path)
+    }
+
+    /// 1-based line and column of a byte offset
+    pub(super) fn position(&self, file: FileId, offset: usize) -> Option<(usize, usize)> {
+        let contents = &self.files.get(file.0)?.contents;
+        let offset = offset.min(contents.len());
+        let line_start = contents[..offset].rfind('\n').map_or(0, |i| i + 1);
+        Some((
+            contents[..line_start].matches('\n').count() + 1,
+            contents[line_start..offset].chars().count() + 1,
+        ))
+    }
+
+    fn locate(&self, span: Span) -> Option<Location<'_>> {
+        let file = self.files.get(span.file.0)?;
+        let start = span.start.min(file.contents.len());
+        let line_start = file.contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = file.contents[start..]
+            .find('\n')
+            .map_or(file.contents.len(), |i| start + i);
+        let text = &file.contents[line_start..line_end];
+        let end = span.end.clamp(start, line_end);
+        Some(Location {
+            path: &file.path,
+            line: file.contents[..line_start].matches('\n').count() + 1,
+            column: file.contents[line_start..start].chars().count() + 1,
+            text,
+            width: file.contents[start..end].chars().count().max(1),
+        })
+    }
+
+    /// Formats a diagnostic the way it is shown to the user, e.g.
+    ///
+    /// ```text
+    /// error[E0002]: Unknown variable `cout`
+    ///  --> main.sb:2:13
+    ///   |
+    /// 2 |     println(cout)
+    ///   |             ^^^^ not found in this scope
+    ///   |
+    ///   = help: did you mean `count`?
+    /// ```
+    ///
+    /// Labels that point into files which are not part of the map are
+    /// left out.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = match diagnostic.code {
+            Some(code) => format!("{}[{}]: {}", diagnostic.severity, code, diagnostic.message),
+            None => format!("{}: {}", diagnostic.severity, diagnostic.message),
+        };
+
+        let labels: Vec<(&Label, Location, char)> = diagnostic
+            .primary
+            .iter()
+            .map(|label| (label.as_ref(), '^'))
+            .chain(diagnostic.secondary.iter().map(|label| (label, '-')))
+            .filter_map(|(label, marker)| Some((label, self.locate(label.span)?, marker)))
+            .collect();
+        let gutter = labels
+            .iter()
+            .map(|(_, location, _)| location.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(gutter);
+
+        let mut current_file = None;
+        for (label, location, marker) in &labels {
+            if current_file != Some(label.span.file) {
+                let arrow = if current_file.is_none() { "-->" } else { ":::" };
+                out.push_str(&format!(
+                    "\n{}{} {}:{}:{}",
+                    pad,
+                    arrow,
+                    location.path.display(),
+                    location.line,
+                    location.column
+                ));
+                current_file = Some(label.span.file);
+            }
+            // Tabs keep their width so the markers line up with the source
+            let indent: String = location
+                .text
+                .chars()
+                .take(location.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let markers = marker.to_string().repeat(location.width);
+            out.push_str(&format!("\n{} |", pad));
+            out.push_str(&format!(
+                "\n{:>width$} | {}",
+                location.line,
+                location.text,
+                width = gutter
+            ));
+            out.push_str(&format!("\n{} | {}{}", pad, indent, markers));
+            if !label.message.is_empty() {
+                out.push_str(&format!(" {}", label.message));
+            }
+        }
+
+        if !labels.is_empty() && (!diagnostic.notes.is_empty() || diagnostic.help.is_some()) {
+            out.push_str(&format!("\n{} |", pad));
+        }
+        for note in &diagnostic.notes {
+            out.push_str(&format!("\n{} = note: {}", pad, note));
+        }
+        if let Some(help) = &diagnostic.help {
+            out.push_str(&format!("\n{} = help: {}", pad, help));
+        }
+        out
+    }
+
+    /// Renders several diagnostics, separated by an empty line
+    pub fn render_all(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|diagnostic| self.render(diagnostic))
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+
+    /// Renders diagnostics in the given format
+    pub fn report(&self, diagnostics: &[Diagnostic], format: Format) -> String {
+        match format {
+            Format::Human => self.render_all(diagnostics),
+            Format::Json => diagnostics
+                .iter()
+                .map(|diagnostic| self.render_json(diagnostic))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::*;
+use crate::ast::span::FileId;
+use crate::lexer::tokenize_file;
+use crate::parser::{parse_file, DEFAULT_ERROR_LIMIT};
+use std::path::PathBuf;
+
+fn source_map(contents: &str) -> (SourceMap, FileId) {
+    let mut sources = SourceMap::default();
+    let file = sources.add(PathBuf::from("main.sb"), contents.to_string());
+    (sources, file)
+}
+
+#[test]
+fn test_render_with_source() {
+    let raw = "fn main() {\n    let count = 1\n    println(cout)\n}";
+    let (sources, file) = source_map(raw);
+    let start = raw.find("cout").unwrap();
+    let diagnostic = Diagnostic::error("Unknown variable `cout`")
+        .with_code(codes::UNKNOWN_NAME)
+        .with_label(Span::new(file, start, start + 4), "not found in this scope")
+        .with_help("did you mean `count`?");
+    assert_eq!(
+        sources.render(&diagnostic),
+        "error[E0002]: Unknown variable `cout`
+ --> main.sb:3:13
+  |
+3 |     println(cout)
+  |             ^^^^ not found in this scope
+  |
+  = help: did you mean `count`?"
+    );
+}
+
+#[test]
+fn test_render_secondary_label_and_note() {
+    let raw = "type A = B\ntype B = A";
+    let (sources, file) = source_map(raw);
+    let diagnostic = Diagnostic::warning("Type `A` is defined in terms of itself")
+        .with_label(Span::new(file, 0, 10), "")
+        .with_secondary(Span::new(file, 11, 21), "refers back to `A`")
+        .with_note("types are resolved before inference");
+    assert_eq!(
+        sources.render(&diagnostic),
+        "warning: Type `A` is defined in terms of itself
+ --> main.sb:1:1
+  |
+1 | type A = B
+  | ^^^^^^^^^^
+  |
+2 | type B = A
+  | ---------- refers back to `A`
+  |
+  = note: types are resolved before inference"
+    );
+}
+
+#[test]
+fn test_render_without_source() {
+    let diagnostic = Diagnostic::error("Could not open file: main.sb")
+        .with_label(Span::new(FileId(3), 0, 1), "not shown")
+        .with_help("check the path");
+    assert_eq!(
+        diagnostic.to_string(),
+        "error: Could not open file: main.sb\n = help: check the path"
+    );
+}
+
+#[test]
+fn test_render_json() {
+    let raw = "fn main() {\n    let count = 1\n    println(cout)\n}";
+    let (sources, file) = source_map(raw);
+    let start = raw.find("cout").unwrap();
+    let span = Span::new(file, start, start + 4);
+    let diagnostic = Diagnostic::error("Unknown variable `cout`")
+        .with_code(codes::UNKNOWN_NAME)
+        .with_label(span, "not found in this scope")
+        .with_help("did you mean `count`?")
+        .with_suggestion(span, "count");
+    let json = sources.render_json(&diagnostic);
+    assert!(!json.contains('\n'));
+    assert!(json.starts_with(
+        "{\"severity\":\"error\",\"code\":\"E0002\",\"message\":\"Unknown variable `cout`\",\
+         \"file\":\"main.sb\",\"line\":3,\"column\":13,\"end_line\":3,\"end_column\":17,\
+         \"labels\":[{\"file\":\"main.sb\",\"line\":3,\"column\":13,\"end_line\":3,\"end_column\":17,\
+         \"message\":\"not found in this scope\",\"primary\":true}],\"notes\":[],\
+         \"help\":\"did you mean `count`?\",\"suggestions\":[{\"file\":\"main.sb\",\"line\":3,\
+         \"column\":13,\"end_line\":3,\"end_column\":17,\"replacement\":\"count\"}],\
+         \"rendered\":\"error[E0002]: Unknown variable `cout`\\n --> main.sb:3:13\\n"
+    ));
+}
+
+#[test]
+fn test_render_json_without_source() {
+    let diagnostic = Diagnostic::error("Could not open file: \"main.sb\"").with_note("a\tb");
+    assert_eq!(
+        SourceMap::default().report(&[diagnostic.clone(), diagnostic], Format::Json),
+        [
+            "{\"severity\":\"error\",\"code\":null,\"message\":\"Could not open file: \\\"main.sb\\\"\",\
+             \"file\":null,\"line\":null,\"column\":null,\"end_line\":null,\"end_column\":null,\
+             \"labels\":[],\"notes\":[\"a\\tb\"],\"help\":null,\"suggestions\":[],\
+             \"rendered\":\"error: Could not open file: \\\"main.sb\\\"\\n = note: a\\tb\"}";
+            2
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn test_located_at_keeps_precise_label() {
+    let inner = Span::new(FileId(0), 4, 8);
+    let outer = Span::new(FileId(0), 0, 20);
+    let diagnostic = Diagnostic::error("error").with_label(inner, "here");
+    assert_eq!(diagnostic.located_at(outer).primary.unwrap().span, inner);
+    let diagnostic = Diagnostic::error("error").located_at(outer);
+    assert_eq!(diagnostic.primary.unwrap().span, outer);
+}
+
+#[test]
+fn test_syntax_errors_point_at_the_source() {
+    let raw = "fn main() {\n    let x = \"abc\n}";
+    let (sources, file) = source_map(raw);
+    let error = tokenize_file(raw, file).unwrap_err();
+    assert_eq!(error.code, Some(codes::SYNTAX));
+    assert!(sources.render(&error).contains(" --> main.sb:2:13"));
+
+    let raw = "fn main() {\n    let x = 1 +\n}";
+    let (sources, file) = source_map(raw);
+    let (_, errors) = parse_file(tokenize_file(raw, file).unwrap(), file, DEFAULT_ERROR_LIMIT);
+    let error = &errors[0];
+    assert_eq!(error.message, "Expected expression, found `}`");
+    assert!(sources.render(error).contains(" --> main.sb:3:1"));
+}
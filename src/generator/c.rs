@@ -60,11 +60,20 @@ pub(super) fn generate_arguments(args: Vec<Variable>) -> String {
 fn type_to_c_type(ty: &Option<Type>) -> String {
     match ty {
         Some(Type::Int) => "int".to_string(),
+        Some(Type::I8) => "int8_t".to_string(),
+        Some(Type::I16) => "int16_t".to_string(),
+        Some(Type::I32) => "int32_t".to_string(),
+        Some(Type::I64) => "int64_t".to_string(),
+        Some(Type::U8) => "uint8_t".to_string(),
+        Some(Type::U16) => "uint16_t".to_string(),
+        Some(Type::U32) => "uint32_t".to_string(),
+        Some(Type::U64) => "uint64_t".to_string(),
         Some(Type::Bool) => "bool".to_string(),
         Some(Type::Str) => "char*".to_string(),
         Some(Type::Array(inner, _)) => format!("{}*", type_to_c_type(&Some(*inner.clone()))),
         Some(Type::Struct(name)) => name.clone(),
         Some(Type::Any) => "void*".to_string(),
+        Some(Type::Newtype(_, inner)) => type_to_c_type(&Some(*inner.clone())),
         None => "void".to_string(),
     }
 }
@@ -106,33 +115,39 @@ pub(super) fn generate_struct_definition(struct_def: StructDef) -> String {
     }
     buf += &format!("}} {};\n\n", &struct_def.name);
 
-    // Generate method prototypes
+    // Methods are plain functions taking a pointer to the struct as their
+    // first argument, like in the QBE backend
     for method in &struct_def.methods {
-        let mut method_copy = method.clone();
-        // Add self parameter as first argument
-        let self_var = Variable {
-            name: "self".to_string(),
-            ty: Some(Type::Struct(struct_def.name.clone())),
-        };
-        method_copy.arguments.insert(0, self_var);
-        buf += &generate_function_prototype(&method_copy);
+        buf += &format!("{};\n", generate_method_signature(&struct_def.name, method));
     }
 
-    // Generate method implementations
-    for method in &struct_def.methods {
-        let mut method_copy = method.clone();
-        // Add self parameter as first argument
-        let self_var = Variable {
-            name: "self".to_string(),
-            ty: Some(Type::Struct(struct_def.name.clone())),
-        };
-        method_copy.arguments.insert(0, self_var);
-        buf += &generate_function(method_copy);
+    for method in struct_def.methods {
+        buf += &format!("{} ", generate_method_signature(&struct_def.name, &method));
+        buf += &generate_block(method.body, None);
+        buf += "\n";
     }
 
     buf
 }
 
+/// Only `mut self` methods can change the struct they are called on, so
+/// `self` points to a `const` struct in the others
+fn generate_method_signature(struct_name: &str, method: &Function) -> String {
+    let return_type = match &method.ret_type {
+        Some(ty) => type_to_c_type(&Some(ty.clone())),
+        None => "void".to_string(),
+    };
+    let qualifier = if method.mutable_self { "" } else { "const " };
+    let mut arguments = vec![format!("{}{} *self", qualifier, struct_name)];
+    arguments.extend(
+        method
+            .arguments
+            .iter()
+            .map(|var| format!("{} {}", type_to_c_type(&var.ty), var.name)),
+    );
+    format!("{} {}({})", return_type, method.name, arguments.join(", "))
+}
+
 pub(super) fn generate_block(block: Statement, prepend: Option<String>) -> String {
     let mut generated = String::from("{\n");
 
@@ -140,12 +155,12 @@ pub(super) fn generate_block(block: Statement, prepend: Option<String>) -> Strin
         generated += &pre;
     }
 
-    let statements = match block {
-        Statement::Block {
+    let statements = match block.kind {
+        StatementKind::Block {
             statements,
             scope: _,
         } => statements,
-        _ => panic!("Block body should be of type Statement::Block"),
+        _ => panic!("Block body should be of type StatementKind::Block"),
     };
 
     for statement in statements {
@@ -157,47 +172,47 @@ pub(super) fn generate_block(block: Statement, prepend: Option<String>) -> Strin
 }
 
 pub(super) fn generate_statement(statement: Statement) -> String {
-    let state = match statement {
-        Statement::Return(ret) => generate_return(ret),
-        Statement::Declare { variable, value } => generate_declare(variable, value),
-        Statement::Exp(val) => generate_expression(val),
-        Statement::If {
+    let state = match statement.kind {
+        StatementKind::Return(ret) => generate_return(ret),
+        StatementKind::Declare { variable, value } => generate_declare(variable, value),
+        StatementKind::Exp(val) => generate_expression(val),
+        StatementKind::If {
             condition,
             body,
             else_branch,
         } => generate_conditional(condition, *body, else_branch.map(|x| *x)),
-        Statement::Assign { lhs, rhs } => generate_assign(*lhs, *rhs),
-        Statement::Block {
-            statements: _,
-            scope: _,
-        } => return generate_block(statement, None),
-        Statement::While { condition, body } => generate_while_loop(condition, *body),
-        Statement::For { ident, expr, body } => generate_for_loop(ident, expr, *body),
-        Statement::Continue => "continue".to_string(),
-        Statement::Break => "break".to_string(),
+        StatementKind::Assign { lhs, rhs } => generate_assign(*lhs, *rhs),
+        kind @ StatementKind::Block { .. } => {
+            return generate_block(Statement::new(kind, statement.span), None)
+        }
+        StatementKind::While { condition, body } => generate_while_loop(condition, *body),
+        StatementKind::For { ident, expr, body } => generate_for_loop(ident, expr, *body),
+        StatementKind::Continue => "continue".to_string(),
+        StatementKind::Break => "break".to_string(),
     };
 
     format!("    {};\n", state)
 }
 
 pub(super) fn generate_expression(expr: Expression) -> String {
-    match expr {
-        Expression::Int(val) => val.to_string(),
-        Expression::Selff => "self".to_string(),
-        Expression::Str(val) => format!("\"{}\"", val.replace("\"", "\\\"")),
-        Expression::Variable(val) => val,
-        Expression::Bool(b) => if b { "true" } else { "false" }.to_string(),
-        Expression::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
-        Expression::Array {
+    match expr.kind {
+        ExpressionKind::Int(val) => val.to_string(),
+        ExpressionKind::Selff => "(*self)".to_string(),
+        ExpressionKind::Str(val) => format!("\"{}\"", val.replace("\"", "\\\"")),
+        ExpressionKind::Variable(val) => val,
+        ExpressionKind::Bool(b) => if b { "true" } else { "false" }.to_string(),
+        ExpressionKind::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
+        ExpressionKind::Array {
             capacity: _,
             elements,
         } => generate_array(elements),
-        Expression::ArrayAccess { name, index } => generate_array_access(name, *index),
-        Expression::BinOp { lhs, op, rhs } => generate_bin_op(*lhs, op, *rhs),
-        Expression::StructInitialization { name, fields } => {
+        ExpressionKind::ArrayAccess { expr, index } => generate_array_access(*expr, *index),
+        ExpressionKind::BinOp { lhs, op, rhs, ty } => generate_bin_op(*lhs, op, *rhs, ty),
+        ExpressionKind::StructInitialization { name, fields } => {
             generate_struct_initialization(name, fields)
         }
-        Expression::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+        ExpressionKind::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+        ExpressionKind::Cast { expr, from, to } => generate_cast(*expr, from, to),
     }
 }
 
@@ -230,21 +245,83 @@ pub(super) fn generate_for_loop(ident: Variable, expr: Expression, body: Stateme
     out_str
 }
 
+/// Array literals are compound literals, e.g. `(int[]){1, 2}`, which
+/// decay to a pointer to their first element like the arrays of the
+/// program. The element type is taken from the first element.
 pub(super) fn generate_array(elements: Vec<Expression>) -> String {
-    let mut out_str = String::from("{");
+    generate_compound_literal(elements, None, false)
+}
+
+/// A value that outlives the current function, because it is returned or
+/// stored in a struct. Compound literals live on the stack, so array
+/// literals are copied to the heap instead.
+fn generate_escaping(expr: Expression) -> String {
+    match expr.kind {
+        ExpressionKind::Array { elements, .. } => generate_compound_literal(elements, None, true),
+        _ => generate_expression(expr),
+    }
+}
 
-    out_str += &elements
-        .iter()
-        .map(|el| generate_expression(el.clone()))
+fn generate_compound_literal(elements: Vec<Expression>, ty: Option<Type>, heap: bool) -> String {
+    let ty = ty.or_else(|| elements.first().and_then(literal_type));
+    let len = elements.len();
+    let initializer = generate_initializer(elements, ty.clone(), heap);
+    match ty {
+        Some(ty) if heap => {
+            let c_type = type_to_c_type(&Some(ty));
+            format!(
+                "_heap_array(({}[]){}, {} * sizeof({}))",
+                c_type, initializer, len, c_type
+            )
+        }
+        Some(ty) => format!("({}[]){}", type_to_c_type(&Some(ty)), initializer),
+        None => initializer,
+    }
+}
+
+/// The braced elements of an array of `ty`. Nested arrays are compound
+/// literals, because braces alone cannot initialize a pointer.
+fn generate_initializer(elements: Vec<Expression>, ty: Option<Type>, heap: bool) -> String {
+    let inner = match ty {
+        Some(Type::Array(inner, _)) => Some(*inner),
+        _ => None,
+    };
+    let elements = elements
+        .into_iter()
+        .map(|el| match el.kind {
+            ExpressionKind::Array { elements, .. } => {
+                generate_compound_literal(elements, inner.clone(), heap)
+            }
+            _ => generate_expression(el),
+        })
         .collect::<Vec<String>>()
         .join(", ");
+    format!("{{{}}}", elements)
+}
 
-    out_str += "}";
-    out_str
+/// The type of a literal, as far as it can be told without the types of
+/// variables
+fn literal_type(expr: &Expression) -> Option<Type> {
+    match &expr.kind {
+        ExpressionKind::Int(_) => Some(Type::Int),
+        ExpressionKind::Str(_) => Some(Type::Str),
+        ExpressionKind::Bool(_) => Some(Type::Bool),
+        ExpressionKind::StructInitialization { name, .. } => Some(Type::Struct(name.clone())),
+        ExpressionKind::Cast { to, .. } => Some(to.clone()),
+        ExpressionKind::Array { elements, .. } => Some(Type::Array(
+            Box::new(literal_type(elements.first()?)?),
+            None,
+        )),
+        _ => None,
+    }
 }
 
-pub(super) fn generate_array_access(name: String, expr: Expression) -> String {
-    format!("{}[{}]", name, generate_expression(expr))
+pub(super) fn generate_array_access(expr: Expression, index: Expression) -> String {
+    format!(
+        "{}[{}]",
+        generate_expression(expr),
+        generate_expression(index)
+    )
 }
 
 pub(super) fn generate_conditional(
@@ -270,19 +347,29 @@ pub(super) fn generate_declare<V: AsRef<Variable>>(
     let ident = identifier.as_ref();
     let type_str = type_to_c_type(&ident.ty);
 
-    match val {
-        Some(expr) => format!(
+    match (val, &ident.ty) {
+        // An array that is declared with a literal is a fixed-size array,
+        // so `sizeof` tells its length
+        (
+            Some(Expression {
+                kind: ExpressionKind::Array { elements, .. },
+                ..
+            }),
+            Some(Type::Array(inner, _)),
+        ) => format!(
+            "{} {}[] = {}",
+            type_to_c_type(&Some(*inner.clone())),
+            ident.name,
+            generate_initializer(elements, Some(*inner.clone()), false)
+        ),
+        (Some(expr), _) => format!(
             "{} {} = {}",
             type_str,
             ident.name,
             generate_expression(expr)
         ),
-        None => match &ident.ty {
-            Some(Type::Array(_, _)) => {
-                format!("{} {}[]", type_str, ident.name)
-            }
-            _ => format!("{} {}", type_str, ident.name),
-        },
+        (None, Some(Type::Array(_, _))) => format!("{} {}[]", type_str, ident.name),
+        (None, _) => format!("{} {}", type_str, ident.name),
     }
 }
 
@@ -298,12 +385,17 @@ pub(super) fn generate_function_call(func: String, args: Vec<Expression>) -> Str
 
 pub(super) fn generate_return(ret: Option<Expression>) -> String {
     match ret {
-        Some(expr) => format!("return {}", generate_expression(expr)),
+        Some(expr) => format!("return {}", generate_escaping(expr)),
         None => "return".to_string(),
     }
 }
 
-pub(super) fn generate_bin_op(left: Expression, op: BinOp, right: Expression) -> String {
+pub(super) fn generate_bin_op(
+    left: Expression,
+    op: BinOp,
+    right: Expression,
+    ty: Option<Type>,
+) -> String {
     let op_str = match op {
         BinOp::Addition => "+",
         BinOp::And => "&&",
@@ -323,13 +415,69 @@ pub(super) fn generate_bin_op(left: Expression, op: BinOp, right: Expression) ->
         BinOp::MultiplyAssign => "*=",
         BinOp::DivideAssign => "/=",
     };
+    // Literals are never negative
+    let literal = matches!(right.kind, ExpressionKind::Int(_));
+    let l = generate_expression(left);
+    let r = generate_expression(right);
+    let ty = match ty.filter(Type::is_integer) {
+        Some(ty) => ty,
+        None => return format!("{} {} {}", l, op_str, r),
+    };
 
-    format!(
-        "{} {} {}",
-        generate_expression(left),
-        op_str,
-        generate_expression(right)
-    )
+    let (op, compound) = match op {
+        BinOp::AddAssign => ("+", true),
+        BinOp::SubtractAssign => ("-", true),
+        BinOp::MultiplyAssign => ("*", true),
+        BinOp::DivideAssign => ("/", true),
+        _ => (op_str, false),
+    };
+    let c_type = type_to_c_type(&Some(ty.clone()));
+    let wide = ty.int_bits() == Some(64);
+    // Signed overflow is undefined in C, so integers are added, subtracted
+    // and multiplied as unsigned values, which wrap around
+    let unsigned = match wide {
+        true => "uint64_t",
+        false => "uint32_t",
+    };
+    // Dividing the smallest signed value by -1 traps, so signed integers
+    // are divided as 64-bit values, or by a builtin that checks for -1
+    let signed_division = matches!(op, "/" | "%") && !ty.is_unsigned() && !literal;
+    match (op, compound) {
+        // The target is evaluated once, so `a[next()] += 1` calls `next`
+        // once. It is changed through the unsigned type of the same width.
+        ("+" | "-" | "*", true) => format!(
+            "*({}*)&({}) {}= ({})({})",
+            unsigned_c_type(&ty),
+            l,
+            op,
+            unsigned,
+            r
+        ),
+        ("/", true) if signed_division && wide => format!("_div_assign_i64(&({}), {})", l, r),
+        (_, true) if signed_division => format!("{} {}= (int64_t)({})", l, op, r),
+        (_, true) => format!("{} {}= {}", l, op, r),
+        ("+" | "-" | "*", false) => format!(
+            "(({})(({u})({}) {} ({u})({})))",
+            c_type,
+            l,
+            op,
+            r,
+            u = unsigned
+        ),
+        ("/", false) if signed_division => format!("(({})_div_i64({}, {}))", c_type, l, r),
+        (_, false) if signed_division => format!("(({})_rem_i64({}, {}))", c_type, l, r),
+        _ => format!("(({})({} {} {}))", c_type, l, op, r),
+    }
+}
+
+/// The unsigned integer type with the width of `ty`
+fn unsigned_c_type(ty: &Type) -> &'static str {
+    match ty.int_bits() {
+        Some(8) => "uint8_t",
+        Some(16) => "uint16_t",
+        Some(64) => "uint64_t",
+        _ => "uint32_t",
+    }
 }
 
 pub(super) fn generate_struct_initialization(
@@ -340,7 +488,7 @@ pub(super) fn generate_struct_initialization(
 
     let field_inits: Vec<String> = fields
         .into_iter()
-        .map(|(key, value)| format!(".{} = {}", key, generate_expression(*value)))
+        .map(|(key, value)| format!(".{} = {}", key, generate_escaping(*value)))
         .collect();
 
     out_str += &field_inits.join(", ");
@@ -350,14 +498,66 @@ pub(super) fn generate_struct_initialization(
 }
 
 pub(super) fn generate_field_access(expr: Expression, field: Expression) -> String {
-    // In C, we use -> for pointer access and . for direct access
-    // For simplicity, we'll use . here, but in a real implementation
-    // you'd need to check if expr is a pointer
-    format!(
-        "{}.{}",
-        generate_expression(expr),
-        generate_expression(field)
-    )
+    if let ExpressionKind::FunctionCall { fn_name, args } = field.kind {
+        let mut arguments = vec![generate_receiver(expr)];
+        arguments.extend(args.into_iter().map(generate_expression));
+        return format!("{}({})", fn_name, arguments.join(", "));
+    }
+    match expr.kind {
+        ExpressionKind::Selff => format!("self->{}", generate_expression(field)),
+        _ => format!(
+            "{}.{}",
+            generate_expression(expr),
+            generate_expression(field)
+        ),
+    }
+}
+
+/// The address of the struct a method is called on. Values that are not
+/// stored anywhere, such as the result of a call, are copied into a
+/// compound literal first.
+fn generate_receiver(expr: Expression) -> String {
+    if let ExpressionKind::Selff = expr.kind {
+        return "self".to_string();
+    }
+    if is_place(&expr) {
+        return format!("&{}", generate_expression(expr));
+    }
+    let value = generate_expression(expr);
+    format!("(__typeof__({0})[]){{{0}}}", value)
+}
+
+/// Whether an expression refers to a stored value, whose address can be taken
+fn is_place(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Variable(_)
+        | ExpressionKind::Selff
+        | ExpressionKind::ArrayAccess { .. } => true,
+        ExpressionKind::FieldAccess { expr, field } => {
+            !matches!(field.kind, ExpressionKind::FunctionCall { .. }) && is_place(expr)
+        }
+        _ => false,
+    }
+}
+
+pub(super) fn generate_cast(expr: Expression, from: Type, to: Type) -> String {
+    let value = generate_expression(expr);
+    let c_type = type_to_c_type(&Some(to.clone()));
+    match (from, to) {
+        // `any` is a pointer, which integers are stored in
+        (from, Type::Any) if from.is_integer() || from == Type::Bool => {
+            format!("((void*)(intptr_t)({}))", value)
+        }
+        (_, Type::Any) => format!("((void*)({}))", value),
+        (Type::U64, Type::Str) => format!("_uint_to_str({})", value),
+        (from, Type::Str) if from.is_integer() => format!("_int_to_str({})", value),
+        (Type::Bool, Type::Str) => format!("({} ? \"true\" : \"false\")", value),
+        (Type::Str, Type::Int) => format!("_parse_int({})", value),
+        (Type::Str, _) => format!("(({})_parse_long({}))", c_type, value),
+        (Type::Bool, _) => format!("(({})({} ? 1 : 0))", c_type, value),
+        (_, Type::Bool) => format!("({} != 0)", value),
+        _ => format!("(({})({}))", c_type, value),
+    }
 }
 
 pub(super) fn generate_assign(name: Expression, expr: Expression) -> String {
@@ -110,12 +110,12 @@ fn generate_block(block: Statement, prepend: Option<String>) -> String {
     }
 
     // TODO: Prepend statements
-    let statements = match block {
-        Statement::Block {
+    let statements = match block.kind {
+        StatementKind::Block {
             statements,
             scope: _,
         } => statements,
-        _ => panic!("Block body should be of type Statement::Block"),
+        _ => panic!("Block body should be of type StatementKind::Block"),
     };
 
     for statement in statements {
@@ -128,47 +128,47 @@ fn generate_block(block: Statement, prepend: Option<String>) -> String {
 }
 
 fn generate_statement(statement: Statement) -> String {
-    let state = match statement {
-        Statement::Return(ret) => generate_return(ret),
-        Statement::Declare { variable, value } => generate_declare(variable, value),
-        Statement::Exp(val) => generate_expression(val),
-        Statement::If {
+    let state = match statement.kind {
+        StatementKind::Return(ret) => generate_return(ret),
+        StatementKind::Declare { variable, value } => generate_declare(variable, value),
+        StatementKind::Exp(val) => generate_expression(val),
+        StatementKind::If {
             condition,
             body,
             else_branch,
         } => generate_conditional(condition, *body, else_branch.map(|x| *x)),
-        Statement::Assign { lhs, rhs } => generate_assign(*lhs, *rhs),
-        Statement::Block {
-            statements: _,
-            scope: _,
-        } => generate_block(statement, None),
-        Statement::While { condition, body } => generate_while_loop(condition, *body),
-        Statement::For { ident, expr, body } => generate_for_loop(ident, expr, *body),
-        Statement::Continue => generate_continue(),
-        Statement::Break => generate_break(),
+        StatementKind::Assign { lhs, rhs } => generate_assign(*lhs, *rhs),
+        kind @ StatementKind::Block { .. } => {
+            generate_block(Statement::new(kind, statement.span), None)
+        }
+        StatementKind::While { condition, body } => generate_while_loop(condition, *body),
+        StatementKind::For { ident, expr, body } => generate_for_loop(ident, expr, *body),
+        StatementKind::Continue => generate_continue(),
+        StatementKind::Break => generate_break(),
     };
 
     format!("{};\n", state)
 }
 
 fn generate_expression(expr: Expression) -> String {
-    match expr {
-        Expression::Int(val) => val.to_string(),
-        Expression::Selff => "this".to_string(),
-        Expression::Str(val) => super::string_syntax(val),
-        Expression::Variable(val) => val,
-        Expression::Bool(b) => b.to_string(),
-        Expression::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
-        Expression::Array {
+    match expr.kind {
+        ExpressionKind::Int(val) => val.to_string(),
+        ExpressionKind::Selff => "this".to_string(),
+        ExpressionKind::Str(val) => super::string_syntax(val),
+        ExpressionKind::Variable(val) => val,
+        ExpressionKind::Bool(b) => b.to_string(),
+        ExpressionKind::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
+        ExpressionKind::Array {
             capacity: _,
             elements,
         } => generate_array(elements),
-        Expression::ArrayAccess { name, index } => generate_array_access(name, *index),
-        Expression::BinOp { lhs, op, rhs } => generate_bin_op(*lhs, op, *rhs),
-        Expression::StructInitialization { name, fields } => {
+        ExpressionKind::ArrayAccess { expr, index } => generate_array_access(*expr, *index),
+        ExpressionKind::BinOp { lhs, op, rhs, ty } => generate_bin_op(*lhs, op, *rhs, ty),
+        ExpressionKind::StructInitialization { name, fields } => {
             generate_struct_initialization(name, fields)
         }
-        Expression::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+        ExpressionKind::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+        ExpressionKind::Cast { expr, from, to } => generate_cast(*expr, from, to),
     }
 }
 
@@ -227,8 +227,12 @@ fn generate_array(elements: Vec<Expression>) -> String {
     out_str
 }
 
-fn generate_array_access(name: String, expr: Expression) -> String {
-    format!("{n}[{e}]", n = name, e = generate_expression(expr))
+fn generate_array_access(expr: Expression, index: Expression) -> String {
+    format!(
+        "{e}[{i}]",
+        e = generate_expression(expr),
+        i = generate_expression(index)
+    )
 }
 
 fn generate_conditional(
@@ -238,8 +242,8 @@ fn generate_conditional(
 ) -> String {
     let expr_str = generate_expression(expr);
 
-    let body = match if_state {
-        Statement::Block {
+    let body = match if_state.kind {
+        StatementKind::Block {
             statements,
             scope: _,
         } => statements,
@@ -264,23 +268,23 @@ fn generate_conditional(
 fn generate_declare<V: AsRef<Variable>>(identifier: V, val: Option<Expression>) -> String {
     // AsRef prevents unnecessary cloning here
     let ident = identifier.as_ref();
-    // var is used here to not collide with scopes.
-    // TODO: Can let be used instead?
+    // Variables have unique names within a function, so the block scoping
+    // of `let` matches the scoping of the source
     match val {
-        Some(expr) => format!("var {} = {}", ident.name, generate_expression(expr)),
+        Some(expr) => format!("let {} = {}", ident.name, generate_expression(expr)),
         None => match ident.ty {
             // Accessing an array that has not been initialized will throw an error,
             // So we have to initialize it as an empty array.
             //
             // This crashes:
-            // var x;
+            // let x;
             // x[0] = 1;
             //
             // But this works:
-            // var x = [];
+            // let x = [];
             // x[0] = 1;
-            Some(Type::Array(_, _)) => format!("var {} = []", ident.name),
-            _ => format!("var {}", ident.name),
+            Some(Type::Array(_, _)) => format!("let {} = []", ident.name),
+            _ => format!("let {}", ident.name),
         },
     }
 }
@@ -288,23 +292,24 @@ fn generate_declare<V: AsRef<Variable>>(identifier: V, val: Option<Expression>)
 fn generate_function_call(func: String, args: Vec<Expression>) -> String {
     let formatted_args = args
         .into_iter()
-        .map(|arg| match arg {
-            Expression::Int(i) => i.to_string(),
-            Expression::Bool(v) => v.to_string(),
-            Expression::Selff => "this".to_string(),
-            Expression::ArrayAccess { name, index } => generate_array_access(name, *index),
-            Expression::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
-            Expression::Str(s) => super::string_syntax(s),
-            Expression::Variable(s) => s,
-            Expression::Array {
+        .map(|arg| match arg.kind {
+            ExpressionKind::Int(i) => i.to_string(),
+            ExpressionKind::Bool(v) => v.to_string(),
+            ExpressionKind::Selff => "this".to_string(),
+            ExpressionKind::ArrayAccess { expr, index } => generate_array_access(*expr, *index),
+            ExpressionKind::FunctionCall { fn_name, args } => generate_function_call(fn_name, args),
+            ExpressionKind::Str(s) => super::string_syntax(s),
+            ExpressionKind::Variable(s) => s,
+            ExpressionKind::Array {
                 capacity: _,
                 elements,
             } => generate_array(elements),
-            Expression::BinOp { lhs, op, rhs } => generate_bin_op(*lhs, op, *rhs),
-            Expression::StructInitialization { name, fields } => {
+            ExpressionKind::BinOp { lhs, op, rhs, ty } => generate_bin_op(*lhs, op, *rhs, ty),
+            ExpressionKind::StructInitialization { name, fields } => {
                 generate_struct_initialization(name, fields)
             }
-            Expression::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+            ExpressionKind::FieldAccess { expr, field } => generate_field_access(*expr, *field),
+            ExpressionKind::Cast { expr, from, to } => generate_cast(*expr, from, to),
         })
         .collect::<Vec<String>>()
         .join(",");
@@ -318,7 +323,7 @@ fn generate_return(ret: Option<Expression>) -> String {
     }
 }
 
-fn generate_bin_op(left: Expression, op: BinOp, right: Expression) -> String {
+fn generate_bin_op(left: Expression, op: BinOp, right: Expression, ty: Option<Type>) -> String {
     let op_str = match op {
         BinOp::Addition => "+",
         BinOp::And => "&&",
@@ -338,12 +343,74 @@ fn generate_bin_op(left: Expression, op: BinOp, right: Expression) -> String {
         BinOp::MultiplyAssign => "*=",
         BinOp::DivideAssign => "/=",
     };
-    format!(
-        "{l} {op} {r}",
-        l = generate_expression(left),
-        op = op_str,
-        r = generate_expression(right)
-    )
+    let r = generate_expression(right);
+    let ty = match ty.filter(Type::is_integer) {
+        Some(ty) => ty,
+        None => {
+            let l = generate_expression(left);
+            return format!("{l} {op} {r}", l = l, op = op_str, r = r);
+        }
+    };
+    // Integers wrap around on overflow and division truncates, so the result
+    // is truncated to the width of its type. `x += 1` becomes `x = wrap(x + 1)`.
+    let (op, compound) = match op {
+        BinOp::AddAssign => ("+", true),
+        BinOp::SubtractAssign => ("-", true),
+        BinOp::MultiplyAssign => ("*", true),
+        BinOp::DivideAssign => ("/", true),
+        _ => (op_str, false),
+    };
+    let value = |l: &str| {
+        // A 32-bit product can exceed the 53 bits a number holds exactly
+        let value = match (op, ty.int_bits()) {
+            ("*", Some(32)) => format!("Math.imul({}, {})", l, r),
+            _ => format!("({} {} {})", l, op, r),
+        };
+        wrap_integer(&ty, value)
+    };
+    if !compound || is_simple(&left) {
+        let l = generate_expression(left);
+        return match compound {
+            true => format!("{} = {}", l, value(&l)),
+            false => value(&l),
+        };
+    }
+    // The target is evaluated once, so `a[next()] += 1` calls `next` once.
+    // `$` can not appear in names of the source.
+    match left.kind {
+        ExpressionKind::ArrayAccess { expr, index } => format!(
+            "(($a, $i) => $a[$i] = {})({}, {})",
+            value("$a[$i]"),
+            generate_expression(*expr),
+            generate_expression(*index)
+        ),
+        ExpressionKind::FieldAccess { expr, field } => {
+            let field = generate_expression(*field);
+            format!(
+                "(($o) => $o.{} = {})({})",
+                field,
+                value(&format!("$o.{}", field)),
+                generate_expression(*expr)
+            )
+        }
+        _ => {
+            let l = generate_expression(left);
+            format!("{} = {}", l, value(&l))
+        }
+    }
+}
+
+/// Whether `expr` can be evaluated twice without changing the result or
+/// repeating a side effect
+fn is_simple(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Variable(_) | ExpressionKind::Selff | ExpressionKind::Int(_) => true,
+        ExpressionKind::FieldAccess { expr, field } => {
+            is_simple(expr) && matches!(field.kind, ExpressionKind::Variable(_))
+        }
+        ExpressionKind::ArrayAccess { expr, index } => is_simple(expr) && is_simple(index),
+        _ => false,
+    }
 }
 
 fn generate_struct_initialization(
@@ -368,6 +435,53 @@ fn generate_field_access(expr: Expression, field: Expression) -> String {
     )
 }
 
+/// 64-bit integers are represented as `BigInt`, every other integer type as
+/// a number that is truncated to the width of its type
+fn generate_cast(expr: Expression, from: Type, to: Type) -> String {
+    let wide = |ty: &Type| ty.int_bits() == Some(64);
+    if let (ExpressionKind::Int(literal), true) = (&expr.kind, wide(&to)) {
+        return format!("{}n", literal);
+    }
+    let value = generate_expression(expr);
+    match (from, to) {
+        (_, Type::Any) => value,
+        (from, Type::Str) if from.is_integer() || from == Type::Bool => {
+            format!("String({})", value)
+        }
+        (Type::Str, to) if wide(&to) => wrap_integer(&to, format!("_parse_long({})", value)),
+        (Type::Str, Type::Int) => format!("_parse_int({})", value),
+        (Type::Str, to) => wrap_integer(&to, format!("_parse_int({})", value)),
+        (Type::Bool, to) if wide(&to) => format!("({} ? 1n : 0n)", value),
+        (Type::Bool, _) => format!("({} ? 1 : 0)", value),
+        (from, Type::Bool) if wide(&from) => format!("({} !== 0n)", value),
+        (_, Type::Bool) => format!("({} !== 0)", value),
+        (from, to) if wide(&from) && !wide(&to) => {
+            let bits = to.int_bits().unwrap_or(32);
+            match to.is_unsigned() {
+                true => format!("Number(BigInt.asUintN({}, {}))", bits, value),
+                false => format!("Number(BigInt.asIntN({}, {}))", bits, value),
+            }
+        }
+        (from, to) if !wide(&from) && wide(&to) => wrap_integer(&to, format!("BigInt({})", value)),
+        (_, to) => wrap_integer(&to, value),
+    }
+}
+
+/// Truncates an integer to the range of the given type
+fn wrap_integer(ty: &Type, value: String) -> String {
+    match ty {
+        Type::I8 => format!("({} << 24 >> 24)", value),
+        Type::U8 => format!("({} & 255)", value),
+        Type::I16 => format!("({} << 16 >> 16)", value),
+        Type::U16 => format!("({} & 65535)", value),
+        Type::Int | Type::I32 => format!("({} | 0)", value),
+        Type::U32 => format!("({} >>> 0)", value),
+        Type::I64 => format!("BigInt.asIntN(64, {})", value),
+        Type::U64 => format!("BigInt.asUintN(64, {})", value),
+        _ => value,
+    }
+}
+
 fn generate_assign(name: Expression, expr: Expression) -> String {
     format!(
         "{} = {}",
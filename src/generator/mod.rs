@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 use crate::ast::*;
+use crate::diagnostic::{codes, Diagnostic};
 use std::path;
 use std::str::FromStr;
 
@@ -60,7 +61,12 @@ impl FromStr for Target {
     }
 }
 
-pub type GeneratorResult<T> = Result<T, String>;
+pub type GeneratorResult<T> = Result<T, Diagnostic>;
+
+/// A program that the generator cannot translate
+pub fn backend_error(message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(message).with_code(codes::BACKEND)
+}
 
 pub trait Generator {
     fn generate(prog: Module) -> GeneratorResult<String>;
@@ -13,7 +13,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use super::{Generator, GeneratorResult};
+use super::{backend_error, Generator, GeneratorResult};
 use crate::ast::types::Type;
 use crate::ast::*;
 use std::cmp;
@@ -28,6 +28,7 @@ use std::sync::Arc;
 /// * `_exit`      – flush all stdio streams then terminate via `_Exit`
 /// * `_strlen`    – thin word-width wrapper around libc `strlen`
 /// * `_parse_int` – thin wrapper around libc `atoi`
+/// * `_parse_long` – thin wrapper around libc `atol`, used for 64-bit casts
 ///
 /// `_str_concat`, `_int_to_str`, and `_read_line` still live in
 /// `builtin_qbe.c` because they need `malloc`/`snprintf`/`fgets` logic that
@@ -64,6 +65,13 @@ export function w $_parse_int(l %s) {
     ret %n
 }
 
+# _parse_long(s: l): l — atol wrapper
+export function l $_parse_long(l %s) {
+@start
+    %n =l call $atol(l %s)
+    ret %n
+}
+
 # _strcmp(a: l, b: l): w — compare two C strings via libc strcmp
 export function w $_strcmp(l %a, l %b) {
 @start
@@ -93,6 +101,18 @@ export function w $_fclose(l %fp) {
     ret %r
 }
 
+# _bool_to_str(b: w): l — "true" or "false" as a C string
+data $__true_str = { b "true", b 0 }
+data $__false_str = { b "false", b 0 }
+export function l $_bool_to_str(w %b) {
+@start
+    jnz %b, @true, @false
+@true
+    ret $__true_str
+@false
+    ret $__false_str
+}
+
 # Global storage for argc/argv (stashed at main entry)
 data $__argc = { w 0 }
 data $__argv = { l 0 }
@@ -146,103 +166,22 @@ pub struct QbeGenerator {
     fn_param_types: HashMap<String, Vec<qbe::Type>>,
     /// Function name -> AST return type (populated by pre-pass before codegen)
     fn_ast_signatures: HashMap<String, Option<Type>>,
-    /// Function name -> AST parameter types (populated by pre-pass before codegen)
-    fn_param_ast_types: HashMap<String, Vec<Option<Type>>>,
     /// Functions replaced by inline intrinsics
     intrinsics: HashMap<String, Intrinsic>,
     /// Module being built
     module: qbe::Module,
 }
 
-/// Mapping of field -> (type, offset, ast_type)
-type StructMeta = HashMap<String, (qbe::Type, u64, Option<Type>)>;
-
-/// Infer the return type of a function from its body when no annotation is present.
-/// Scans declarations and parameters for typed variables, then looks for a matching return statement.
-///
-/// TODO: This logic belongs in `src/parser/infer.rs`, which should populate
-/// `HFunction::ret_type` so all backends get inferred return types for free.
-/// Once that's done, remove these helpers and the `fn_ast_signatures` pre-pass.
-fn infer_fn_return_type(body: &Statement, args: &[Variable]) -> Option<Type> {
-    let mut var_types: HashMap<String, Type> = HashMap::new();
-    for arg in args {
-        if let Some(ty) = &arg.ty {
-            var_types.insert(arg.name.clone(), ty.clone());
-        }
-    }
-    collect_decl_types(body, &mut var_types);
-    find_return_type(body, &var_types)
-}
-
-fn collect_decl_types(stmt: &Statement, out: &mut HashMap<String, Type>) {
-    match stmt {
-        Statement::Block { statements, .. } => {
-            for s in statements {
-                collect_decl_types(s, out);
-            }
-        }
-        Statement::Declare { variable, .. } => {
-            if let Some(ty) = &variable.ty {
-                out.insert(variable.name.clone(), ty.clone());
-            }
-        }
-        _ => {}
-    }
-}
-
-fn find_return_type(stmt: &Statement, var_types: &HashMap<String, Type>) -> Option<Type> {
-    match stmt {
-        Statement::Block { statements, .. } => {
-            for s in statements {
-                if let Some(ty) = find_return_type(s, var_types) {
-                    return Some(ty);
-                }
-            }
-            None
-        }
-        Statement::Return(Some(expr)) => infer_expr_type(expr, var_types),
-        _ => None,
-    }
-}
-
-fn infer_expr_type(expr: &Expression, var_types: &HashMap<String, Type>) -> Option<Type> {
-    match expr {
-        Expression::Int(_) => Some(Type::Int),
-        Expression::Str(_) => Some(Type::Str),
-        Expression::Bool(_) => Some(Type::Bool),
-        Expression::Variable(name) => var_types.get(name).cloned(),
-        Expression::StructInitialization { name, .. } => Some(Type::Struct(name.clone())),
-        Expression::BinOp { lhs, op, .. } => {
-            if matches!(op, BinOp::Addition) && is_string_expr_static(lhs, var_types) {
-                return Some(Type::Str);
-            }
-            match op {
-                BinOp::Equal
-                | BinOp::NotEqual
-                | BinOp::LessThan
-                | BinOp::LessThanOrEqual
-                | BinOp::GreaterThan
-                | BinOp::GreaterThanOrEqual
-                | BinOp::And
-                | BinOp::Or => Some(Type::Bool),
-                _ => Some(Type::Int),
-            }
-        }
-        Expression::FunctionCall { .. } => None,
-        _ => None,
-    }
+/// A location that can be assigned to
+enum Place {
+    /// A variable, held in a temporary of the given type
+    Variable(qbe::Type, qbe::Value),
+    /// A pointer to memory that stores a value of the given type
+    Memory(qbe::Type, qbe::Value),
 }
 
-fn is_string_expr_static(expr: &Expression, var_types: &HashMap<String, Type>) -> bool {
-    match expr {
-        Expression::Str(_) => true,
-        Expression::Variable(name) => matches!(var_types.get(name), Some(Type::Str)),
-        Expression::BinOp { lhs, op, .. } => {
-            matches!(op, BinOp::Addition) && is_string_expr_static(lhs, var_types)
-        }
-        _ => false,
-    }
-}
+/// Mapping of field -> (type, offset, ast_type)
+type StructMeta = HashMap<String, (qbe::Type, u64, Option<Type>)>;
 
 impl Generator for QbeGenerator {
     fn generate(prog: Module) -> GeneratorResult<String> {
@@ -259,7 +198,6 @@ impl Generator for QbeGenerator {
             fn_signatures: HashMap::new(),
             fn_param_types: HashMap::new(),
             fn_ast_signatures: HashMap::new(),
-            fn_param_ast_types: HashMap::new(),
             intrinsics,
             module: qbe::Module::new(),
         };
@@ -306,14 +244,18 @@ impl Generator for QbeGenerator {
                 if qbe_ret_ty == qbe::Type::Word {
                     continue;
                 }
-                if let Statement::Block { statements, .. } = &func.body {
-                    if let [Statement::Return(Some(Expression::FunctionCall { fn_name, .. }))] =
-                        statements.as_slice()
+                if let StatementKind::Block { statements, .. } = &func.body.kind {
+                    if let [Statement {
+                        kind: StatementKind::Return(Some(value)),
+                        ..
+                    }] = statements.as_slice()
                     {
-                        if fn_name.starts_with('_') {
-                            generator
-                                .fn_signatures
-                                .insert(fn_name.clone(), Some(qbe_ret_ty));
+                        if let ExpressionKind::FunctionCall { fn_name, .. } = &value.kind {
+                            if fn_name.starts_with('_') {
+                                generator
+                                    .fn_signatures
+                                    .insert(fn_name.clone(), Some(qbe_ret_ty));
+                            }
                         }
                     }
                 }
@@ -322,11 +264,7 @@ impl Generator for QbeGenerator {
 
         // Pre-pass: collect function return types so callers know what type to expect
         for func in &prog.func {
-            let ast_ret = func
-                .ret_type
-                .clone()
-                .or_else(|| infer_fn_return_type(&func.body, &func.arguments));
-            let ret_type = match &ast_ret {
+            let ret_type = match &func.ret_type {
                 Some(Type::Struct(_)) => Some(qbe::Type::Long),
                 Some(ty) => Some(generator.get_type(ty.to_owned())?.into_abi()),
                 None => None,
@@ -334,23 +272,22 @@ impl Generator for QbeGenerator {
             generator.fn_signatures.insert(func.name.clone(), ret_type);
             generator
                 .fn_ast_signatures
-                .insert(func.name.clone(), ast_ret);
+                .insert(func.name.clone(), func.ret_type.clone());
 
             let param_types: Vec<qbe::Type> = func
                 .arguments
                 .iter()
-                .filter_map(|arg| arg.ty.as_ref())
-                .map(|ty| generator.get_type(ty.to_owned()).map(|t| t.into_abi()))
+                .map(|arg| {
+                    let ty = arg
+                        .ty
+                        .as_ref()
+                        .ok_or(backend_error("Function arguments must have a type"))?;
+                    generator.get_type(ty.to_owned()).map(|t| t.into_abi())
+                })
                 .collect::<Result<Vec<_>, _>>()?;
             generator
                 .fn_param_types
                 .insert(func.name.clone(), param_types);
-
-            let param_ast_types: Vec<Option<Type>> =
-                func.arguments.iter().map(|arg| arg.ty.clone()).collect();
-            generator
-                .fn_param_ast_types
-                .insert(func.name.clone(), param_ast_types);
         }
 
         // Pre-pass: collect method return types
@@ -362,19 +299,26 @@ impl Generator for QbeGenerator {
                 } else {
                     None
                 };
-                generator.fn_signatures.insert(mangled, ret_type);
+                generator.fn_signatures.insert(mangled.clone(), ret_type);
+                generator
+                    .fn_ast_signatures
+                    .insert(mangled, method.ret_type.clone());
             }
         }
 
         for func in &prog.func {
-            let func = generator.generate_function(func)?;
+            let func = generator
+                .generate_function(func)
+                .map_err(|e| e.located_at(func.span))?;
             generator.module.add_function(func);
         }
 
         // Generate methods as standalone functions: StructName__methodName(self: Long, ...)
         for def in &prog.structs {
             for method in &def.methods {
-                let qfunc = generator.generate_method(def, method)?;
+                let qfunc = generator
+                    .generate_method(def, method)
+                    .map_err(|e| e.located_at(method.span))?;
                 generator.module.add_function(qfunc);
             }
         }
@@ -448,13 +392,14 @@ impl QbeGenerator {
         let mut max_align = 1_u64;
 
         for field in &def.fields {
-            let ty = self.get_type(
-                field
-                    .ty
-                    .as_ref()
-                    .ok_or_else(|| "Structure field must have a type".to_owned())?
-                    .to_owned(),
-            )?;
+            let field_ty = field
+                .ty
+                .as_ref()
+                .ok_or_else(|| backend_error("Structure field must have a type"))?;
+            let ty = match Self::sub_word_type(field_ty) {
+                Some(ty) => ty,
+                None => self.get_type(field_ty.to_owned())?,
+            };
 
             let field_align = self.type_alignment(&ty);
             max_align = cmp::max(max_align, field_align);
@@ -463,7 +408,7 @@ impl QbeGenerator {
             offset = self.align_offset(offset, field_align);
 
             meta.insert(field.name.clone(), (ty.clone(), offset, field.ty.clone()));
-            items.push((ty.clone(), 1));
+            items.push((Self::typedef_item(&ty), 1));
 
             offset += self.type_size(&ty);
         }
@@ -496,7 +441,7 @@ impl QbeGenerator {
             let ast_type = arg
                 .ty
                 .as_ref()
-                .ok_or("Function arguments must have a type")?
+                .ok_or(backend_error("Function arguments must have a type"))?
                 .to_owned();
             let ty = self.get_type(ast_type.clone())?;
             let tmp = self.new_var(&ty, &arg.name, Some(ast_type))?;
@@ -504,23 +449,18 @@ impl QbeGenerator {
             arguments.push((ty.into_abi(), tmp));
         }
 
-        let effective_ret = func
-            .ret_type
-            .clone()
-            .or_else(|| infer_fn_return_type(&func.body, &func.arguments));
-
         // Special-case `main`: when the source declared no return type, the
         // OS-level exit code must still be deterministic. We declare the QBE
         // function with Word return and force `ret 0` on every implicit and
         // explicit-no-value return path. Without this, main()'s exit code is
         // whatever was in w0 from the last call (typically `printf`'s
         // byte-count return).
-        let is_void_main = func.name == "main" && effective_ret.is_none();
+        let is_void_main = func.name == "main" && func.ret_type.is_none();
 
         let return_ty = if is_void_main {
             Some(qbe::Type::Word)
         } else {
-            match &effective_ret {
+            match &func.ret_type {
                 Some(Type::Struct(_)) => Some(qbe::Type::Long),
                 Some(ty) => Some(self.get_type(ty.to_owned())?.into_abi()),
                 None => None,
@@ -579,8 +519,8 @@ impl QbeGenerator {
             }
         }
 
-        // Check if the function properly returns
-        let last_block_empty = qfunc.blocks.last().is_some_and(|b| b.items.is_empty());
+        // Functions with a return type return on every path, which
+        // `parser::check_flow` verifies before code generation
         let returns = qfunc.blocks.last().is_some_and(|b| {
             b.items.last().is_some_and(|item| {
                 matches!(
@@ -595,16 +535,11 @@ impl QbeGenerator {
                 // Void main needs a deterministic 0 exit code on the
                 // fall-through path.
                 qfunc.add_instr(qbe::Instr::Ret(Some(qbe::Value::Const(0))));
-            } else if func.ret_type.is_none() || last_block_empty {
+            } else {
                 // For void functions, add an implicit return.
-                // For typed functions where the last block is empty, all
-                // reachable paths already return — add a dummy ret for QBE.
+                // For typed functions the end is unreachable, but QBE
+                // still needs every block to end with a jump or return.
                 qfunc.add_instr(qbe::Instr::Ret(None));
-            } else {
-                return Err(format!(
-                    "Function '{}' does not return in all code paths",
-                    &func.name
-                ));
             }
         }
 
@@ -634,7 +569,7 @@ impl QbeGenerator {
             let ast_type = arg
                 .ty
                 .as_ref()
-                .ok_or("Method arguments must have a type")?
+                .ok_or(backend_error("Method arguments must have a type"))?
                 .to_owned();
             let ty = self.get_type(ast_type.clone())?;
             let tmp = self.new_var(&ty, &arg.name, Some(ast_type))?;
@@ -658,7 +593,6 @@ impl QbeGenerator {
         qfunc.add_block("start".to_owned());
         self.generate_statement(&mut qfunc, &method.body)?;
 
-        let last_block_empty = qfunc.blocks.last().is_some_and(|b| b.items.is_empty());
         let returns = qfunc.blocks.last().is_some_and(|b| {
             b.items.last().is_some_and(|item| {
                 matches!(
@@ -669,20 +603,27 @@ impl QbeGenerator {
         });
 
         if !returns {
-            if method.ret_type.is_none() || last_block_empty {
-                qfunc.add_instr(qbe::Instr::Ret(None));
-            } else {
-                return Err(format!(
-                    "Method '{}' does not return in all code paths",
-                    mangled_name
-                ));
-            }
+            qfunc.add_instr(qbe::Instr::Ret(None));
         }
 
         self.scopes.pop();
         Ok(qfunc)
     }
 
+    /// Generates a function or method argument. Array literals are passed
+    /// by reference, like arrays held in a variable, instead of by value.
+    fn generate_argument(
+        &mut self,
+        func: &mut qbe::Function,
+        arg: &Expression,
+    ) -> GeneratorResult<(qbe::Type, qbe::Value)> {
+        let (ty, value) = self.generate_expression(func, arg)?;
+        match &arg.kind {
+            ExpressionKind::Array { .. } => Ok((qbe::Type::Long, value)),
+            _ => Ok((ty, value)),
+        }
+    }
+
     /// Generates a method call: `obj.method(args)` → `call StructName__methodName(obj_ptr, args...)`
     fn generate_method_call(
         &mut self,
@@ -696,7 +637,7 @@ impl QbeGenerator {
 
         let mut call_args: Vec<(qbe::Type, qbe::Value)> = vec![(qbe::Type::Long, obj_ptr)];
         for arg in args {
-            let result = self.generate_expression(func, arg)?;
+            let result = self.generate_argument(func, arg)?;
             call_args.push(result);
         }
 
@@ -729,22 +670,25 @@ impl QbeGenerator {
         func: &mut qbe::Function,
         stmt: &Statement,
     ) -> GeneratorResult<()> {
-        match stmt {
-            Statement::Block {
+        match &stmt.kind {
+            StatementKind::Block {
                 statements,
                 scope: _,
             } => {
                 self.scopes.push(HashMap::new());
                 for stmt in statements.iter() {
-                    self.generate_statement(func, stmt)?;
+                    self.generate_statement(func, stmt)
+                        .map_err(|e| e.located_at(stmt.span))?;
                 }
                 self.scopes.pop();
             }
-            Statement::Declare { variable, value } => {
+            StatementKind::Declare { variable, value } => {
                 let ast_type = variable
                     .ty
                     .as_ref()
-                    .ok_or_else(|| format!("Missing type for variable '{}'", &variable.name))?
+                    .ok_or_else(|| {
+                        backend_error(format!("Missing type for variable '{}'", &variable.name))
+                    })?
                     .to_owned();
                 let ty = self.get_type(ast_type.clone())?;
                 let tmp = self.new_var(&ty, &variable.name, Some(ast_type.clone()))?;
@@ -752,14 +696,14 @@ impl QbeGenerator {
                 if let Some(expr) = value {
                     let (expr_type, expr_value) = self.generate_expression(func, expr)?;
                     func.assign_instr(tmp, expr_type, qbe::Instr::Copy(expr_value));
-                } else if let Type::Array(ref elem_ast_type, Some(size)) = variable
-                    .ty
-                    .as_ref()
-                    .ok_or_else(|| format!("Missing type for variable '{}'", &variable.name))?
+                } else if let Type::Array(ref elem_ast_type, Some(size)) =
+                    variable.ty.as_ref().ok_or_else(|| {
+                        backend_error(format!("Missing type for variable '{}'", &variable.name))
+                    })?
                 {
                     // Uninitialized sized arrays need memory allocated upfront,
                     // since subsequent index assignments use the variable as a base pointer.
-                    let elem_qbe_type = self.get_type(*elem_ast_type.clone())?;
+                    let elem_qbe_type = self.get_elem_type(elem_ast_type)?;
                     let elem_size = self.type_size(&elem_qbe_type);
                     let total_size = 8 + (*size as u64) * elem_size;
 
@@ -776,52 +720,55 @@ impl QbeGenerator {
                     let typedef = qbe::TypeDef::Regular {
                         ident: name.clone(),
                         align: None,
-                        items: vec![(qbe::Type::Long, 1), (elem_qbe_type, *size)],
+                        items: vec![
+                            (qbe::Type::Long, 1),
+                            (Self::typedef_item(&elem_qbe_type), *size),
+                        ],
                     };
                     let typedef_arc = Arc::new(typedef);
                     self.module.add_type(Arc::clone(&typedef_arc));
                     self.typedefs.push(typedef_arc);
                 }
             }
-            Statement::Assign { lhs, rhs } => {
+            StatementKind::Assign { lhs, rhs } => {
                 let (_, rhs_value) = self.generate_expression(func, rhs)?;
                 self.generate_assignment(func, lhs, rhs_value)?;
             }
-            Statement::Return(val) => match val {
+            StatementKind::Return(val) => match val {
                 Some(expr) => {
                     let (_, result) = self.generate_expression(func, expr)?;
                     func.add_instr(qbe::Instr::Ret(Some(result)));
                 }
                 None => func.add_instr(qbe::Instr::Ret(None)),
             },
-            Statement::If {
+            StatementKind::If {
                 condition,
                 body,
                 else_branch,
             } => {
                 self.generate_if(func, condition, body, else_branch)?;
             }
-            Statement::While { condition, body } => {
+            StatementKind::While { condition, body } => {
                 self.generate_while(func, condition, body)?;
             }
-            Statement::Break => {
+            StatementKind::Break => {
                 if let Some(label) = &self.loop_labels.last() {
                     func.add_instr(qbe::Instr::Jmp(format!("{}.end", label)));
                 } else {
-                    return Err("break used outside of a loop".to_owned());
+                    return Err(backend_error("break used outside of a loop"));
                 }
             }
-            Statement::Continue => {
+            StatementKind::Continue => {
                 if let Some(label) = &self.loop_labels.last() {
                     func.add_instr(qbe::Instr::Jmp(format!("{}.cond", label)));
                 } else {
-                    return Err("continue used outside of a loop".to_owned());
+                    return Err(backend_error("continue used outside of a loop"));
                 }
             }
-            Statement::For { ident, expr, body } => {
+            StatementKind::For { ident, expr, body } => {
                 self.generate_for_loop(func, ident, expr, body)?;
             }
-            Statement::Exp(expr) => {
+            StatementKind::Exp(expr) => {
                 self.generate_expression(func, expr)?;
             }
         }
@@ -839,7 +786,7 @@ impl QbeGenerator {
             Intrinsic::ArrayLen => {
                 let arr_arg = args
                     .first()
-                    .ok_or_else(|| "len() requires one argument".to_string())?;
+                    .ok_or_else(|| backend_error("len() requires one argument"))?;
                 let (_, arr_ptr) = self.generate_expression(func, arr_arg)?;
 
                 // Load length (Long) from array header at offset 0
@@ -865,8 +812,8 @@ impl QbeGenerator {
         func: &mut qbe::Function,
         expr: &Expression,
     ) -> GeneratorResult<(qbe::Type, qbe::Value)> {
-        match expr {
-            Expression::Int(literal) => {
+        match &expr.kind {
+            ExpressionKind::Int(literal) => {
                 let tmp = self.new_temporary();
                 func.assign_instr(
                     tmp.clone(),
@@ -876,8 +823,8 @@ impl QbeGenerator {
 
                 Ok((qbe::Type::Word, tmp))
             }
-            Expression::Str(string) => self.generate_string(string),
-            Expression::Bool(literal) => {
+            ExpressionKind::Str(string) => self.generate_string(string),
+            ExpressionKind::Bool(literal) => {
                 let tmp = self.new_temporary();
                 func.assign_instr(
                     tmp.clone(),
@@ -887,10 +834,10 @@ impl QbeGenerator {
 
                 Ok((qbe::Type::Word, tmp))
             }
-            Expression::Array { capacity, elements } => {
+            ExpressionKind::Array { capacity, elements } => {
                 self.generate_array(func, *capacity, elements)
             }
-            Expression::FunctionCall { fn_name, args } => {
+            ExpressionKind::FunctionCall { fn_name, args } => {
                 if let Some(intrinsic) = self.intrinsics.get(fn_name).cloned() {
                     return self.generate_intrinsic(func, &intrinsic, args);
                 }
@@ -898,36 +845,14 @@ impl QbeGenerator {
                 // Collect arguments first to avoid multiple mutable borrows
                 let mut arg_results = Vec::new();
                 for arg in args.iter() {
-                    let result = self.generate_expression(func, arg)?;
+                    let result = self.generate_argument(func, arg)?;
                     arg_results.push(result);
                 }
 
                 // Widen arguments if the callee expects a larger type (e.g. Type::Any → Long)
                 let param_types_opt = self.fn_param_types.get(fn_name).cloned();
-                let param_ast_types_opt = self.fn_param_ast_types.get(fn_name).cloned();
                 let mut new_args: Vec<(qbe::Type, qbe::Value)> = Vec::new();
                 for (i, (arg_ty, arg_val)) in arg_results.into_iter().enumerate() {
-                    // int → string coercion: call _int_to_str when passing an int to a string param
-                    if arg_ty == qbe::Type::Word {
-                        let param_ast_ty = param_ast_types_opt
-                            .as_ref()
-                            .and_then(|v| v.get(i))
-                            .and_then(|t| t.as_ref());
-                        if matches!(param_ast_ty, Some(Type::Str)) {
-                            let str_tmp = self.new_temporary();
-                            func.assign_instr(
-                                str_tmp.clone(),
-                                qbe::Type::Long,
-                                qbe::Instr::Call(
-                                    "_int_to_str".to_string(),
-                                    vec![(qbe::Type::Word, arg_val)],
-                                    None,
-                                ),
-                            );
-                            new_args.push((qbe::Type::Long, str_tmp));
-                            continue;
-                        }
-                    }
                     if let Some(ref param_types) = param_types_opt {
                         if let Some(param_ty) = param_types.get(i) {
                             if *param_ty == qbe::Type::Long && arg_ty == qbe::Type::Word {
@@ -977,80 +902,101 @@ impl QbeGenerator {
                     Ok((ret_type, tmp))
                 }
             }
-            Expression::Variable(name) => {
+            ExpressionKind::Variable(name) => {
                 let (ty, val, _) = self.get_var(name)?;
                 Ok((ty.to_owned(), val.to_owned()))
             }
-            Expression::Selff => {
+            ExpressionKind::Selff => {
                 let (ty, val, _) = self.get_var("self")?;
                 Ok((ty.to_owned(), val.to_owned()))
             }
-            Expression::BinOp { lhs, op, rhs } => self.generate_binop(func, lhs, op, rhs),
-            Expression::StructInitialization { name, fields } => {
+            ExpressionKind::BinOp { lhs, op, rhs, .. } => self.generate_binop(func, lhs, op, rhs),
+            ExpressionKind::StructInitialization { name, fields } => {
                 self.generate_struct_init(func, name, fields)
             }
-            Expression::FieldAccess { expr, field } => {
-                if let Expression::FunctionCall { fn_name, args } = field.as_ref() {
+            ExpressionKind::FieldAccess { expr, field } => {
+                if let ExpressionKind::FunctionCall { fn_name, args } = &field.kind {
                     self.generate_method_call(func, expr, fn_name, args)
                 } else {
                     self.generate_field_access(func, expr, field)
                 }
             }
-            Expression::ArrayAccess { name, index } => {
-                // Clone to avoid borrow conflicts with the later generate_expression call
-                let (_, base, ast_type) = self.get_var(name)?.clone();
-                let elem_ast_type = match ast_type {
-                    Some(Type::Array(inner, _)) => *inner,
-                    _ => return Err(format!("'{}' is not an array", name)),
-                };
-                let elem_qbe_type = self.get_type(elem_ast_type)?;
-                let elem_size = self.type_size(&elem_qbe_type);
-
-                let (_, idx_val) = self.generate_expression(func, index)?;
-
-                // Sign-extend Word index to Long for pointer arithmetic
-                let idx_long = self.new_temporary();
-                func.assign_instr(
-                    idx_long.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Extsw(idx_val),
-                );
-
-                // scaled = index * elem_size
-                let scaled = self.new_temporary();
-                func.assign_instr(
-                    scaled.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Mul(idx_long, qbe::Value::Const(elem_size)),
-                );
-
-                // elem_ptr = base + 8 + scaled
-                let with_header = self.new_temporary();
-                func.assign_instr(
-                    with_header.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Add(scaled, qbe::Value::Const(8)),
-                );
-                let elem_ptr = self.new_temporary();
-                func.assign_instr(
-                    elem_ptr.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Add(base, with_header),
-                );
+            ExpressionKind::Cast { expr, from, to } => self.generate_cast(func, expr, from, to),
+            ExpressionKind::ArrayAccess { expr, index } => {
+                let (elem_ast_type, elem_qbe_type, elem_ptr) =
+                    self.generate_element_ptr(func, expr, index)?;
 
                 // Load and return the element
                 let result = self.new_temporary();
                 func.assign_instr(
                     result.clone(),
-                    elem_qbe_type.clone(),
+                    elem_qbe_type.clone().into_abi(),
                     qbe::Instr::Load(elem_qbe_type.clone(), elem_ptr),
                 );
 
-                Ok((elem_qbe_type, result))
+                // Struct elements are stored as pointers, but callers expect
+                // struct values to carry their aggregate type
+                let ty = match elem_ast_type {
+                    Type::Struct(_) => self.get_type(elem_ast_type)?,
+                    _ => elem_qbe_type.into_abi(),
+                };
+
+                Ok((ty, result))
             }
         }
     }
 
+    /// Computes the address of `expr[index]`, returning the AST type, the
+    /// QBE storage type and a pointer to the element
+    fn generate_element_ptr(
+        &mut self,
+        func: &mut qbe::Function,
+        expr: &Expression,
+        index: &Expression,
+    ) -> GeneratorResult<(Type, qbe::Type, qbe::Value)> {
+        let elem_ast_type = match self.expr_ast_type(expr) {
+            Some(Type::Array(inner, _)) => *inner,
+            _ => return Err(backend_error(format!("'{:?}' is not an array", expr))),
+        };
+        let elem_qbe_type = self.get_elem_type(&elem_ast_type)?;
+        let elem_size = self.type_size(&elem_qbe_type);
+
+        let (_, base) = self.generate_expression(func, expr)?;
+        let (_, idx_val) = self.generate_expression(func, index)?;
+
+        // Sign-extend Word index to Long for pointer arithmetic
+        let idx_long = self.new_temporary();
+        func.assign_instr(
+            idx_long.clone(),
+            qbe::Type::Long,
+            qbe::Instr::Extsw(idx_val),
+        );
+
+        // scaled = index * elem_size
+        let scaled = self.new_temporary();
+        func.assign_instr(
+            scaled.clone(),
+            qbe::Type::Long,
+            qbe::Instr::Mul(idx_long, qbe::Value::Const(elem_size)),
+        );
+
+        // elem_ptr = base + 8 + scaled
+        let with_header = self.new_temporary();
+        func.assign_instr(
+            with_header.clone(),
+            qbe::Type::Long,
+            qbe::Instr::Add(scaled, qbe::Value::Const(8)),
+        );
+        let elem_ptr = self.new_temporary();
+        func.assign_instr(
+            elem_ptr.clone(),
+            qbe::Type::Long,
+            qbe::Instr::Add(base, with_header),
+        );
+
+        Ok((elem_ast_type, elem_qbe_type, elem_ptr))
+    }
+
     /// Generates an `if` statement
     fn generate_if(
         &mut self,
@@ -1183,7 +1129,24 @@ impl QbeGenerator {
         op: &BinOp,
         rhs: &Expression,
     ) -> GeneratorResult<(qbe::Type, qbe::Value)> {
-        let (lhs_ty, lhs_val) = self.generate_expression(func, lhs)?;
+        if matches!(op, BinOp::And | BinOp::Or) {
+            return self.generate_logical_op(func, lhs, op, rhs);
+        }
+
+        let compound = matches!(
+            op,
+            BinOp::AddAssign | BinOp::SubtractAssign | BinOp::MultiplyAssign | BinOp::DivideAssign
+        );
+        // The target of `a[next()] += 1` is evaluated once, so `next` is
+        // called once
+        let place = match compound {
+            true => Some(self.generate_place(func, lhs)?),
+            false => None,
+        };
+        let (lhs_ty, lhs_val) = match &place {
+            Some(place) => self.load_place(func, place),
+            None => self.generate_expression(func, lhs)?,
+        };
         let (rhs_ty, rhs_val) = self.generate_expression(func, rhs)?;
         let tmp = self.new_temporary();
 
@@ -1209,9 +1172,8 @@ impl QbeGenerator {
             );
 
             // Handle AddAssign for strings
-            if matches!(op, BinOp::AddAssign) {
-                let tmp_clone = tmp.clone();
-                self.generate_assignment(func, lhs, tmp_clone)?;
+            if let Some(place) = &place {
+                Self::store_place(func, place, tmp.clone());
             }
 
             return Ok((qbe::Type::Long, tmp));
@@ -1247,6 +1209,12 @@ impl QbeGenerator {
 
         // Use the wider of the two operand types for the result
         let ty = Self::wider_type(&lhs_ty, &rhs_ty);
+        let ast_ty = self
+            .expr_ast_type(lhs)
+            .or_else(|| self.expr_ast_type(rhs))
+            .map(Type::erased)
+            .unwrap_or(Type::Int);
+        let unsigned = ast_ty.is_unsigned();
 
         // Widen operands if needed (e.g. Byte → Word via extub)
         let lhs_val = if lhs_ty == qbe::Type::Byte && ty != qbe::Type::Byte {
@@ -1272,6 +1240,20 @@ impl QbeGenerator {
             rhs_val
         };
 
+        // Dividing the smallest signed value by -1 traps. A divisor of -1 is
+        // replaced by 1 and the quotient is negated, which wraps around.
+        // Literals are never negative.
+        let signed_division = matches!(op, BinOp::Division | BinOp::DivideAssign | BinOp::Modulus)
+            && !unsigned
+            && !matches!(rhs.kind, ExpressionKind::Int(_));
+        let (rhs_val, sign) = match signed_division {
+            true => {
+                let (divisor, sign) = self.guard_divisor(func, &ty, rhs_val);
+                (divisor, Some(sign))
+            }
+            false => (rhs_val, None),
+        };
+
         func.assign_instr(
             tmp.clone(),
             ty.clone(),
@@ -1279,16 +1261,21 @@ impl QbeGenerator {
                 BinOp::Addition | BinOp::AddAssign => qbe::Instr::Add(lhs_val, rhs_val),
                 BinOp::Subtraction | BinOp::SubtractAssign => qbe::Instr::Sub(lhs_val, rhs_val),
                 BinOp::Multiplication | BinOp::MultiplyAssign => qbe::Instr::Mul(lhs_val, rhs_val),
+                BinOp::Division | BinOp::DivideAssign if unsigned => {
+                    qbe::Instr::Udiv(lhs_val, rhs_val)
+                }
                 BinOp::Division | BinOp::DivideAssign => qbe::Instr::Div(lhs_val, rhs_val),
+                BinOp::Modulus if unsigned => qbe::Instr::Urem(lhs_val, rhs_val),
                 BinOp::Modulus => qbe::Instr::Rem(lhs_val, rhs_val),
 
-                BinOp::And => qbe::Instr::And(lhs_val, rhs_val),
-                BinOp::Or => qbe::Instr::Or(lhs_val, rhs_val),
-
                 // Others should be comparisons
                 cmp => qbe::Instr::Cmp(
                     ty.clone(),
                     match cmp {
+                        BinOp::LessThan if unsigned => qbe::Cmp::Ult,
+                        BinOp::LessThanOrEqual if unsigned => qbe::Cmp::Ule,
+                        BinOp::GreaterThan if unsigned => qbe::Cmp::Ugt,
+                        BinOp::GreaterThanOrEqual if unsigned => qbe::Cmp::Uge,
                         BinOp::LessThan => qbe::Cmp::Slt,
                         BinOp::LessThanOrEqual => qbe::Cmp::Sle,
                         BinOp::GreaterThan => qbe::Cmp::Sgt,
@@ -1303,23 +1290,112 @@ impl QbeGenerator {
             },
         );
 
-        // *Assign BinOps work just like normal ones except that here the
-        // result is assigned to the left hand side. This essentially makes
-        // `a += 1` the same as `a = a + 1`.
-        match op {
-            BinOp::AddAssign
+        let tmp = match (op, sign) {
+            (BinOp::Division | BinOp::DivideAssign, Some(sign)) => {
+                let signed = self.new_temporary();
+                func.assign_instr(signed.clone(), ty.clone(), qbe::Instr::Mul(tmp, sign));
+                signed
+            }
+            _ => tmp,
+        };
+
+        let tmp = match op {
+            BinOp::Addition
+            | BinOp::AddAssign
+            | BinOp::Subtraction
             | BinOp::SubtractAssign
+            | BinOp::Multiplication
             | BinOp::MultiplyAssign
-            | BinOp::DivideAssign => {
-                let tmp_clone = tmp.clone();
-                self.generate_assignment(func, lhs, tmp_clone)?;
-            }
-            _ => {}
+            | BinOp::Division
+            | BinOp::DivideAssign => self.wrap_sub_word(func, &ast_ty, tmp),
+            _ => tmp,
         };
 
+        // *Assign BinOps work just like normal ones except that here the
+        // result is assigned to the left hand side. This essentially makes
+        // `a += 1` the same as `a = a + 1`.
+        if let Some(place) = &place {
+            Self::store_place(func, place, tmp.clone());
+        }
+
         Ok((ty, tmp))
     }
 
+    /// Replaces a divisor of -1 with 1, so that a signed division can not
+    /// trap. Returns the new divisor and the sign of the quotient, which is
+    /// -1 if the divisor was replaced and 1 otherwise.
+    fn guard_divisor(
+        &mut self,
+        func: &mut qbe::Function,
+        ty: &qbe::Type,
+        divisor: qbe::Value,
+    ) -> (qbe::Value, qbe::Value) {
+        // minus_one = (divisor + 1 == 0), as 0 or 1
+        let incremented = self.new_temporary();
+        func.assign_instr(
+            incremented.clone(),
+            ty.clone(),
+            qbe::Instr::Add(divisor.clone(), qbe::Value::Const(1)),
+        );
+        let minus_one = self.new_temporary();
+        func.assign_instr(
+            minus_one.clone(),
+            ty.clone(),
+            qbe::Instr::Cmp(ty.clone(), qbe::Cmp::Eq, incremented, qbe::Value::Const(0)),
+        );
+        // divisor + 2 * minus_one
+        let twice = self.new_temporary();
+        func.assign_instr(
+            twice.clone(),
+            ty.clone(),
+            qbe::Instr::Mul(minus_one, qbe::Value::Const(2)),
+        );
+        let guarded = self.new_temporary();
+        func.assign_instr(
+            guarded.clone(),
+            ty.clone(),
+            qbe::Instr::Add(divisor, twice.clone()),
+        );
+        // 1 - 2 * minus_one
+        let sign = self.new_temporary();
+        func.assign_instr(
+            sign.clone(),
+            ty.clone(),
+            qbe::Instr::Sub(qbe::Value::Const(1), twice),
+        );
+        (guarded, sign)
+    }
+
+    /// Generates `&&` and `||`. The right hand side is only evaluated if
+    /// the left hand side does not already decide the result.
+    fn generate_logical_op(
+        &mut self,
+        func: &mut qbe::Function,
+        lhs: &Expression,
+        op: &BinOp,
+        rhs: &Expression,
+    ) -> GeneratorResult<(qbe::Type, qbe::Value)> {
+        let (_, lhs_val) = self.generate_expression(func, lhs)?;
+
+        self.tmp_counter += 1;
+        let rhs_label = format!("logic.{}.rhs", self.tmp_counter);
+        let end_label = format!("logic.{}.end", self.tmp_counter);
+
+        let result = self.new_temporary();
+        func.assign_instr(result.clone(), qbe::Type::Word, qbe::Instr::Copy(lhs_val));
+        func.add_instr(match op {
+            BinOp::And => qbe::Instr::Jnz(result.clone(), rhs_label.clone(), end_label.clone()),
+            _ => qbe::Instr::Jnz(result.clone(), end_label.clone(), rhs_label.clone()),
+        });
+
+        func.add_block(rhs_label);
+        let (_, rhs_val) = self.generate_expression(func, rhs)?;
+        func.assign_instr(result.clone(), qbe::Type::Word, qbe::Instr::Copy(rhs_val));
+
+        func.add_block(end_label);
+        Ok((qbe::Type::Word, result))
+    }
+
     /// Generates an assignment to either a variable, field access or array
     /// access
     fn generate_assignment(
@@ -1328,77 +1404,73 @@ impl QbeGenerator {
         lhs: &Expression,
         rhs: qbe::Value,
     ) -> GeneratorResult<()> {
-        match lhs {
-            Expression::Variable(name) => {
+        let place = self.generate_place(func, lhs)?;
+        Self::store_place(func, &place, rhs);
+        Ok(())
+    }
+
+    /// Evaluates the target of an assignment, e.g. the index of an array
+    /// access, without reading its value
+    fn generate_place(
+        &mut self,
+        func: &mut qbe::Function,
+        lhs: &Expression,
+    ) -> GeneratorResult<Place> {
+        match &lhs.kind {
+            ExpressionKind::Variable(name) => {
                 let (vty, tmp, _) = self.get_var(name)?;
-                func.assign_instr(
-                    tmp.to_owned(),
-                    vty.to_owned(),
-                    qbe::Instr::Copy(rhs),
-                );
+                Ok(Place::Variable(vty.to_owned(), tmp.to_owned()))
             }
-            Expression::FieldAccess { expr, field } => {
-                // First get all the info we need
-                let access_result = self.resolve_field_access(func, expr, field)?;
-                let (src, ty, offset) = access_result;
+            ExpressionKind::FieldAccess { expr, field } => {
+                let (src, ty, offset) = self.resolve_field_access(func, expr, field)?;
 
                 // Then create a temporary for the field pointer
                 let field_ptr = self.new_temporary();
-
-                // Do the operations
                 func.assign_instr(
                     field_ptr.clone(),
                     qbe::Type::Long,
                     qbe::Instr::Add(src, qbe::Value::Const(offset)),
                 );
-
-                func.add_instr(qbe::Instr::Store(ty, field_ptr, rhs));
+                Ok(Place::Memory(ty, field_ptr))
             }
-            Expression::ArrayAccess { name, index } => {
-                let (_, base, ast_type) = self.get_var(name)?.clone();
-                let elem_ast_type = match ast_type {
-                    Some(Type::Array(inner, _)) => *inner,
-                    _ => return Err(format!("'{}' is not an array", name)),
-                };
-                let elem_qbe_type = self.get_type(elem_ast_type)?;
-                let elem_size = self.type_size(&elem_qbe_type);
-
-                let (_, idx_val) = self.generate_expression(func, index)?;
-
-                let idx_long = self.new_temporary();
-                func.assign_instr(
-                    idx_long.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Extsw(idx_val),
-                );
-
-                let scaled = self.new_temporary();
-                func.assign_instr(
-                    scaled.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Mul(idx_long, qbe::Value::Const(elem_size)),
-                );
-
-                let with_header = self.new_temporary();
-                func.assign_instr(
-                    with_header.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Add(scaled, qbe::Value::Const(8)),
-                );
+            ExpressionKind::ArrayAccess { expr, index } => {
+                let (_, elem_qbe_type, elem_ptr) = self.generate_element_ptr(func, expr, index)?;
+                Ok(Place::Memory(elem_qbe_type, elem_ptr))
+            }
+            _ => Err(backend_error("Left side of an assignment must be either a variable, field access or array access")),
+        }
+    }
 
-                let elem_ptr = self.new_temporary();
+    /// Reads the value of an assignment target
+    fn load_place(&mut self, func: &mut qbe::Function, place: &Place) -> (qbe::Type, qbe::Value) {
+        match place {
+            Place::Variable(ty, tmp) => (ty.clone(), tmp.clone()),
+            // Nested structs are stored inline, their value is the pointer
+            Place::Memory(ty @ qbe::Type::Aggregate(_), ptr) => (ty.clone(), ptr.clone()),
+            Place::Memory(ty, ptr) => {
+                let tmp = self.new_temporary();
                 func.assign_instr(
-                    elem_ptr.clone(),
-                    qbe::Type::Long,
-                    qbe::Instr::Add(base, with_header),
+                    tmp.clone(),
+                    ty.clone().into_abi(),
+                    qbe::Instr::Load(ty.clone(), ptr.clone()),
                 );
-
-                func.add_instr(qbe::Instr::Store(elem_qbe_type, elem_ptr, rhs));
+                (ty.clone().into_abi(), tmp)
             }
-            _ => return Err("Left side of an assignment must be either a variable, field access or array access".to_owned()),
         }
+    }
 
-        Ok(())
+    fn store_place(func: &mut qbe::Function, place: &Place, value: qbe::Value) {
+        match place {
+            Place::Variable(ty, tmp) => {
+                func.assign_instr(tmp.clone(), ty.clone(), qbe::Instr::Copy(value))
+            }
+            Place::Memory(ty @ qbe::Type::Aggregate(_), ptr) => {
+                func.add_instr(qbe::Instr::Blit(value, ptr.clone(), ty.size()));
+            }
+            Place::Memory(ty, ptr) => {
+                func.add_instr(qbe::Instr::Store(ty.clone(), ptr.clone(), value))
+            }
+        }
     }
 
     /// Generates struct initialization
@@ -1412,7 +1484,9 @@ impl QbeGenerator {
         let (ty, meta, size) = self
             .struct_map
             .get(name)
-            .ok_or_else(|| format!("Initialization of undeclared struct '{}'", name))?
+            .ok_or_else(|| {
+                backend_error(format!("Initialization of undeclared struct '{}'", name))
+            })?
             .to_owned();
 
         // Allocate space for the struct
@@ -1424,7 +1498,7 @@ impl QbeGenerator {
             // Get field info
             let (field_type, offset, _) = meta
                 .get(name)
-                .ok_or_else(|| format!("Unknown field '{}'", name))?
+                .ok_or_else(|| backend_error(format!("Unknown field '{}'", name)))?
                 .clone();
 
             // Generate expression for field value
@@ -1476,15 +1550,20 @@ impl QbeGenerator {
             qbe::Instr::Add(src, qbe::Value::Const(offset)),
         );
 
+        // Nested structs are stored inline, their value is the field pointer
+        if let qbe::Type::Aggregate(_) = ty {
+            return Ok((ty, field_ptr));
+        }
+
         // Load the field value
         let tmp = self.new_temporary();
         func.assign_instr(
             tmp.clone(),
-            ty.clone(),
+            ty.clone().into_abi(),
             qbe::Instr::Load(ty.clone(), field_ptr),
         );
 
-        Ok((ty, tmp))
+        Ok((ty.into_abi(), tmp))
     }
 
     /// Resolves an expression that should evaluate to a struct value,
@@ -1494,59 +1573,60 @@ impl QbeGenerator {
         func: &mut qbe::Function,
         expr: &Expression,
     ) -> GeneratorResult<(qbe::Value, String, u64)> {
-        match expr {
-            Expression::Variable(var) => {
+        match &expr.kind {
+            ExpressionKind::Variable(var) => {
                 let (_, src, ast_type) = self.get_var(var)?.to_owned();
                 match ast_type {
                     Some(Type::Struct(name)) => Ok((src, name, 0)),
-                    _ => Err(format!("Variable '{}' is not a struct", var)),
+                    _ => Err(backend_error(format!("Variable '{}' is not a struct", var))),
                 }
             }
-            Expression::Selff => {
+            ExpressionKind::Selff => {
                 let (_, src, ast_type) = self.get_var("self")?.to_owned();
                 match ast_type {
                     Some(Type::Struct(name)) => Ok((src, name, 0)),
-                    _ => Err("'self' must refer to a struct".to_owned()),
+                    _ => Err(backend_error("'self' must refer to a struct")),
                 }
             }
-            Expression::FunctionCall { fn_name, .. } => {
-                let ast_ret = self.fn_ast_signatures.get(fn_name).cloned().flatten();
-                match ast_ret {
-                    Some(Type::Struct(struct_name)) => {
-                        let (_, val) = self.generate_expression(func, expr)?;
-                        Ok((val, struct_name, 0))
-                    }
-                    _ => Err(format!("Function '{}' does not return a struct", fn_name)),
-                }
-            }
-            Expression::FieldAccess { expr, field } => {
+            ExpressionKind::FieldAccess { expr, field } => {
                 let (src, parent_name, parent_off) = self.resolve_struct_expr(func, expr)?;
-                let field_name = match field.as_ref() {
-                    Expression::Variable(v) => v,
+                let field_name = match &field.kind {
+                    ExpressionKind::Variable(v) => v,
                     _ => unreachable!(),
                 };
                 let (_, meta, _) = self
                     .struct_map
                     .get(&parent_name)
-                    .ok_or_else(|| format!("Unknown struct '{}'", parent_name))?;
+                    .ok_or_else(|| backend_error(format!("Unknown struct '{}'", parent_name)))?;
                 let (_, field_off, ast_type) = meta
                     .get(field_name.as_str())
                     .ok_or_else(|| {
-                        format!("No field '{}' on struct '{}'", field_name, parent_name)
+                        backend_error(format!(
+                            "No field '{}' on struct '{}'",
+                            field_name, parent_name
+                        ))
                     })?
                     .to_owned();
                 match ast_type {
                     Some(Type::Struct(name)) => Ok((src, name, parent_off + field_off)),
-                    _ => Err(format!(
+                    _ => Err(backend_error(format!(
                         "Field '{}' on struct '{}' is not a struct",
                         field_name, parent_name
-                    )),
+                    ))),
                 }
             }
-            other => Err(format!(
-                "Invalid field access type: expected variable, field access or 'self', got {:?}",
-                other,
-            )),
+            // Function calls, array elements and other struct-valued expressions
+            // evaluate to a pointer to the struct
+            other => match self.expr_ast_type(expr) {
+                Some(Type::Struct(struct_name)) => {
+                    let (_, val) = self.generate_expression(func, expr)?;
+                    Ok((val, struct_name, 0))
+                }
+                _ => Err(backend_error(format!(
+                    "Invalid field access type: expected a struct, got {:?}",
+                    other,
+                ))),
+            },
         }
     }
 
@@ -1559,9 +1639,9 @@ impl QbeGenerator {
     ) -> GeneratorResult<(qbe::Value, qbe::Type, u64)> {
         let (src, struct_name, base_off) = self.resolve_struct_expr(func, obj)?;
 
-        let field_name = match field {
-            Expression::Variable(v) => v,
-            Expression::FunctionCall { .. } => {
+        let field_name = match &field.kind {
+            ExpressionKind::Variable(v) => v,
+            ExpressionKind::FunctionCall { .. } => {
                 unreachable!("method calls should be intercepted in generate_expression")
             }
             _ => unreachable!(),
@@ -1570,11 +1650,16 @@ impl QbeGenerator {
         let (_, meta, _) = self
             .struct_map
             .get(&struct_name)
-            .ok_or_else(|| format!("Unknown struct '{}'", struct_name))?;
+            .ok_or_else(|| backend_error(format!("Unknown struct '{}'", struct_name)))?;
 
         let (ty, field_off, _) = meta
             .get(field_name.as_str())
-            .ok_or_else(|| format!("No field '{}' on struct {}", field_name, struct_name))?
+            .ok_or_else(|| {
+                backend_error(format!(
+                    "No field '{}' on struct {}",
+                    field_name, struct_name
+                ))
+            })?
             .to_owned();
 
         Ok((src, ty, base_off + field_off))
@@ -1592,10 +1677,16 @@ impl QbeGenerator {
         let elem_ast_type = ident
             .ty
             .as_ref()
-            .ok_or_else(|| format!("Missing type for for-loop variable '{}'", ident.name))?
+            .ok_or_else(|| {
+                backend_error(format!(
+                    "Missing type for for-loop variable '{}'",
+                    ident.name
+                ))
+            })?
             .clone();
         let elem_type = self.get_type(elem_ast_type.clone())?;
-        let elem_size = self.type_size(&elem_type);
+        let elem_storage_type = self.get_elem_type(&elem_ast_type)?;
+        let elem_size = self.type_size(&elem_storage_type);
 
         // Generate array expression -> base pointer
         let (_, base_ptr) = self.generate_expression(func, expr)?;
@@ -1675,8 +1766,8 @@ impl QbeGenerator {
         let elem_val = self.new_temporary();
         func.assign_instr(
             elem_val.clone(),
-            elem_type.clone(),
-            qbe::Instr::Load(elem_type.clone(), elem_ptr),
+            elem_storage_type.clone().into_abi(),
+            qbe::Instr::Load(elem_storage_type, elem_ptr),
         );
         // Assign loaded value to ident
         func.assign_instr(ident_tmp, elem_type, qbe::Instr::Copy(elem_val));
@@ -1722,16 +1813,27 @@ impl QbeGenerator {
             item_results.push(result);
         }
 
-        // Then process the results
+        // 8 and 16 bit integers are stored in their own width
+        let sub_word = items
+            .first()
+            .and_then(|item| self.expr_ast_type(item))
+            .and_then(|ty| Self::sub_word_type(&ty));
+
+        // Then process the results. Nested arrays and structs are stored as
+        // pointers, so their aggregate types collapse to `Long`.
         for (ty, result) in item_results {
+            let ty = match &sub_word {
+                Some(storage) => storage.clone(),
+                None => ty.into_base(),
+            };
             results.push(result);
 
             if let Some(ref first_type) = first_type {
                 if ty != *first_type {
-                    return Err(format!(
+                    return Err(backend_error(format!(
                         "Inconsistent array types {:?} and {:?} (possibly more)",
                         first_type, ty
-                    ));
+                    )));
                 }
             } else {
                 first_type = Some(ty);
@@ -1789,7 +1891,7 @@ impl QbeGenerator {
             ident: name.clone(),
             align: None,
             items: if let Some(ty) = first_type {
-                vec![(qbe::Type::Long, 1), (ty, len)]
+                vec![(qbe::Type::Long, 1), (Self::typedef_item(&ty), len)]
             } else {
                 // No elements
                 vec![(qbe::Type::Long, 1)]
@@ -1813,7 +1915,8 @@ impl QbeGenerator {
         qbe::Value::Temporary(format!("tmp.{}", self.tmp_counter))
     }
 
-    /// Returns a new temporary bound to a variable
+    /// Returns a new temporary bound to a variable. Variables have unique
+    /// names within a function, see `parser::rename_shadowed`.
     fn new_var(
         &mut self,
         ty: &qbe::Type,
@@ -1821,7 +1924,10 @@ impl QbeGenerator {
         ast_type: Option<Type>,
     ) -> GeneratorResult<qbe::Value> {
         if self.get_var(name).is_ok() {
-            return Err(format!("Re-declaration of variable '{}'", name));
+            return Err(backend_error(format!(
+                "Re-declaration of variable '{}'",
+                name
+            )));
         }
 
         let tmp = self.new_temporary();
@@ -1842,7 +1948,7 @@ impl QbeGenerator {
             .rev()
             .filter_map(|s| s.get(name))
             .next()
-            .ok_or_else(|| format!("Undefined variable '{}'", name))
+            .ok_or_else(|| backend_error(format!("Undefined variable '{}'", name)))
     }
 
     /// Returns a QBE type for the given AST type
@@ -1850,17 +1956,22 @@ impl QbeGenerator {
         match ty {
             Type::Any => Ok(qbe::Type::Long),
             Type::Int => Ok(qbe::Type::Word),
+            Type::I8 | Type::U8 | Type::I16 | Type::U16 | Type::I32 | Type::U32 => {
+                Ok(qbe::Type::Word)
+            }
+            Type::I64 | Type::U64 => Ok(qbe::Type::Long),
             Type::Bool => Ok(qbe::Type::Word),
             Type::Str => Ok(qbe::Type::Long),
             Type::Struct(name) => {
                 let (ty, ..) = self
                     .struct_map
                     .get(&name)
-                    .ok_or_else(|| format!("Use of undeclared struct '{}'", name))?
+                    .ok_or_else(|| backend_error(format!("Use of undeclared struct '{}'", name)))?
                     .to_owned();
                 Ok(ty)
             }
             Type::Array(..) => Ok(qbe::Type::Long),
+            Type::Newtype(_, inner) => self.get_type(*inner),
         }
     }
 
@@ -1873,49 +1984,230 @@ impl QbeGenerator {
     /// This uses the AST type stored in scope to distinguish strings from
     /// arrays, since both map to qbe::Type::Long.
     fn is_string_expression(&self, expr: &Expression) -> bool {
-        match expr {
-            Expression::Str(_) => true,
-            Expression::Variable(name) => self
-                .get_var(name)
-                .map(|(_, _, ast_ty)| matches!(ast_ty, Some(Type::Str)))
-                .unwrap_or(false),
-            Expression::BinOp { lhs, op, .. } => {
-                matches!(op, BinOp::Addition | BinOp::AddAssign) && self.is_string_expression(lhs)
-            }
-            Expression::FieldAccess { expr, field } => {
-                if let Expression::Variable(field_name) = field.as_ref() {
-                    if let Ok(struct_name) = self.get_struct_name_of(expr) {
-                        if let Some((_, meta, _)) = self.struct_map.get(&struct_name) {
-                            if let Some((_, _, ast_type)) = meta.get(field_name) {
-                                return matches!(ast_type, Some(Type::Str));
-                            }
-                        }
-                    }
-                }
-                false
-            }
-            _ => false,
-        }
+        matches!(self.expr_ast_type(expr), Some(Type::Str))
     }
 
     /// Returns the Antimony struct name for an expression that evaluates to a struct pointer
     fn get_struct_name_of(&self, expr: &Expression) -> GeneratorResult<String> {
-        match expr {
-            Expression::Variable(name) => {
-                let (_, _, ast_type) = self.get_var(name)?;
-                match ast_type {
-                    Some(Type::Struct(s)) => Ok(s.clone()),
-                    _ => Err(format!("'{}' is not a struct", name)),
+        match self.expr_ast_type(expr) {
+            Some(Type::Struct(s)) => Ok(s),
+            _ => match &expr.kind {
+                ExpressionKind::Variable(name) => {
+                    Err(backend_error(format!("'{}' is not a struct", name)))
                 }
+                ExpressionKind::Selff => Err(backend_error("'self' does not refer to a struct")),
+                _ => Err(backend_error(
+                    "Cannot determine struct type for complex expression",
+                )),
+            },
+        }
+    }
+
+    /// Returns the QBE type used to store an element of the given AST type
+    /// inside an array. Structs are stored as pointers rather than inline.
+    fn get_elem_type(&self, ty: &Type) -> GeneratorResult<qbe::Type> {
+        match ty {
+            Type::Struct(_) => Ok(qbe::Type::Long),
+            other => match Self::sub_word_type(other) {
+                Some(ty) => Ok(ty),
+                None => self.get_type(other.to_owned()),
+            },
+        }
+    }
+
+    /// Returns the memory type of 8 and 16 bit integers. They are held in
+    /// words while in temporaries, and sign or zero extended when loaded.
+    fn sub_word_type(ty: &Type) -> Option<qbe::Type> {
+        match ty {
+            Type::I8 => Some(qbe::Type::SignedByte),
+            Type::U8 => Some(qbe::Type::UnsignedByte),
+            Type::I16 => Some(qbe::Type::SignedHalfword),
+            Type::U16 => Some(qbe::Type::UnsignedHalfword),
+            Type::Newtype(_, inner) => Self::sub_word_type(inner),
+            _ => None,
+        }
+    }
+
+    /// Type definitions do not distinguish signedness of their items
+    fn typedef_item(ty: &qbe::Type) -> qbe::Type {
+        match ty {
+            qbe::Type::SignedByte | qbe::Type::UnsignedByte => qbe::Type::Byte,
+            qbe::Type::SignedHalfword | qbe::Type::UnsignedHalfword => qbe::Type::Halfword,
+            other => other.clone(),
+        }
+    }
+
+    /// Truncates an arithmetic result to the width of an 8 or 16 bit
+    /// integer type, so that overflow wraps around like in memory
+    fn wrap_sub_word(
+        &mut self,
+        func: &mut qbe::Function,
+        ty: &Type,
+        value: qbe::Value,
+    ) -> qbe::Value {
+        let instr = match Self::sub_word_type(ty) {
+            Some(qbe::Type::SignedByte) => qbe::Instr::Extsb(value),
+            Some(qbe::Type::UnsignedByte) => qbe::Instr::Extub(value),
+            Some(qbe::Type::SignedHalfword) => qbe::Instr::Extsh(value),
+            Some(qbe::Type::UnsignedHalfword) => qbe::Instr::Extuh(value),
+            _ => return value,
+        };
+        let tmp = self.new_temporary();
+        func.assign_instr(tmp.clone(), qbe::Type::Word, instr);
+        tmp
+    }
+
+    /// Generates a conversion between two primitive types
+    fn generate_cast(
+        &mut self,
+        func: &mut qbe::Function,
+        expr: &Expression,
+        from: &Type,
+        to: &Type,
+    ) -> GeneratorResult<(qbe::Type, qbe::Value)> {
+        let wide = |ty: &Type| ty.int_bits() == Some(64);
+        // 64-bit literals may not fit into the word an `int` literal uses
+        if let (ExpressionKind::Int(literal), true) = (&expr.kind, wide(to)) {
+            let tmp = self.new_temporary();
+            func.assign_instr(
+                tmp.clone(),
+                qbe::Type::Long,
+                qbe::Instr::Copy(qbe::Value::Const(*literal as u64)),
+            );
+            return Ok((qbe::Type::Long, tmp));
+        }
+        let (ty, value) = self.generate_expression(func, expr)?;
+        let tmp = self.new_temporary();
+        let (result_ty, instr) = match (from, to) {
+            // Every `any` value is a long
+            (from, Type::Any) if from.is_integer() => {
+                let value = self.extend_to_long(func, from, value);
+                (qbe::Type::Long, qbe::Instr::Copy(value))
             }
-            Expression::Selff => {
-                let (_, _, ast_type) = self.get_var("self")?;
-                match ast_type {
-                    Some(Type::Struct(s)) => Ok(s.clone()),
-                    _ => Err("'self' does not refer to a struct".to_owned()),
+            (Type::Bool, Type::Any) => (qbe::Type::Long, qbe::Instr::Extuw(value)),
+            (_, Type::Any) => (qbe::Type::Long, qbe::Instr::Copy(value)),
+            (Type::U64, Type::Str) => (
+                qbe::Type::Long,
+                qbe::Instr::Call("_uint_to_str".into(), vec![(ty, value)], None),
+            ),
+            (from, Type::Str) if from.is_integer() => {
+                // _int_to_str takes a C long
+                let value = self.extend_to_long(func, from, value);
+                (
+                    qbe::Type::Long,
+                    qbe::Instr::Call("_int_to_str".into(), vec![(qbe::Type::Long, value)], None),
+                )
+            }
+            (Type::Bool, Type::Str) => (
+                qbe::Type::Long,
+                qbe::Instr::Call("_bool_to_str".into(), vec![(ty, value)], None),
+            ),
+            (Type::Str, to) if wide(to) => (
+                qbe::Type::Long,
+                qbe::Instr::Call("_parse_long".into(), vec![(ty, value)], None),
+            ),
+            (Type::Str, to) if to.is_integer() => {
+                func.assign_instr(
+                    tmp.clone(),
+                    qbe::Type::Word,
+                    qbe::Instr::Call("_parse_int".into(), vec![(ty, value)], None),
+                );
+                return Ok((qbe::Type::Word, self.wrap_sub_word(func, to, tmp)));
+            }
+            // Booleans already are words holding 0 or 1
+            (Type::Bool, to) if wide(to) => (qbe::Type::Long, qbe::Instr::Extuw(value)),
+            (Type::Bool, to) if to.is_integer() => (qbe::Type::Word, qbe::Instr::Copy(value)),
+            (from, Type::Bool) if from.is_integer() => (
+                qbe::Type::Word,
+                qbe::Instr::Cmp(ty, qbe::Cmp::Ne, value, qbe::Value::Const(0)),
+            ),
+            (from, to) if from.is_integer() && wide(to) => {
+                let value = self.extend_to_long(func, from, value);
+                (qbe::Type::Long, qbe::Instr::Copy(value))
+            }
+            // Narrowing keeps the low bits, using a long as a word truncates it
+            (from, to) if from.is_integer() && to.is_integer() => {
+                func.assign_instr(tmp.clone(), qbe::Type::Word, qbe::Instr::Copy(value));
+                return Ok((qbe::Type::Word, self.wrap_sub_word(func, to, tmp)));
+            }
+            (from, to) => return Err(backend_error(format!("Cannot cast `{}` to `{}`", from, to))),
+        };
+        func.assign_instr(tmp.clone(), result_ty.clone(), instr);
+        Ok((result_ty, tmp))
+    }
+
+    /// Sign or zero extends an integer of at most 32 bits to a long
+    fn extend_to_long(
+        &mut self,
+        func: &mut qbe::Function,
+        ty: &Type,
+        value: qbe::Value,
+    ) -> qbe::Value {
+        if ty.int_bits() == Some(64) {
+            return value;
+        }
+        let instr = if ty.is_unsigned() {
+            qbe::Instr::Extuw(value)
+        } else {
+            qbe::Instr::Extsw(value)
+        };
+        let wide = self.new_temporary();
+        func.assign_instr(wide.clone(), qbe::Type::Long, instr);
+        wide
+    }
+
+    /// Determines the AST type of an expression from the variables in scope,
+    /// struct definitions and known function signatures
+    fn expr_ast_type(&self, expr: &Expression) -> Option<Type> {
+        match &expr.kind {
+            ExpressionKind::Int(_) => Some(Type::Int),
+            ExpressionKind::Str(_) => Some(Type::Str),
+            ExpressionKind::Bool(_) => Some(Type::Bool),
+            ExpressionKind::Selff => self.get_var("self").ok()?.2.clone(),
+            ExpressionKind::Variable(name) => self.get_var(name).ok()?.2.clone(),
+            ExpressionKind::Array { elements, .. } => {
+                let elem = self.expr_ast_type(elements.first()?)?;
+                Some(Type::Array(Box::new(elem), Some(elements.len())))
+            }
+            ExpressionKind::ArrayAccess { expr, .. } => match self.expr_ast_type(expr)? {
+                Type::Array(inner, _) => Some(*inner),
+                _ => None,
+            },
+            ExpressionKind::FunctionCall { fn_name, .. } => match self.intrinsics.get(fn_name) {
+                Some(Intrinsic::ArrayLen) => Some(Type::Int),
+                None => self.fn_ast_signatures.get(fn_name).cloned().flatten(),
+            },
+            ExpressionKind::BinOp { lhs, op, .. } => match op {
+                BinOp::Equal
+                | BinOp::NotEqual
+                | BinOp::LessThan
+                | BinOp::LessThanOrEqual
+                | BinOp::GreaterThan
+                | BinOp::GreaterThanOrEqual
+                | BinOp::And
+                | BinOp::Or => Some(Type::Bool),
+                _ => self.expr_ast_type(lhs).or(Some(Type::Int)),
+            },
+            ExpressionKind::StructInitialization { name, .. } => Some(Type::Struct(name.clone())),
+            ExpressionKind::FieldAccess { expr, field } => {
+                let struct_name = match self.expr_ast_type(expr)? {
+                    Type::Struct(name) => name,
+                    _ => return None,
+                };
+                match &field.kind {
+                    ExpressionKind::Variable(field_name) => {
+                        let (_, meta, _) = self.struct_map.get(&struct_name)?;
+                        meta.get(field_name)?.2.clone()
+                    }
+                    ExpressionKind::FunctionCall { fn_name, .. } => self
+                        .fn_ast_signatures
+                        .get(&format!("{}_{}", struct_name, fn_name))
+                        .cloned()
+                        .flatten(),
+                    _ => None,
                 }
             }
-            _ => Err("Cannot determine struct type for complex expression".to_owned()),
+            ExpressionKind::Cast { to, .. } => Some(to.clone()),
         }
     }
 }
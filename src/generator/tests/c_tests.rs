@@ -1,18 +1,17 @@
 use crate::ast::types::Type;
 use crate::ast::BinOp::*;
-use crate::ast::Expression::*;
-use crate::ast::Statement::*;
-use crate::ast::{Function, StructDef, Variable};
+use crate::ast::{ExpressionKind, Function, Span, StatementKind, StructDef, Variable};
 use crate::generator::c::*;
 use std::collections::HashMap;
 
 #[test]
 fn test_generate_block_empty() {
     let t = generate_block(
-        Block {
+        StatementKind::Block {
             statements: vec![],
             scope: vec![],
-        },
+        }
+        .into(),
         None,
     );
     assert_eq!(t, "{\n}\n")
@@ -21,10 +20,11 @@ fn test_generate_block_empty() {
 #[test]
 fn test_generate_block_with_statement() {
     let t = generate_block(
-        Block {
-            statements: vec![Return(Some(Int(42)))],
+        StatementKind::Block {
+            statements: vec![StatementKind::Return(Some(ExpressionKind::Int(42).into())).into()],
             scope: vec![],
-        },
+        }
+        .into(),
         None,
     );
     assert_eq!(t, "{\n    return 42;\n}\n")
@@ -36,10 +36,13 @@ fn test_generate_function() {
         name: "test_func".to_string(),
         arguments: vec![],
         ret_type: Some(Type::Int),
-        body: Block {
-            statements: vec![Return(Some(Int(0)))],
+        body: StatementKind::Block {
+            statements: vec![StatementKind::Return(Some(ExpressionKind::Int(0).into())).into()],
             scope: vec![],
-        },
+        }
+        .into(),
+        mutable_self: false,
+        span: Span::default(),
     };
     let result = generate_function(func);
     assert_eq!(result, "int test_func(void) {\n    return 0;\n}\n\n")
@@ -57,10 +60,12 @@ fn test_generate_arguments_with_params() {
         Variable {
             name: "x".to_string(),
             ty: Some(Type::Int),
+            span: Span::default(),
         },
         Variable {
             name: "y".to_string(),
             ty: Some(Type::Bool),
+            span: Span::default(),
         },
     ];
     assert_eq!(generate_arguments(args), "int x, bool y")
@@ -68,56 +73,207 @@ fn test_generate_arguments_with_params() {
 
 #[test]
 fn test_generate_expression_int() {
-    assert_eq!(generate_expression(Int(42)), "42")
+    assert_eq!(generate_expression(ExpressionKind::Int(42).into()), "42")
 }
 
 #[test]
 fn test_generate_expression_string() {
-    assert_eq!(generate_expression(Str("hello".to_string())), "\"hello\"")
+    assert_eq!(
+        generate_expression(ExpressionKind::Str("hello".to_string()).into()),
+        "\"hello\""
+    )
 }
 
 #[test]
 fn test_generate_expression_bool() {
-    assert_eq!(generate_expression(Bool(true)), "true")
+    assert_eq!(
+        generate_expression(ExpressionKind::Bool(true).into()),
+        "true"
+    )
 }
 
 #[test]
 fn test_generate_binary_operation() {
-    let expr = BinOp {
-        lhs: Box::new(Int(1)),
+    let expr = ExpressionKind::BinOp {
+        lhs: Box::new(ExpressionKind::Int(1).into()),
         op: Addition,
-        rhs: Box::new(Int(2)),
-    };
+        rhs: Box::new(ExpressionKind::Int(2).into()),
+        ty: None,
+    }
+    .into();
     assert_eq!(generate_expression(expr), "1 + 2")
 }
 
+#[test]
+fn test_generate_integer_arithmetic() {
+    let op = |op, ty| {
+        ExpressionKind::BinOp {
+            lhs: Box::new(ExpressionKind::Variable("a".into()).into()),
+            op,
+            rhs: Box::new(ExpressionKind::Variable("b".into()).into()),
+            ty: Some(ty),
+        }
+        .into()
+    };
+    assert_eq!(
+        generate_expression(op(Addition, Type::Int)),
+        "((int)((uint32_t)(a) + (uint32_t)(b)))"
+    );
+    assert_eq!(
+        generate_expression(op(MultiplyAssign, Type::I64)),
+        "*(uint64_t*)&(a) *= (uint64_t)(b)"
+    );
+    assert_eq!(
+        generate_expression(op(AddAssign, Type::I16)),
+        "*(uint16_t*)&(a) += (uint32_t)(b)"
+    );
+    assert_eq!(
+        generate_expression(op(Division, Type::I8)),
+        "((int8_t)_div_i64(a, b))"
+    );
+    assert_eq!(
+        generate_expression(op(Modulus, Type::U32)),
+        "((uint32_t)(a % b))"
+    );
+    assert_eq!(
+        generate_expression(op(DivideAssign, Type::Int)),
+        "a /= (int64_t)(b)"
+    );
+    assert_eq!(
+        generate_expression(op(DivideAssign, Type::I64)),
+        "_div_assign_i64(&(a), b)"
+    );
+}
+
 #[test]
 fn test_generate_function_call() {
-    let call = FunctionCall {
+    let call = ExpressionKind::FunctionCall {
         fn_name: "test".to_string(),
-        args: vec![Int(1), Int(2)],
-    };
+        args: vec![ExpressionKind::Int(1).into(), ExpressionKind::Int(2).into()],
+    }
+    .into();
     assert_eq!(generate_expression(call), "test(1, 2)")
 }
 
 #[test]
 fn test_generate_array() {
-    let arr = Array {
+    let arr = ExpressionKind::Array {
         capacity: 3,
-        elements: vec![Int(1), Int(2), Int(3)],
+        elements: vec![
+            ExpressionKind::Int(1).into(),
+            ExpressionKind::Int(2).into(),
+            ExpressionKind::Int(3).into(),
+        ],
+    }
+    .into();
+    assert_eq!(generate_expression(arr), "(int[]){1, 2, 3}")
+}
+
+#[test]
+fn test_generate_nested_array() {
+    let row = |a, b| {
+        ExpressionKind::Array {
+            capacity: 2,
+            elements: vec![ExpressionKind::Int(a).into(), ExpressionKind::Int(b).into()],
+        }
+        .into()
+    };
+    let grid = ExpressionKind::Array {
+        capacity: 2,
+        elements: vec![row(1, 2), row(3, 4)],
+    };
+    assert_eq!(
+        generate_expression(grid.clone().into()),
+        "(int*[]){(int[]){1, 2}, (int[]){3, 4}}"
+    );
+    let var = Variable {
+        name: "grid".to_string(),
+        ty: Some(Type::Array(
+            Box::new(Type::Array(Box::new(Type::Int), None)),
+            None,
+        )),
+        span: Span::default(),
     };
-    assert_eq!(generate_expression(arr), "{1, 2, 3}")
+    assert_eq!(
+        generate_declare(var, Some(grid.into())),
+        "int* grid[] = {(int[]){1, 2}, (int[]){3, 4}}"
+    )
 }
 
 #[test]
-fn test_generate_array_access() {
-    let access = ArrayAccess {
-        name: "arr".to_string(),
-        index: Box::new(Int(0)),
+fn test_generate_returned_array() {
+    let row = |a, b| {
+        ExpressionKind::Array {
+            capacity: 2,
+            elements: vec![ExpressionKind::Int(a).into(), ExpressionKind::Int(b).into()],
+        }
+        .into()
+    };
+    let grid = ExpressionKind::Array {
+        capacity: 1,
+        elements: vec![row(1, 2)],
     };
+    assert_eq!(
+        generate_return(Some(grid.into())),
+        "return _heap_array((int*[]){_heap_array((int[]){1, 2}, 2 * sizeof(int))}, 1 * sizeof(int*))"
+    )
+}
+
+#[test]
+fn test_generate_struct_array() {
+    let users = ExpressionKind::Array {
+        capacity: 1,
+        elements: vec![ExpressionKind::Variable("u".to_string()).into()],
+    };
+    let var = Variable {
+        name: "users".to_string(),
+        ty: Some(Type::Array(
+            Box::new(Type::Struct("User".to_string())),
+            None,
+        )),
+        span: Span::default(),
+    };
+    assert_eq!(
+        generate_declare(var, Some(users.into())),
+        "User users[] = {u}"
+    )
+}
+
+#[test]
+fn test_generate_array_access() {
+    let access = ExpressionKind::ArrayAccess {
+        expr: Box::new(ExpressionKind::Variable("arr".to_string()).into()),
+        index: Box::new(ExpressionKind::Int(0).into()),
+    }
+    .into();
     assert_eq!(generate_expression(access), "arr[0]")
 }
 
+#[test]
+fn test_generate_compound_assignment_to_element() {
+    let target = ExpressionKind::ArrayAccess {
+        expr: Box::new(ExpressionKind::Variable("arr".to_string()).into()),
+        index: Box::new(
+            ExpressionKind::FunctionCall {
+                fn_name: "idx".to_string(),
+                args: vec![],
+            }
+            .into(),
+        ),
+    };
+    let expr = ExpressionKind::BinOp {
+        lhs: Box::new(target.into()),
+        op: AddAssign,
+        rhs: Box::new(ExpressionKind::Int(1).into()),
+        ty: Some(Type::Int),
+    }
+    .into();
+    assert_eq!(
+        generate_expression(expr),
+        "*(uint32_t*)&(arr[idx()]) += (uint32_t)(1)"
+    )
+}
+
 #[test]
 fn test_generate_struct_definition() {
     let struct_def = StructDef {
@@ -125,8 +281,10 @@ fn test_generate_struct_definition() {
         fields: vec![Variable {
             name: "field1".to_string(),
             ty: Some(Type::Int),
+            span: Span::default(),
         }],
         methods: vec![],
+        span: Span::default(),
     };
     let result = generate_struct_definition(struct_def);
     assert_eq!(
@@ -136,28 +294,81 @@ fn test_generate_struct_definition() {
 }
 
 #[test]
-fn test_generate_conditional() {
-    let if_stmt = If {
-        condition: Bool(true),
-        body: Box::new(Block {
-            statements: vec![Return(Some(Int(1)))],
+fn test_generate_struct_methods() {
+    let method = |name: &str, mutable_self| Function {
+        name: name.to_string(),
+        arguments: vec![],
+        ret_type: None,
+        body: StatementKind::Block {
+            // Only a `mut self` method may assign to a field
+            statements: if mutable_self {
+                vec![StatementKind::Assign {
+                    lhs: Box::new(
+                        ExpressionKind::FieldAccess {
+                            expr: Box::new(ExpressionKind::Selff.into()),
+                            field: Box::new(ExpressionKind::Variable("count".to_string()).into()),
+                        }
+                        .into(),
+                    ),
+                    rhs: Box::new(ExpressionKind::Int(0).into()),
+                }
+                .into()]
+            } else {
+                vec![]
+            },
             scope: vec![],
-        }),
-        else_branch: None,
+        }
+        .into(),
+        mutable_self,
+        span: Span::default(),
     };
+    let struct_def = StructDef {
+        name: "Counter".to_string(),
+        fields: vec![],
+        methods: vec![method("reset", true), method("peek", false)],
+        span: Span::default(),
+    };
+    assert_eq!(
+        generate_struct_definition(struct_def),
+        "typedef struct Counter {\n} Counter;\n\n\
+         void reset(Counter *self);\n\
+         void peek(const Counter *self);\n\
+         void reset(Counter *self) {\n    self->count = 0;\n}\n\n\
+         void peek(const Counter *self) {\n}\n\n"
+    )
+}
+
+#[test]
+fn test_generate_conditional() {
+    let if_stmt = StatementKind::If {
+        condition: ExpressionKind::Bool(true).into(),
+        body: Box::new(
+            StatementKind::Block {
+                statements: vec![StatementKind::Return(Some(ExpressionKind::Int(1).into())).into()],
+                scope: vec![],
+            }
+            .into(),
+        ),
+        else_branch: None,
+    }
+    .into();
     let result = generate_statement(if_stmt);
     assert_eq!(result, "    if (true) {\n    return 1;\n}\n;\n")
 }
 
 #[test]
 fn test_generate_while_loop() {
-    let while_stmt = While {
-        condition: Bool(true),
-        body: Box::new(Block {
-            statements: vec![Break],
-            scope: vec![],
-        }),
-    };
+    let while_stmt = StatementKind::While {
+        condition: ExpressionKind::Bool(true).into(),
+        body: Box::new(
+            StatementKind::Block {
+                statements: vec![StatementKind::Break.into()],
+                scope: vec![],
+            }
+            .into(),
+        ),
+    }
+    .into();
     let result = generate_statement(while_stmt);
     assert_eq!(result, "    while (true) {\n    break;\n}\n;\n")
 }
@@ -165,39 +376,152 @@ fn test_generate_while_loop() {
 #[test]
 fn test_generate_struct_initialization() {
     let mut fields = HashMap::new();
-    fields.insert("x".to_string(), Box::new(Int(1)));
-    let init = StructInitialization {
+    fields.insert("x".to_string(), Box::new(ExpressionKind::Int(1).into()));
+    let init = ExpressionKind::StructInitialization {
         name: "Point".to_string(),
         fields,
-    };
+    }
+    .into();
     assert_eq!(generate_expression(init), "(Point) {.x = 1}")
 }
 
 #[test]
 fn test_generate_field_access() {
-    let access = FieldAccess {
-        expr: Box::new(Variable("point".to_string())),
-        field: Box::new(Variable("x".to_string())),
-    };
+    let access = ExpressionKind::FieldAccess {
+        expr: Box::new(ExpressionKind::Variable("point".to_string()).into()),
+        field: Box::new(ExpressionKind::Variable("x".to_string()).into()),
+    }
+    .into();
     assert_eq!(generate_expression(access), "point.x")
 }
 
+#[test]
+fn test_generate_method_call() {
+    let call = ExpressionKind::FieldAccess {
+        expr: Box::new(
+            ExpressionKind::FieldAccess {
+                expr: Box::new(ExpressionKind::Variable("admin".to_string()).into()),
+                field: Box::new(ExpressionKind::Variable("User".to_string()).into()),
+            }
+            .into(),
+        ),
+        field: Box::new(
+            ExpressionKind::FunctionCall {
+                fn_name: "full_name".to_string(),
+                args: vec![ExpressionKind::Int(1).into()],
+            }
+            .into(),
+        ),
+    }
+    .into();
+    assert_eq!(generate_expression(call), "full_name(&admin.User, 1)")
+}
+
+#[test]
+fn test_generate_cast() {
+    let cast = |expr, from, to| {
+        ExpressionKind::Cast {
+            expr: Box::new(expr),
+            from,
+            to,
+        }
+        .into()
+    };
+    assert_eq!(
+        generate_expression(cast(ExpressionKind::Int(5).into(), Type::Int, Type::Str)),
+        "_int_to_str(5)"
+    );
+    assert_eq!(
+        generate_expression(cast(
+            ExpressionKind::Bool(true).into(),
+            Type::Bool,
+            Type::Str
+        )),
+        "(true ? \"true\" : \"false\")"
+    );
+    assert_eq!(
+        generate_expression(cast(
+            ExpressionKind::Str("42".into()).into(),
+            Type::Str,
+            Type::Int
+        )),
+        "_parse_int(\"42\")"
+    );
+    assert_eq!(
+        generate_expression(cast(
+            ExpressionKind::Variable("n".into()).into(),
+            Type::Int,
+            Type::Bool
+        )),
+        "(n != 0)"
+    );
+}
+
+#[test]
+fn test_generate_sized_integers() {
+    let func = Function {
+        name: "widen".to_string(),
+        arguments: vec![Variable {
+            name: "b".to_string(),
+            ty: Some(Type::U8),
+            span: Span::default(),
+        }],
+        body: StatementKind::Block {
+            statements: vec![StatementKind::Return(Some(
+                ExpressionKind::Cast {
+                    expr: Box::new(ExpressionKind::Variable("b".into()).into()),
+                    from: Type::U8,
+                    to: Type::I64,
+                }
+                .into(),
+            ))
+            .into()],
+            scope: vec![],
+        }
+        .into(),
+        ret_type: Some(Type::I64),
+        mutable_self: false,
+        span: Span::default(),
+    };
+    assert_eq!(
+        generate_function_prototype(&func),
+        "int64_t widen(uint8_t b);\n"
+    );
+    assert_eq!(
+        generate_expression(
+            ExpressionKind::Cast {
+                expr: Box::new(ExpressionKind::Str("7".into()).into()),
+                from: Type::Str,
+                to: Type::U64,
+            }
+            .into()
+        ),
+        "((uint64_t)_parse_long(\"7\"))"
+    );
+}
+
 #[test]
 fn test_generate_for_loop() {
-    let for_stmt = For {
+    let for_stmt = StatementKind::For {
         ident: Variable {
             name: "i".to_string(),
             ty: Some(Type::Int),
+            span: Span::default(),
         },
-        expr: Array {
+        expr: ExpressionKind::Array {
             capacity: 2,
-            elements: vec![Int(1), Int(2)],
-        },
-        body: Box::new(Block {
-            statements: vec![],
-            scope: vec![],
-        }),
-    };
+            elements: vec![ExpressionKind::Int(1).into(), ExpressionKind::Int(2).into()],
+        }
+        .into(),
+        body: Box::new(
+            StatementKind::Block {
+                statements: vec![],
+                scope: vec![],
+            }
+            .into(),
+        ),
+    }
+    .into();
     let result = generate_statement(for_stmt);
     assert!(result.contains("for(int i = 0;"));
 }
@@ -207,13 +531,17 @@ fn test_generate_declare() {
     let var = Variable {
         name: "x".to_string(),
         ty: Some(Type::Int),
+        span: Span::default(),
     };
-    let decl = generate_declare(var, Some(Int(42)));
+    let decl = generate_declare(var, Some(ExpressionKind::Int(42).into()));
     assert_eq!(decl, "int x = 42")
 }
 
 #[test]
 fn test_generate_return() {
-    assert_eq!(generate_return(Some(Int(42))), "return 42");
+    assert_eq!(
+        generate_return(Some(ExpressionKind::Int(42).into())),
+        "return 42"
+    );
     assert_eq!(generate_return(None), "return");
 }
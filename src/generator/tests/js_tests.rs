@@ -27,16 +27,18 @@ mod tests {
     }
 
     fn block(stmts: Vec<Statement>) -> Statement {
-        Statement::Block {
+        StatementKind::Block {
             statements: stmts,
             scope: vec![],
         }
+        .into()
     }
 
     fn var(name: &str, ty: AstType) -> Variable {
         Variable {
             name: name.to_string(),
             ty: Some(ty),
+            span: Span::default(),
         }
     }
 
@@ -54,6 +56,8 @@ mod tests {
             arguments: args,
             ret_type: ret,
             body,
+            mutable_self: false,
+            span: Span::default(),
         }
     }
 
@@ -76,7 +80,10 @@ main();"
 
     #[test]
     fn test_function_return_int() {
-        let body = block(vec![Statement::Return(Some(Expression::Int(42)))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::Int(42).into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("answer", vec![], Some(AstType::Int), body),
@@ -100,11 +107,16 @@ main();"
 
     #[test]
     fn test_function_with_arguments() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Variable("a".to_string())),
-            op: BinOp::Addition,
-            rhs: Box::new(Expression::Variable("b".to_string())),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Variable("a".to_string()).into()),
+                op: BinOp::Addition,
+                rhs: Box::new(ExpressionKind::Variable("b".to_string()).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -137,11 +149,16 @@ main();"
 
     #[test]
     fn test_arithmetic_addition() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Int(3)),
-            op: BinOp::Addition,
-            rhs: Box::new(Expression::Int(4)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Int(3).into()),
+                op: BinOp::Addition,
+                rhs: Box::new(ExpressionKind::Int(4).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("calc", vec![], Some(AstType::Int), body),
@@ -165,11 +182,16 @@ main();"
 
     #[test]
     fn test_arithmetic_subtraction() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Int(10)),
-            op: BinOp::Subtraction,
-            rhs: Box::new(Expression::Int(3)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Int(10).into()),
+                op: BinOp::Subtraction,
+                rhs: Box::new(ExpressionKind::Int(3).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("calc", vec![], Some(AstType::Int), body),
@@ -193,11 +215,16 @@ main();"
 
     #[test]
     fn test_arithmetic_multiplication() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Int(4)),
-            op: BinOp::Multiplication,
-            rhs: Box::new(Expression::Int(5)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Int(4).into()),
+                op: BinOp::Multiplication,
+                rhs: Box::new(ExpressionKind::Int(5).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("calc", vec![], Some(AstType::Int), body),
@@ -221,11 +248,16 @@ main();"
 
     #[test]
     fn test_arithmetic_division() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Int(8)),
-            op: BinOp::Division,
-            rhs: Box::new(Expression::Int(2)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Int(8).into()),
+                op: BinOp::Division,
+                rhs: Box::new(ExpressionKind::Int(2).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("calc", vec![], Some(AstType::Int), body),
@@ -249,11 +281,16 @@ main();"
 
     #[test]
     fn test_arithmetic_modulus() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Int(9)),
-            op: BinOp::Modulus,
-            rhs: Box::new(Expression::Int(4)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Int(9).into()),
+                op: BinOp::Modulus,
+                rhs: Box::new(ExpressionKind::Int(4).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("calc", vec![], Some(AstType::Int), body),
@@ -281,11 +318,16 @@ main();"
 
     #[test]
     fn test_comparison_equal() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Variable("a".to_string())),
-            op: BinOp::Equal,
-            rhs: Box::new(Expression::Variable("b".to_string())),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Variable("a".to_string()).into()),
+                op: BinOp::Equal,
+                rhs: Box::new(ExpressionKind::Variable("b".to_string()).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -314,11 +356,16 @@ main();"
 
     #[test]
     fn test_comparison_not_equal() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Variable("a".to_string())),
-            op: BinOp::NotEqual,
-            rhs: Box::new(Expression::Variable("b".to_string())),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Variable("a".to_string()).into()),
+                op: BinOp::NotEqual,
+                rhs: Box::new(ExpressionKind::Variable("b".to_string()).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -347,11 +394,16 @@ main();"
 
     #[test]
     fn test_comparison_less_than() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Variable("a".to_string())),
-            op: BinOp::LessThan,
-            rhs: Box::new(Expression::Variable("b".to_string())),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Variable("a".to_string()).into()),
+                op: BinOp::LessThan,
+                rhs: Box::new(ExpressionKind::Variable("b".to_string()).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -380,11 +432,16 @@ main();"
 
     #[test]
     fn test_comparison_greater_than() {
-        let body = block(vec![Statement::Return(Some(Expression::BinOp {
-            lhs: Box::new(Expression::Variable("a".to_string())),
-            op: BinOp::GreaterThan,
-            rhs: Box::new(Expression::Variable("b".to_string())),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::BinOp {
+                lhs: Box::new(ExpressionKind::Variable("a".to_string()).into()),
+                op: BinOp::GreaterThan,
+                rhs: Box::new(ExpressionKind::Variable("b".to_string()).into()),
+                ty: None,
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -418,11 +475,12 @@ main();"
     #[test]
     fn test_variable_declaration_with_value() {
         let body = block(vec![
-            Statement::Declare {
+            StatementKind::Declare {
                 variable: var("x", AstType::Int),
-                value: Some(Expression::Int(7)),
-            },
-            Statement::Return(Some(Expression::Variable("x".to_string()))),
+                value: Some(ExpressionKind::Int(7).into()),
+            }
+            .into(),
+            StatementKind::Return(Some(ExpressionKind::Variable("x".to_string()).into())).into(),
         ]);
         let m = module(
             vec![
@@ -435,7 +493,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function get_x(){
-var x = 7;
+let x = 7;
 return x;
 }
 
@@ -448,10 +506,11 @@ main();"
 
     #[test]
     fn test_variable_declaration_no_value() {
-        let body = block(vec![Statement::Declare {
+        let body = block(vec![StatementKind::Declare {
             variable: var("x", AstType::Int),
             value: None,
-        }]);
+        }
+        .into()]);
         let m = module(
             vec![
                 func("decl_only", vec![], None, body),
@@ -463,7 +522,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function decl_only(){
-var x;
+let x;
 }
 
 function main(){
@@ -475,10 +534,11 @@ main();"
 
     #[test]
     fn test_uninitialized_array_becomes_empty_array() {
-        let body = block(vec![Statement::Declare {
+        let body = block(vec![StatementKind::Declare {
             variable: var("buf", AstType::Array(Box::new(AstType::Int), None)),
             value: None,
-        }]);
+        }
+        .into()]);
         let m = module(
             vec![
                 func("f", vec![], None, body),
@@ -490,7 +550,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function f(){
-var buf = [];
+let buf = [];
 }
 
 function main(){
@@ -503,14 +563,16 @@ main();"
     #[test]
     fn test_variable_assignment() {
         let body = block(vec![
-            Statement::Declare {
+            StatementKind::Declare {
                 variable: var("x", AstType::Int),
-                value: Some(Expression::Int(1)),
-            },
-            Statement::Assign {
-                lhs: Box::new(Expression::Variable("x".to_string())),
-                rhs: Box::new(Expression::Int(99)),
-            },
+                value: Some(ExpressionKind::Int(1).into()),
+            }
+            .into(),
+            StatementKind::Assign {
+                lhs: Box::new(ExpressionKind::Variable("x".to_string()).into()),
+                rhs: Box::new(ExpressionKind::Int(99).into()),
+            }
+            .into(),
         ]);
         let m = module(
             vec![
@@ -523,13 +585,201 @@ main();"
         assert_eq!(
             user_code(&result),
             "function reassign(){
-var x = 1;
+let x = 1;
 x = 99;
 }
 
 function main(){
 }
 
+main();"
+        );
+    }
+
+    #[test]
+    fn test_casts() {
+        let cast = |expr, from, to| {
+            StatementKind::Exp(
+                ExpressionKind::Cast {
+                    expr: Box::new(expr),
+                    from,
+                    to,
+                }
+                .into(),
+            )
+            .into()
+        };
+        let body = block(vec![
+            cast(ExpressionKind::Int(1).into(), AstType::Int, AstType::Str),
+            cast(
+                ExpressionKind::Str("2".into()).into(),
+                AstType::Str,
+                AstType::Int,
+            ),
+            cast(
+                ExpressionKind::Bool(true).into(),
+                AstType::Bool,
+                AstType::Int,
+            ),
+            cast(ExpressionKind::Int(0).into(), AstType::Int, AstType::Bool),
+        ]);
+        let m = module(vec![func("main", vec![], None, body)], vec![]);
+        let result = JsGenerator::generate(m).unwrap();
+        assert_eq!(
+            user_code(&result),
+            "function main(){
+String(1);
+_parse_int(\"2\");
+(true ? 1 : 0);
+(0 !== 0);
+}
+
+main();"
+        );
+    }
+
+    #[test]
+    fn test_sized_integers() {
+        let body = block(vec![
+            StatementKind::Exp(
+                ExpressionKind::Cast {
+                    expr: Box::new(ExpressionKind::Int(5000000000).into()),
+                    from: AstType::Int,
+                    to: AstType::I64,
+                }
+                .into(),
+            )
+            .into(),
+            StatementKind::Exp(
+                ExpressionKind::Cast {
+                    expr: Box::new(ExpressionKind::Variable("x".into()).into()),
+                    from: AstType::I64,
+                    to: AstType::U8,
+                }
+                .into(),
+            )
+            .into(),
+            StatementKind::Exp(
+                ExpressionKind::BinOp {
+                    lhs: Box::new(ExpressionKind::Variable("b".into()).into()),
+                    op: BinOp::AddAssign,
+                    rhs: Box::new(ExpressionKind::Variable("c".into()).into()),
+                    ty: Some(AstType::U8),
+                }
+                .into(),
+            )
+            .into(),
+            StatementKind::Exp(
+                ExpressionKind::BinOp {
+                    lhs: Box::new(ExpressionKind::Variable("x".into()).into()),
+                    op: BinOp::Multiplication,
+                    rhs: Box::new(ExpressionKind::Variable("y".into()).into()),
+                    ty: Some(AstType::I64),
+                }
+                .into(),
+            )
+            .into(),
+        ]);
+        let m = module(vec![func("main", vec![], None, body)], vec![]);
+        let result = JsGenerator::generate(m).unwrap();
+        assert_eq!(
+            user_code(&result),
+            "function main(){
+5000000000n;
+Number(BigInt.asUintN(8, x));
+b = ((b + c) & 255);
+BigInt.asIntN(64, (x * y));
+}
+
+main();"
+        );
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        let op = |op, ty| {
+            StatementKind::Exp(
+                ExpressionKind::BinOp {
+                    lhs: Box::new(ExpressionKind::Variable("a".into()).into()),
+                    op,
+                    rhs: Box::new(ExpressionKind::Variable("b".into()).into()),
+                    ty,
+                }
+                .into(),
+            )
+            .into()
+        };
+        let body = block(vec![
+            op(BinOp::Division, Some(AstType::Int)),
+            op(BinOp::Multiplication, Some(AstType::Int)),
+            op(BinOp::Modulus, Some(AstType::U32)),
+            op(BinOp::Addition, None),
+        ]);
+        let m = module(vec![func("main", vec![], None, body)], vec![]);
+        let result = JsGenerator::generate(m).unwrap();
+        assert_eq!(
+            user_code(&result),
+            "function main(){
+((a / b) | 0);
+(Math.imul(a, b) | 0);
+((a % b) >>> 0);
+a + b;
+}
+
+main();"
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_evaluates_target_once() {
+        let call = |name: &str| -> Box<Expression> {
+            Box::new(
+                ExpressionKind::FunctionCall {
+                    fn_name: name.into(),
+                    args: vec![],
+                }
+                .into(),
+            )
+        };
+        let add = |lhs: Expression| {
+            StatementKind::Exp(
+                ExpressionKind::BinOp {
+                    lhs: Box::new(lhs),
+                    op: BinOp::AddAssign,
+                    rhs: Box::new(ExpressionKind::Int(1).into()),
+                    ty: Some(AstType::Int),
+                }
+                .into(),
+            )
+            .into()
+        };
+        let body = block(vec![
+            add(ExpressionKind::ArrayAccess {
+                expr: Box::new(ExpressionKind::Variable("arr".into()).into()),
+                index: call("idx"),
+            }
+            .into()),
+            add(ExpressionKind::FieldAccess {
+                expr: call("user"),
+                field: Box::new(ExpressionKind::Variable("age".into()).into()),
+            }
+            .into()),
+            add(ExpressionKind::ArrayAccess {
+                expr: Box::new(ExpressionKind::Variable("arr".into()).into()),
+                index: Box::new(ExpressionKind::Variable("i".into()).into()),
+            }
+            .into()),
+        ]);
+        let m = module(vec![func("main", vec![], None, body)], vec![]);
+        let result = JsGenerator::generate(m).unwrap();
+        assert_eq!(
+            user_code(&result),
+            "function main(){
+(($a, $i) => $a[$i] = (($a[$i] + 1) | 0))(arr, idx());
+(($o) => $o.age = (($o.age + 1) | 0))(user());
+arr[i] = ((arr[i] + 1) | 0);
+}
+
 main();"
         );
     }
@@ -540,11 +790,15 @@ main();"
 
     #[test]
     fn test_if_no_else() {
-        let body = block(vec![Statement::If {
-            condition: Expression::Bool(true),
-            body: Box::new(block(vec![Statement::Return(Some(Expression::Int(1)))])),
+        let body = block(vec![StatementKind::If {
+            condition: ExpressionKind::Bool(true).into(),
+            body: Box::new(block(vec![StatementKind::Return(Some(
+                ExpressionKind::Int(1).into(),
+            ))
+            .into()])),
             else_branch: None,
-        }]);
+        }
+        .into()]);
         let m = module(
             vec![
                 func("branching", vec![], Some(AstType::Int), body),
@@ -570,13 +824,18 @@ main();"
 
     #[test]
     fn test_if_with_else() {
-        let body = block(vec![Statement::If {
-            condition: Expression::Variable("flag".to_string()),
-            body: Box::new(block(vec![Statement::Return(Some(Expression::Int(1)))])),
-            else_branch: Some(Box::new(block(vec![Statement::Return(Some(
-                Expression::Int(0),
-            ))]))),
-        }]);
+        let body = block(vec![StatementKind::If {
+            condition: ExpressionKind::Variable("flag".to_string()).into(),
+            body: Box::new(block(vec![StatementKind::Return(Some(
+                ExpressionKind::Int(1).into(),
+            ))
+            .into()])),
+            else_branch: Some(Box::new(block(vec![StatementKind::Return(Some(
+                ExpressionKind::Int(0).into(),
+            ))
+            .into()]))),
+        }
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -615,10 +874,11 @@ main();"
 
     #[test]
     fn test_while_loop() {
-        let body = block(vec![Statement::While {
-            condition: Expression::Bool(true),
-            body: Box::new(block(vec![Statement::Break])),
-        }]);
+        let body = block(vec![StatementKind::While {
+            condition: ExpressionKind::Bool(true).into(),
+            body: Box::new(block(vec![StatementKind::Break.into()])),
+        }
+        .into()]);
         let m = module(
             vec![
                 func("loop_fn", vec![], None, body),
@@ -646,14 +906,20 @@ main();"
 
     #[test]
     fn test_for_loop() {
-        let body = block(vec![Statement::For {
+        let body = block(vec![StatementKind::For {
             ident: var("item", AstType::Int),
-            expr: Expression::Array {
+            expr: ExpressionKind::Array {
                 capacity: 3,
-                elements: vec![Expression::Int(1), Expression::Int(2), Expression::Int(3)],
-            },
+                elements: vec![
+                    ExpressionKind::Int(1).into(),
+                    ExpressionKind::Int(2).into(),
+                    ExpressionKind::Int(3).into(),
+                ],
+            }
+            .into(),
             body: Box::new(block(vec![])),
-        }]);
+        }
+        .into()]);
         let m = module(
             vec![
                 func("for_fn", vec![], None, body),
@@ -665,7 +931,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function for_fn(){
-var loop_orig_item = [1, 2, 3];
+let loop_orig_item = [1, 2, 3];
 for (let iter_item = 0; iter_item < loop_orig_item.length; iter_item++){
 let item = loop_orig_item[iter_item];
 }
@@ -685,13 +951,21 @@ main();"
 
     #[test]
     fn test_array_literal() {
-        let body = block(vec![Statement::Declare {
+        let body = block(vec![StatementKind::Declare {
             variable: var("nums", AstType::Array(Box::new(AstType::Int), None)),
-            value: Some(Expression::Array {
-                capacity: 3,
-                elements: vec![Expression::Int(1), Expression::Int(2), Expression::Int(3)],
-            }),
-        }]);
+            value: Some(
+                ExpressionKind::Array {
+                    capacity: 3,
+                    elements: vec![
+                        ExpressionKind::Int(1).into(),
+                        ExpressionKind::Int(2).into(),
+                        ExpressionKind::Int(3).into(),
+                    ],
+                }
+                .into(),
+            ),
+        }
+        .into()]);
         let m = module(
             vec![
                 func("arr_fn", vec![], None, body),
@@ -703,7 +977,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function arr_fn(){
-var nums = [1, 2, 3];
+let nums = [1, 2, 3];
 }
 
 function main(){
@@ -715,10 +989,14 @@ main();"
 
     #[test]
     fn test_array_access() {
-        let body = block(vec![Statement::Return(Some(Expression::ArrayAccess {
-            name: "arr".to_string(),
-            index: Box::new(Expression::Int(0)),
-        }))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::ArrayAccess {
+                expr: Box::new(ExpressionKind::Variable("arr".to_string()).into()),
+                index: Box::new(ExpressionKind::Int(0).into()),
+            }
+            .into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func(
@@ -741,6 +1019,44 @@ return arr[0];
 function main(){
 }
 
+main();"
+        );
+    }
+
+    #[test]
+    fn test_nested_array_access() {
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::ArrayAccess {
+                expr: Box::new(
+                    ExpressionKind::ArrayAccess {
+                        expr: Box::new(ExpressionKind::Variable("grid".to_string()).into()),
+                        index: Box::new(ExpressionKind::Int(1).into()),
+                    }
+                    .into(),
+                ),
+                index: Box::new(ExpressionKind::Int(0).into()),
+            }
+            .into(),
+        ))
+        .into()]);
+        let grid_ty = AstType::Array(Box::new(AstType::Array(Box::new(AstType::Int), None)), None);
+        let m = module(
+            vec![
+                func("cell", vec![var("grid", grid_ty)], Some(AstType::Int), body),
+                func("main", vec![], None, block(vec![])),
+            ],
+            vec![],
+        );
+        let result = JsGenerator::generate(m).unwrap();
+        assert_eq!(
+            user_code(&result),
+            "function cell(grid){
+return grid[1][0];
+}
+
+function main(){
+}
+
 main();"
         );
     }
@@ -755,6 +1071,7 @@ main();"
             name: "Point".to_string(),
             fields: vec![var("x", AstType::Int), var("y", AstType::Int)],
             methods: vec![],
+            span: Span::default(),
         };
         let m = module(
             vec![func("main", vec![], None, block(vec![]))],
@@ -777,14 +1094,18 @@ main();"
     #[test]
     fn test_struct_initialization() {
         let mut fields = HashMap::new();
-        fields.insert("x".to_string(), Box::new(Expression::Int(3)));
-        let body = block(vec![Statement::Declare {
+        fields.insert("x".to_string(), Box::new(ExpressionKind::Int(3).into()));
+        let body = block(vec![StatementKind::Declare {
             variable: var("p", AstType::Struct("Point".to_string())),
-            value: Some(Expression::StructInitialization {
-                name: "Point".to_string(),
-                fields,
-            }),
-        }]);
+            value: Some(
+                ExpressionKind::StructInitialization {
+                    name: "Point".to_string(),
+                    fields,
+                }
+                .into(),
+            ),
+        }
+        .into()]);
         let m = module(
             vec![
                 func("make_point", vec![], None, body),
@@ -796,7 +1117,7 @@ main();"
         assert_eq!(
             user_code(&result),
             "function make_point(){
-var p = new Point({x: 3,});
+let p = new Point({x: 3,});
 }
 
 function main(){
@@ -812,9 +1133,10 @@ main();"
 
     #[test]
     fn test_string_return() {
-        let body = block(vec![Statement::Return(Some(Expression::Str(
-            "hello".to_string(),
-        )))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::Str("hello".to_string()).into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("greet", vec![], Some(AstType::Str), body),
@@ -838,7 +1160,10 @@ main();"
 
     #[test]
     fn test_boolean_return() {
-        let body = block(vec![Statement::Return(Some(Expression::Bool(true)))]);
+        let body = block(vec![StatementKind::Return(Some(
+            ExpressionKind::Bool(true).into(),
+        ))
+        .into()]);
         let m = module(
             vec![
                 func("get_true", vec![], Some(AstType::Bool), body),
@@ -866,10 +1191,14 @@ main();"
 
     #[test]
     fn test_function_call_statement() {
-        let body = block(vec![Statement::Exp(Expression::FunctionCall {
-            fn_name: "print".to_string(),
-            args: vec![Expression::Str("hi".to_string())],
-        })]);
+        let body = block(vec![StatementKind::Exp(
+            ExpressionKind::FunctionCall {
+                fn_name: "print".to_string(),
+                args: vec![ExpressionKind::Str("hi".to_string()).into()],
+            }
+            .into(),
+        )
+        .into()]);
         let m = module(vec![func("main", vec![], None, body)], vec![]);
         let result = JsGenerator::generate(m).unwrap();
         assert_eq!(
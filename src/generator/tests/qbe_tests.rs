@@ -31,6 +31,8 @@ mod tests {
             arguments: Vec::new(),
             ret_type,
             body,
+            mutable_self: false,
+            span: Span::default(),
         }
     }
 
@@ -46,6 +48,8 @@ mod tests {
             arguments,
             ret_type,
             body,
+            mutable_self: false,
+            span: Span::default(),
         }
     }
 
@@ -54,63 +58,70 @@ mod tests {
         Variable {
             name: name.to_string(),
             ty: Some(typ),
+            span: Span::default(),
         }
     }
 
     fn create_int_expr(value: usize) -> Expression {
-        Expression::Int(value)
+        ExpressionKind::Int(value).into()
     }
 
     fn create_bool_expr(value: bool) -> Expression {
-        Expression::Bool(value)
+        ExpressionKind::Bool(value).into()
     }
 
     fn create_str_expr(value: &str) -> Expression {
-        Expression::Str(value.to_string())
+        ExpressionKind::Str(value.to_string()).into()
     }
 
     fn create_var_expr(name: &str) -> Expression {
-        Expression::Variable(name.to_string())
+        ExpressionKind::Variable(name.to_string()).into()
     }
 
     fn create_binop_expr(lhs: Expression, op: BinOp, rhs: Expression) -> Expression {
-        Expression::BinOp {
+        ExpressionKind::BinOp {
             lhs: Box::new(lhs),
             op,
             rhs: Box::new(rhs),
+            ty: None,
         }
+        .into()
     }
 
     fn create_call_expr(fn_name: &str, args: Vec<Expression>) -> Expression {
-        Expression::FunctionCall {
+        ExpressionKind::FunctionCall {
             fn_name: fn_name.to_string(),
             args,
         }
+        .into()
     }
 
     fn create_return_stmt(expr: Option<Expression>) -> Statement {
-        Statement::Return(expr)
+        StatementKind::Return(expr).into()
     }
 
     fn create_declare_stmt(name: &str, typ: AstType, value: Option<Expression>) -> Statement {
-        Statement::Declare {
+        StatementKind::Declare {
             variable: create_variable(name, typ),
             value,
         }
+        .into()
     }
 
     fn create_assign_stmt(lhs: Expression, rhs: Expression) -> Statement {
-        Statement::Assign {
+        StatementKind::Assign {
             lhs: Box::new(lhs),
             rhs: Box::new(rhs),
         }
+        .into()
     }
 
     fn create_block_stmt(statements: Vec<Statement>) -> Statement {
-        Statement::Block {
+        StatementKind::Block {
             statements,
             scope: Vec::new(),
         }
+        .into()
     }
 
     fn create_if_stmt(
@@ -118,18 +129,20 @@ mod tests {
         body: Statement,
         else_branch: Option<Statement>,
     ) -> Statement {
-        Statement::If {
+        StatementKind::If {
             condition,
             body: Box::new(body),
             else_branch: else_branch.map(Box::new),
         }
+        .into()
     }
 
     fn create_while_stmt(condition: Expression, body: Statement) -> Statement {
-        Statement::While {
+        StatementKind::While {
             condition,
             body: Box::new(body),
         }
+        .into()
     }
 
     fn create_struct_def(name: &str, fields: Vec<Variable>) -> StructDef {
@@ -137,6 +150,7 @@ mod tests {
             name: name.to_string(),
             fields,
             methods: Vec::new(),
+            span: Span::default(),
         }
     }
 
@@ -149,6 +163,7 @@ mod tests {
             name: name.to_string(),
             fields,
             methods,
+            span: Span::default(),
         }
     }
 
@@ -277,7 +292,6 @@ mod tests {
             (BinOp::Addition, "add"),
             (BinOp::Subtraction, "sub"),
             (BinOp::Multiplication, "mul"),
-            (BinOp::Division, "div"),
         ];
 
         for (op, op_name) in operations {
@@ -308,6 +322,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_signed_division_by_minus_one() {
+        // A divisor of -1 becomes 1 and the quotient is negated, so that
+        // dividing the smallest value does not trap
+        let operations = vec![
+            (
+                BinOp::Division,
+                "div",
+                "%tmp.11 =w mul %tmp.5, %tmp.10\nret %tmp.11",
+            ),
+            (BinOp::Modulus, "rem", "ret %tmp.5"),
+        ];
+
+        for (op, op_name, result) in operations {
+            let decl_a = create_declare_stmt("a", AstType::Int, Some(create_int_expr(10)));
+            let decl_b = create_declare_stmt("b", AstType::Int, Some(create_int_expr(5)));
+            let binop_expr = create_binop_expr(create_var_expr("a"), op, create_var_expr("b"));
+            let ret_stmt = create_return_stmt(Some(binop_expr));
+            let block = create_block_stmt(vec![decl_a, decl_b, ret_stmt]);
+            let func = create_function(&format!("test_{}", op_name), Some(AstType::Int), block);
+            let module = create_module(vec![func], Vec::new());
+            let result_ir = QbeGenerator::generate(module).unwrap();
+
+            let expected = normalize_qbe(&format!(
+                r#"
+                export function w $test_{op_name}() {{
+                @start
+                    %tmp.2 =w copy 10
+                    %tmp.1 =w copy %tmp.2
+                    %tmp.4 =w copy 5
+                    %tmp.3 =w copy %tmp.4
+                    %tmp.6 =w add %tmp.3, 1
+                    %tmp.7 =w ceqw %tmp.6, 0
+                    %tmp.8 =w mul %tmp.7, 2
+                    %tmp.9 =w add %tmp.3, %tmp.8
+                    %tmp.10 =w sub 1, %tmp.8
+                    %tmp.5 =w {op_name} %tmp.1, %tmp.9
+                    {result}
+                }}
+            "#
+            ));
+
+            assert_eq!(normalize_qbe(&result_ir), expected);
+        }
+    }
+
     #[test]
     fn test_comparison_operations() {
         let operations = vec![
@@ -585,7 +645,7 @@ mod tests {
         let decl_i = create_declare_stmt("i", AstType::Int, Some(create_int_expr(0)));
         let if_break = create_if_stmt(
             create_binop_expr(create_var_expr("i"), BinOp::Equal, create_int_expr(5)),
-            Statement::Break,
+            StatementKind::Break.into(),
             None,
         );
         let if_continue = create_if_stmt(
@@ -594,7 +654,7 @@ mod tests {
                 BinOp::Equal,
                 create_int_expr(0),
             ),
-            Statement::Continue,
+            StatementKind::Continue.into(),
             None,
         );
         let loop_body = create_block_stmt(vec![
@@ -707,7 +767,7 @@ mod tests {
     #[test]
     fn test_function_call() {
         let call_expr = create_call_expr("print", vec![create_str_expr("Hello, world!")]);
-        let stmt = Statement::Exp(call_expr);
+        let stmt = StatementKind::Exp(call_expr).into();
         let func = create_function("test_call", None, create_block_stmt(vec![stmt]));
         let module = create_module(vec![func], Vec::new());
         let result = QbeGenerator::generate(module).unwrap();
@@ -726,6 +786,69 @@ mod tests {
         assert_eq!(normalize_qbe(&result), expected);
     }
 
+    #[test]
+    fn test_array_literal_argument_is_pointer() {
+        let array = ExpressionKind::Array {
+            capacity: 1,
+            elements: vec![create_int_expr(7)],
+        }
+        .into();
+        let stmt = StatementKind::Exp(create_call_expr("count", vec![array])).into();
+        let func = create_function("test_call", None, create_block_stmt(vec![stmt]));
+        let module = create_module(vec![func], Vec::new());
+        let result = QbeGenerator::generate(module).unwrap();
+
+        let expected = normalize_qbe(
+            r#"
+            type :array.4 = { l, w }
+            export function $test_call() {
+            @start
+                %tmp.1 =w copy 7
+                %tmp.2 =l alloc8 12
+                storel 1, %tmp.2
+                %tmp.3 =l add %tmp.2, 8
+                storew %tmp.1, %tmp.3
+                %tmp.5 =w call $count(l %tmp.2)
+                ret
+            }
+        "#,
+        );
+
+        assert_eq!(normalize_qbe(&result), expected);
+    }
+
+    #[test]
+    fn test_cast_to_any_widens_to_long() {
+        // Every `any` value is a long, so signed integers are sign extended
+        let cast = ExpressionKind::Cast {
+            expr: Box::new(create_var_expr("n")),
+            from: AstType::Int,
+            to: AstType::Any,
+        }
+        .into();
+        let decl = create_declare_stmt("n", AstType::Int, Some(create_int_expr(7)));
+        let stmt = StatementKind::Exp(create_call_expr("show", vec![cast])).into();
+        let func = create_function("test_call", None, create_block_stmt(vec![decl, stmt]));
+        let module = create_module(vec![func], Vec::new());
+        let result = QbeGenerator::generate(module).unwrap();
+
+        let expected = normalize_qbe(
+            r#"
+            export function $test_call() {
+            @start
+                %tmp.2 =w copy 7
+                %tmp.1 =w copy %tmp.2
+                %tmp.4 =l extsw %tmp.1
+                %tmp.3 =l copy %tmp.4
+                %tmp.5 =w call $show(l %tmp.3)
+                ret
+            }
+        "#,
+        );
+
+        assert_eq!(normalize_qbe(&result), expected);
+    }
+
     #[test]
     fn test_compound_expressions() {
         let expr = create_binop_expr(
@@ -788,7 +911,8 @@ mod tests {
 
     #[test]
     fn test_expression_bodied_string_concat() {
-        // Mirrors: fn greet(name: string) = "Hello " + name  (no explicit return type)
+        // Mirrors: fn greet(name: string) = "Hello " + name
+        // The return type is inferred by the frontend
         let arg = create_variable("name", AstType::Str);
         let concat = create_binop_expr(
             create_str_expr("Hello "),
@@ -796,7 +920,7 @@ mod tests {
             create_var_expr("name"),
         );
         let body = create_block_stmt(vec![create_return_stmt(Some(concat))]);
-        let greet = create_function_with_args("greet", vec![arg], None, body);
+        let greet = create_function_with_args("greet", vec![arg], Some(AstType::Str), body);
         let module = create_module(vec![greet], Vec::new());
         let result = QbeGenerator::generate(module).unwrap();
 
@@ -931,7 +1055,7 @@ mod tests {
     fn test_function_call_unknown_defaults_to_word() {
         // Calling an unknown/external function should fall back to Word
         let call_expr = create_call_expr("unknown_fn", vec![create_int_expr(42)]);
-        let stmt = Statement::Exp(call_expr);
+        let stmt = StatementKind::Exp(call_expr).into();
         let func = create_function("test", None, create_block_stmt(vec![stmt]));
         let module = create_module(vec![func], Vec::new());
         let result = QbeGenerator::generate(module).unwrap();
@@ -956,23 +1080,25 @@ mod tests {
         //     let arr: int[3] = [10, 20, 30]
         //     return arr[1]
         // }
-        let array_expr = Expression::Array {
+        let array_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_int_expr(10),
                 create_int_expr(20),
                 create_int_expr(30),
             ],
-        };
+        }
+        .into();
         let decl = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
             Some(array_expr),
         );
-        let access = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let access = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(1)),
-        };
+        }
+        .into();
         let ret = create_return_stmt(Some(access));
         let block = create_block_stmt(vec![decl, ret]);
         let func = create_function("test_arr_read", Some(AstType::Int), block);
@@ -1016,19 +1142,21 @@ mod tests {
         //     let arr: int[3] = [0, 0, 0]
         //     arr[0] = 42
         // }
-        let array_expr = Expression::Array {
+        let array_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![create_int_expr(0), create_int_expr(0), create_int_expr(0)],
-        };
+        }
+        .into();
         let decl = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
             Some(array_expr),
         );
-        let lhs = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let lhs = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(0)),
-        };
+        }
+        .into();
         let assign = create_assign_stmt(lhs, create_int_expr(42));
         let block = create_block_stmt(vec![decl, assign]);
         let func = create_function("test_arr_write", None, block);
@@ -1102,10 +1230,13 @@ mod tests {
     fn test_method_call_codegen() {
         // struct Counter { count: int; fn get(): int { return self.count } }
         // fn test(): int { let c: Counter = new Counter { count: 5 }; return c.get() }
-        let get_body = create_return_stmt(Some(Expression::FieldAccess {
-            expr: Box::new(Expression::Selff),
-            field: Box::new(Expression::Variable("count".to_string())),
-        }));
+        let get_body = create_return_stmt(Some(
+            ExpressionKind::FieldAccess {
+                expr: Box::new(ExpressionKind::Selff.into()),
+                field: Box::new(ExpressionKind::Variable("count".to_string()).into()),
+            }
+            .into(),
+        ));
         let counter_struct = create_struct_def_with_methods(
             "Counter",
             vec![create_variable("count", AstType::Int)],
@@ -1117,24 +1248,32 @@ mod tests {
             )],
         );
 
-        let call_expr = Expression::FieldAccess {
-            expr: Box::new(Expression::Variable("c".to_string())),
-            field: Box::new(Expression::FunctionCall {
-                fn_name: "get".to_string(),
-                args: vec![],
-            }),
-        };
+        let call_expr = ExpressionKind::FieldAccess {
+            expr: Box::new(ExpressionKind::Variable("c".to_string()).into()),
+            field: Box::new(
+                ExpressionKind::FunctionCall {
+                    fn_name: "get".to_string(),
+                    args: vec![],
+                }
+                .into(),
+            ),
+        }
+        .into();
         let test_body = create_block_stmt(vec![
-            Statement::Declare {
+            StatementKind::Declare {
                 variable: create_variable("c", AstType::Struct("Counter".to_string())),
-                value: Some(Expression::StructInitialization {
-                    name: "Counter".to_string(),
-                    fields: std::collections::HashMap::from([(
-                        "count".to_string(),
-                        Box::new(create_int_expr(5)),
-                    )]),
-                }),
-            },
+                value: Some(
+                    ExpressionKind::StructInitialization {
+                        name: "Counter".to_string(),
+                        fields: std::collections::HashMap::from([(
+                            "count".to_string(),
+                            Box::new(create_int_expr(5)),
+                        )]),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
             create_return_stmt(Some(call_expr)),
         ]);
         let test_func = create_function("test", Some(AstType::Int), test_body);
@@ -1169,10 +1308,13 @@ mod tests {
     #[test]
     fn test_self_field_access_in_method() {
         // struct Point { x: int; fn get_x(): int { return self.x } }
-        let get_x_body = create_return_stmt(Some(Expression::FieldAccess {
-            expr: Box::new(Expression::Selff),
-            field: Box::new(Expression::Variable("x".to_string())),
-        }));
+        let get_x_body = create_return_stmt(Some(
+            ExpressionKind::FieldAccess {
+                expr: Box::new(ExpressionKind::Selff.into()),
+                field: Box::new(ExpressionKind::Variable("x".to_string()).into()),
+            }
+            .into(),
+        ));
         let point_struct = create_struct_def_with_methods(
             "Point",
             vec![create_variable("x", AstType::Int)],
@@ -1210,9 +1352,14 @@ mod tests {
 
     #[test]
     fn test_boolean_operations() {
-        let operations = vec![(BinOp::And, "and"), (BinOp::Or, "or")];
+        // The right hand side is only evaluated if the left hand side does
+        // not decide the result already
+        let operations = vec![
+            (BinOp::And, "and", "@logic.5.rhs, @logic.5.end"),
+            (BinOp::Or, "or", "@logic.5.end, @logic.5.rhs"),
+        ];
 
-        for (op, op_name) in operations {
+        for (op, op_name, targets) in operations {
             let decl_a = create_declare_stmt("a", AstType::Bool, Some(create_bool_expr(true)));
             let decl_b = create_declare_stmt("b", AstType::Bool, Some(create_bool_expr(false)));
             let binop_expr = create_binop_expr(create_var_expr("a"), op, create_var_expr("b"));
@@ -1230,8 +1377,12 @@ mod tests {
                     %tmp.1 =w copy %tmp.2
                     %tmp.4 =w copy 0
                     %tmp.3 =w copy %tmp.4
-                    %tmp.5 =w {op_name} %tmp.1, %tmp.3
-                    ret %tmp.5
+                    %tmp.6 =w copy %tmp.1
+                    jnz %tmp.6, {targets}
+                @logic.5.rhs
+                    %tmp.6 =w copy %tmp.3
+                @logic.5.end
+                    ret %tmp.6
                 }}
             "#
             ));
@@ -1240,6 +1391,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_short_circuit_skips_call() {
+        // fn guarded(): bool { return false && side_effect() }
+        let call = create_call_expr("side_effect", vec![]);
+        let binop_expr = create_binop_expr(create_bool_expr(false), BinOp::And, call);
+        let func = create_function(
+            "guarded",
+            Some(AstType::Bool),
+            create_return_stmt(Some(binop_expr)),
+        );
+        let module = create_module(vec![func], Vec::new());
+        let result = normalize_qbe(&QbeGenerator::generate(module).unwrap());
+
+        let (before, after) = result.split_once("@logic.2.rhs").unwrap();
+        assert!(!before.contains("call $side_effect"));
+        assert!(after.contains("call $side_effect"));
+    }
+
     #[test]
     fn test_any_type_parameter_int_widening() {
         // Define print_any(x: any) with empty body
@@ -1252,7 +1421,11 @@ mod tests {
 
         // Define main() that calls print_any(5) — int (Word) must be widened to Long
         let call = create_call_expr("print_any", vec![create_int_expr(5)]);
-        let main_fn = create_function("main", None, create_block_stmt(vec![Statement::Exp(call)]));
+        let main_fn = create_function(
+            "main",
+            None,
+            create_block_stmt(vec![StatementKind::Exp(call).into()]),
+        );
 
         let module = create_module(vec![print_any, main_fn], Vec::new());
         let result = QbeGenerator::generate(module).unwrap();
@@ -1290,7 +1463,11 @@ mod tests {
 
         // Define main() that calls print_any("hello") — string is already Long, no widening
         let call = create_call_expr("print_any", vec![create_str_expr("hello")]);
-        let main_fn = create_function("main", None, create_block_stmt(vec![Statement::Exp(call)]));
+        let main_fn = create_function(
+            "main",
+            None,
+            create_block_stmt(vec![StatementKind::Exp(call).into()]),
+        );
 
         let module = create_module(vec![print_any, main_fn], Vec::new());
         let result = QbeGenerator::generate(module).unwrap();
@@ -1320,23 +1497,25 @@ mod tests {
     fn test_array_access_read() {
         // let arr: int[] = [10, 20, 30]
         // return arr[1]
-        let arr_expr = Expression::Array {
+        let arr_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_int_expr(10),
                 create_int_expr(20),
                 create_int_expr(30),
             ],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
             Some(arr_expr),
         );
-        let access_expr = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let access_expr = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(1)),
-        };
+        }
+        .into();
         let ret_stmt = create_return_stmt(Some(access_expr));
         let block = create_block_stmt(vec![decl_arr, ret_stmt]);
         let func = create_function("test_arr_read", Some(AstType::Int), block);
@@ -1362,30 +1541,33 @@ mod tests {
         // let arr: int[] = [10, 20, 30]
         // arr[0] = 99
         // return arr[0]
-        let arr_expr = Expression::Array {
+        let arr_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_int_expr(10),
                 create_int_expr(20),
                 create_int_expr(30),
             ],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
             Some(arr_expr),
         );
         let assign_stmt = create_assign_stmt(
-            Expression::ArrayAccess {
-                name: "arr".to_string(),
+            ExpressionKind::ArrayAccess {
+                expr: Box::new(create_var_expr("arr")),
                 index: Box::new(create_int_expr(0)),
-            },
+            }
+            .into(),
             create_int_expr(99),
         );
-        let access_expr = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let access_expr = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(0)),
-        };
+        }
+        .into();
         let ret_stmt = create_return_stmt(Some(access_expr));
         let block = create_block_stmt(vec![decl_arr, assign_stmt, ret_stmt]);
         let func = create_function("test_arr_write", Some(AstType::Int), block);
@@ -1413,16 +1595,18 @@ mod tests {
         let decl_arr =
             create_declare_stmt("arr", AstType::Array(Box::new(AstType::Int), Some(3)), None);
         let assign_stmt = create_assign_stmt(
-            Expression::ArrayAccess {
-                name: "arr".to_string(),
+            ExpressionKind::ArrayAccess {
+                expr: Box::new(create_var_expr("arr")),
                 index: Box::new(create_int_expr(0)),
-            },
+            }
+            .into(),
             create_int_expr(42),
         );
-        let access_expr = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let access_expr = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(0)),
-        };
+        }
+        .into();
         let ret_stmt = create_return_stmt(Some(access_expr));
         let block = create_block_stmt(vec![decl_arr, assign_stmt, ret_stmt]);
         let func = create_function("test_uninit_arr", Some(AstType::Int), block);
@@ -1463,14 +1647,15 @@ mod tests {
         // let sum: int = 0
         // for x in arr { sum = sum + x }
         // return sum
-        let arr_expr = Expression::Array {
+        let arr_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_int_expr(10),
                 create_int_expr(20),
                 create_int_expr(30),
             ],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
@@ -1485,11 +1670,12 @@ mod tests {
                 create_var_expr("x"),
             ),
         )]);
-        let for_stmt = Statement::For {
+        let for_stmt = StatementKind::For {
             ident: create_variable("x", AstType::Int),
             expr: create_var_expr("arr"),
             body: Box::new(loop_body),
-        };
+        }
+        .into();
         let ret_stmt = create_return_stmt(Some(create_var_expr("sum")));
         let block = create_block_stmt(vec![decl_arr, decl_sum, for_stmt, ret_stmt]);
         let func = create_function("test_for", Some(AstType::Int), block);
@@ -1514,25 +1700,27 @@ mod tests {
         //     let arr: string[] = ["One", "Two", "Three"]
         //     for x in arr { println(x) }
         // }
-        let arr_expr = Expression::Array {
+        let arr_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_str_expr("One"),
                 create_str_expr("Two"),
                 create_str_expr("Three"),
             ],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Str), Some(3)),
             Some(arr_expr),
         );
-        let loop_body = create_block_stmt(vec![Statement::Return(None)]);
-        let for_stmt = Statement::For {
+        let loop_body = create_block_stmt(vec![StatementKind::Return(None).into()]);
+        let for_stmt = StatementKind::For {
             ident: create_variable("x", AstType::Str),
             expr: create_var_expr("arr"),
             body: Box::new(loop_body),
-        };
+        }
+        .into();
         let block = create_block_stmt(vec![decl_arr, for_stmt]);
         let func = create_function("main", None, block);
         let module = create_module(vec![func], Vec::new());
@@ -1586,14 +1774,15 @@ mod tests {
         //     let n: int = len(arr)
         //     return n
         // }
-        let array_expr = Expression::Array {
+        let array_expr = ExpressionKind::Array {
             capacity: 3,
             elements: vec![
                 create_int_expr(10),
                 create_int_expr(20),
                 create_int_expr(30),
             ],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Int), Some(3)),
@@ -1699,16 +1888,18 @@ mod tests {
             create_struct_def("Flags", vec![create_variable("active", AstType::Bool)]);
         let mut fields = std::collections::HashMap::new();
         fields.insert("active".to_string(), Box::new(create_bool_expr(true)));
-        let struct_init = Expression::StructInitialization {
+        let struct_init = ExpressionKind::StructInitialization {
             name: "Flags".to_string(),
             fields,
-        };
+        }
+        .into();
         let decl =
             create_declare_stmt("f", AstType::Struct("Flags".to_string()), Some(struct_init));
-        let field_access = Expression::FieldAccess {
+        let field_access = ExpressionKind::FieldAccess {
             expr: Box::new(create_var_expr("f")),
-            field: Box::new(Expression::Variable("active".to_string())),
-        };
+            field: Box::new(ExpressionKind::Variable("active".to_string()).into()),
+        }
+        .into();
         let ret = create_return_stmt(Some(field_access));
         let block = create_block_stmt(vec![decl, ret]);
         let func = create_function("test", Some(AstType::Bool), block);
@@ -1741,19 +1932,21 @@ mod tests {
         //     let arr: bool[2] = [true, false]
         //     return arr[0]
         // }
-        let array_expr = Expression::Array {
+        let array_expr = ExpressionKind::Array {
             capacity: 2,
             elements: vec![create_bool_expr(true), create_bool_expr(false)],
-        };
+        }
+        .into();
         let decl = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Bool), Some(2)),
             Some(array_expr),
         );
-        let access = Expression::ArrayAccess {
-            name: "arr".to_string(),
+        let access = ExpressionKind::ArrayAccess {
+            expr: Box::new(create_var_expr("arr")),
             index: Box::new(create_int_expr(0)),
-        };
+        }
+        .into();
         let ret = create_return_stmt(Some(access));
         let block = create_block_stmt(vec![decl, ret]);
         let func = create_function("test_arr_read", Some(AstType::Bool), block);
@@ -1794,20 +1987,22 @@ mod tests {
         //     let arr: bool[2] = [true, false]
         //     for x in arr { }
         // }
-        let arr_expr = Expression::Array {
+        let arr_expr = ExpressionKind::Array {
             capacity: 2,
             elements: vec![create_bool_expr(true), create_bool_expr(false)],
-        };
+        }
+        .into();
         let decl_arr = create_declare_stmt(
             "arr",
             AstType::Array(Box::new(AstType::Bool), Some(2)),
             Some(arr_expr),
         );
-        let for_stmt = Statement::For {
+        let for_stmt = StatementKind::For {
             ident: create_variable("x", AstType::Bool),
             expr: create_var_expr("arr"),
             body: Box::new(create_block_stmt(vec![])),
-        };
+        }
+        .into();
         let block = create_block_stmt(vec![decl_arr, for_stmt]);
         let func = create_function("test", None, block);
         let module = create_module(vec![func], Vec::new());
@@ -1880,29 +2075,33 @@ mod tests {
             )],
         );
 
-        let inner_init = Expression::StructInitialization {
+        let inner_init = ExpressionKind::StructInitialization {
             name: "Inner".to_string(),
             fields: std::collections::HashMap::from([(
                 "value".to_string(),
                 Box::new(create_int_expr(42)),
             )]),
-        };
-        let inner_decl = Statement::Declare {
+        }
+        .into();
+        let inner_decl = StatementKind::Declare {
             variable: create_variable("i", AstType::Struct("Inner".to_string())),
             value: Some(inner_init),
-        };
+        }
+        .into();
 
-        let wrapper_init = Expression::StructInitialization {
+        let wrapper_init = ExpressionKind::StructInitialization {
             name: "Wrapper".to_string(),
             fields: std::collections::HashMap::from([(
                 "inner".to_string(),
                 Box::new(create_var_expr("i")),
             )]),
-        };
-        let wrapper_decl = Statement::Declare {
+        }
+        .into();
+        let wrapper_decl = StatementKind::Declare {
             variable: create_variable("w", AstType::Struct("Wrapper".to_string())),
             value: Some(wrapper_init),
-        };
+        }
+        .into();
 
         let func = create_function(
             "test_nested",
@@ -1934,4 +2133,64 @@ mod tests {
 
         assert_eq!(normalize_qbe(&result), expected);
     }
+
+    #[test]
+    fn test_nested_struct_field_is_not_loaded() {
+        let inner_struct = create_struct_def("Inner", vec![create_variable("value", AstType::Int)]);
+        let wrapper_struct = create_struct_def(
+            "Wrapper",
+            vec![create_variable(
+                "inner",
+                AstType::Struct("Inner".to_string()),
+            )],
+        );
+        let inner_field = || {
+            ExpressionKind::FieldAccess {
+                expr: Box::new(create_var_expr("w")),
+                field: Box::new(create_var_expr("inner")),
+            }
+            .into()
+        };
+
+        // fn test_nested(w: Wrapper, i: Inner): Inner {
+        //     w.inner = i
+        //     return w.inner
+        // }
+        let func = Function {
+            name: "test_nested".to_string(),
+            arguments: vec![
+                create_variable("w", AstType::Struct("Wrapper".to_string())),
+                create_variable("i", AstType::Struct("Inner".to_string())),
+            ],
+            body: create_block_stmt(vec![
+                StatementKind::Assign {
+                    lhs: Box::new(inner_field()),
+                    rhs: Box::new(create_var_expr("i")),
+                }
+                .into(),
+                StatementKind::Return(Some(inner_field())).into(),
+            ]),
+            ret_type: Some(AstType::Struct("Inner".to_string())),
+            mutable_self: false,
+            span: Span::default(),
+        };
+        let module = create_module(vec![func], vec![inner_struct, wrapper_struct]);
+        let result = QbeGenerator::generate(module).unwrap();
+
+        let expected = normalize_qbe(
+            r#"
+            type :struct.1 = align 4 { w }
+            type :struct.2 = align 4 { :struct.1 }
+            export function l $test_nested(:struct.2 %tmp.3, :struct.1 %tmp.4) {
+            @start
+                %tmp.5 =l add %tmp.3, 0
+                blit %tmp.4, %tmp.5, 4
+                %tmp.6 =l add %tmp.3, 0
+                ret %tmp.6
+            }
+        "#,
+        );
+
+        assert_eq!(normalize_qbe(&result), expected);
+    }
 }
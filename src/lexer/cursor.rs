@@ -68,6 +68,11 @@ impl<'a> Cursor<'a> {
         self.nth_char(0)
     }
 
+    /// Peeks the symbol after the next one without consuming anything.
+    pub(crate) fn second(&self) -> char {
+        self.nth_char(1)
+    }
+
     /// Checks if there is nothing more to consume.
     pub(crate) fn is_eof(&self) -> bool {
         self.chars.as_str().is_empty()
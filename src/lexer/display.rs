@@ -18,7 +18,12 @@ impl std::fmt::Display for Keyword {
             Keyword::New => write!(f, "new"),
             Keyword::Match => write!(f, "match"),
             Keyword::Import => write!(f, "import"),
+            Keyword::Type => write!(f, "type"),
+            Keyword::Newtype => write!(f, "newtype"),
+            Keyword::As => write!(f, "as"),
             Keyword::Selff => write!(f, "self"), // "self"
+            Keyword::Mut => write!(f, "mut"),
+            Keyword::Var => write!(f, "var"),
             Keyword::Unknown => write!(f, "unknown"),
         }
     }
@@ -41,6 +46,7 @@ impl std::fmt::Display for TokenKind {
             TokenKind::Colon => write!(f, ":"),
             TokenKind::SemiColon => write!(f, ";"),
             TokenKind::Dot => write!(f, "."),
+            TokenKind::Ellipsis => write!(f, "..."),
             TokenKind::Exclamation => write!(f, "!"),
             TokenKind::Comma => write!(f, ","),
             TokenKind::Assign => writeln!(f, "="),
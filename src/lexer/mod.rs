@@ -16,6 +16,8 @@
 pub(crate) mod cursor;
 
 use self::TokenKind::*;
+use crate::ast::span::{FileId, Span};
+use crate::diagnostic::{codes, Diagnostic};
 use cursor::Cursor;
 
 #[cfg(test)]
@@ -27,6 +29,8 @@ mod display;
 pub struct Token {
     pub kind: TokenKind,
     pub len: usize,
+    /// Byte offset of the token in the source
+    pub start: usize,
     pub raw: String,
     pub pos: Position,
 }
@@ -37,6 +41,7 @@ impl Token {
             kind,
             len,
             raw,
+            start: 0,
             pos,
         }
     }
@@ -76,6 +81,8 @@ pub enum TokenKind {
     SemiColon,
     /// "."
     Dot,
+    /// "..."
+    Ellipsis,
     /// "!"
     Exclamation,
     /// ","
@@ -151,12 +158,23 @@ pub enum Keyword {
     New,
     Match,
     Import,
+    Type,
+    Newtype,
+    As,
     Selff, // "self"
+    Mut,
+    Var,
     Unknown,
 }
 
-/// Creates an iterator that produces tokens from the input string.
-pub fn tokenize(mut input: &str) -> Result<Vec<Token>, String> {
+/// Splits source that is not read from a file into tokens
+#[cfg(test)]
+pub fn tokenize(input: &str) -> Result<Vec<Token>, Diagnostic> {
+    tokenize_file(input, FileId::default())
+}
+
+/// Splits the contents of `file` into tokens
+pub fn tokenize_file(mut input: &str, file: FileId) -> Result<Vec<Token>, Diagnostic> {
     let mut pos = Position {
         raw: usize::MAX,
         line: 1,
@@ -164,21 +182,22 @@ pub fn tokenize(mut input: &str) -> Result<Vec<Token>, String> {
     };
 
     let mut tokens: Vec<Token> = Vec::new();
+    let mut start = 0;
     while !input.is_empty() {
-        let token = first_token(input, &mut pos)?;
+        let mut cursor = Cursor::new(input, &mut pos);
+        let mut token = cursor.advance_token().map_err(|err| {
+            // The token that could not be read, as far as it got
+            err.located_at(Span::new(file, start, start + cursor.len_consumed()))
+        })?;
         input = &input[token.len..];
+        token.start = start;
+        start += token.len;
         tokens.push(token);
     }
 
     Ok(tokens)
 }
 
-/// Parses the first token from the provided input string.
-pub fn first_token(input: &str, pos: &mut Position) -> Result<Token, String> {
-    debug_assert!(!input.is_empty());
-    Cursor::new(input, pos).advance_token()
-}
-
 pub fn is_whitespace(c: char) -> bool {
     // https://doc.rust-lang.org/reference/whitespace.html
     matches!(
@@ -222,7 +241,7 @@ pub fn is_id_continue(c: char) -> bool {
 
 impl Cursor<'_> {
     /// Parses a token from the input string.
-    fn advance_token(&mut self) -> Result<Token, String> {
+    fn advance_token(&mut self) -> Result<Token, Diagnostic> {
         // Original chars used to identify the token later on
         let original_chars = self.chars();
         // FIXME: Identical value, since it will be used twice and is not clonable later
@@ -232,7 +251,14 @@ impl Cursor<'_> {
             c if is_whitespace(c) => self.whitespace(),
             '0'..='9' => self.number(),
             '"' | '\'' => self.string(first_char)?,
-            '.' => Dot,
+            '.' => match (self.first(), self.second()) {
+                ('.', '.') => {
+                    self.bump();
+                    self.bump();
+                    Ellipsis
+                }
+                _ => Dot,
+            },
             '+' => match self.first() {
                 '=' => {
                     self.bump();
@@ -384,10 +410,14 @@ impl Cursor<'_> {
                 self.eat_digits();
             }
         };
+        // Type suffix, e.g. `255u8` or `1_000i64`
+        if matches!(self.first(), 'i' | 'u') {
+            self.eat_while(is_id_continue);
+        }
         TokenKind::Literal(Value::Int)
     }
 
-    fn string(&mut self, end: char) -> Result<TokenKind, String> {
+    fn string(&mut self, end: char) -> Result<TokenKind, Diagnostic> {
         Ok(TokenKind::Literal(Value::Str(self.eat_string(end)?)))
     }
 
@@ -416,7 +446,12 @@ impl Cursor<'_> {
             c if c == "new" => Keyword::New,
             c if c == "match" => Keyword::Match,
             c if c == "import" => Keyword::Import,
+            c if c == "type" => Keyword::Type,
+            c if c == "newtype" => Keyword::Newtype,
+            c if c == "as" => Keyword::As,
             c if c == "self" => Keyword::Selff,
+            c if c == "mut" => Keyword::Mut,
+            c if c == "var" => Keyword::Var,
             _ => Keyword::Unknown,
         }
     }
@@ -497,7 +532,7 @@ impl Cursor<'_> {
         has_digits
     }
 
-    fn eat_escape(&mut self) -> Result<char, String> {
+    fn eat_escape(&mut self) -> Result<char, Diagnostic> {
         let ch = self.first();
         let ch = match ch {
             'n' => '\n',       // Newline
@@ -507,7 +542,10 @@ impl Cursor<'_> {
             't' => '\t',       // Horizontal tab
             '"' | '\\' => ch,
             ch => {
-                return Err(self.make_error_msg(format!("Unknown escape sequence \\{}", ch)));
+                return Err(
+                    Diagnostic::error(format!("Unknown escape sequence `\\{}`", ch))
+                        .with_code(codes::SYNTAX),
+                );
             }
         };
         self.bump();
@@ -515,14 +553,20 @@ impl Cursor<'_> {
         Ok(ch)
     }
 
-    fn eat_string(&mut self, end: char) -> Result<String, String> {
+    fn eat_string(&mut self, end: char) -> Result<String, Diagnostic> {
         let mut buf = String::new();
         loop {
             if self.is_eof() {
-                return Err(self.make_error_msg("Unterminated string literal".into()));
+                return Err(
+                    Diagnostic::error("Unterminated string literal").with_code(codes::SYNTAX)
+                );
             }
             match self.first() {
-                '\n' => return Err(self.make_error_msg("String does not end on same line".into())),
+                '\n' => {
+                    return Err(Diagnostic::error("String does not end on the same line")
+                        .with_code(codes::SYNTAX)
+                        .with_help(format!("close the string with `{}`", end)))
+                }
                 '\\' => {
                     self.bump();
                     buf.push(self.eat_escape()?)
@@ -540,9 +584,4 @@ impl Cursor<'_> {
 
         Ok(buf)
     }
-
-    fn make_error_msg(&self, msg: String) -> String {
-        let pos = self.pos();
-        format!("{}:{}: {}", pos.line, pos.offset, msg)
-    }
 }
@@ -24,6 +24,7 @@ fn test_basic_tokenizing() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "1".to_owned(),
             pos: Position {
@@ -38,6 +39,7 @@ fn test_basic_tokenizing() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 1,
             kind: TokenKind::Whitespace,
             raw: " ".to_owned(),
             pos: Position {
@@ -52,6 +54,7 @@ fn test_basic_tokenizing() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 2,
             kind: TokenKind::Assign,
             raw: "=".to_owned(),
             pos: Position {
@@ -66,6 +69,7 @@ fn test_basic_tokenizing() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 3,
             kind: TokenKind::Whitespace,
             raw: " ".to_owned(),
             pos: Position {
@@ -80,6 +84,7 @@ fn test_basic_tokenizing() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 4,
             kind: TokenKind::Literal(Value::Int),
             raw: "2".to_owned(),
             pos: Position {
@@ -99,6 +104,7 @@ fn test_tokenizing_without_whitespace() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "1".to_owned(),
             pos: Position {
@@ -113,6 +119,7 @@ fn test_tokenizing_without_whitespace() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 1,
             kind: TokenKind::Assign,
             raw: "=".to_owned(),
             pos: Position {
@@ -127,6 +134,7 @@ fn test_tokenizing_without_whitespace() {
         tokens.next().unwrap(),
         Token {
             len: 1,
+            start: 2,
             kind: TokenKind::Literal(Value::Int),
             raw: "2".to_owned(),
             pos: Position {
@@ -146,6 +154,7 @@ fn test_string() {
         tokens.next().unwrap(),
         Token {
             len: 5,
+            start: 0,
             kind: TokenKind::Literal(Value::Str("aaa".into())),
             raw: "'aaa'".to_owned(),
             pos: Position {
@@ -160,6 +169,7 @@ fn test_string() {
         tokens.nth(1).unwrap(),
         Token {
             len: 5,
+            start: 6,
             kind: TokenKind::Literal(Value::Str("bbb".into())),
             raw: "\"bbb\"".to_owned(),
             pos: Position {
@@ -179,6 +189,7 @@ fn test_string_markers_within_string() {
         tokens.next().unwrap(),
         Token {
             len: 6,
+            start: 0,
             kind: TokenKind::Literal(Value::Str("\"aaa".into())),
             raw: "'\"aaa'".to_owned(),
             pos: Position {
@@ -193,6 +204,7 @@ fn test_string_markers_within_string() {
         tokens.nth(1).unwrap(),
         Token {
             len: 6,
+            start: 7,
             kind: TokenKind::Literal(Value::Str("'bbb".into())),
             raw: "\"'bbb\"".to_owned(),
             pos: Position {
@@ -212,6 +224,7 @@ fn test_numbers() {
         tokens.next().unwrap(),
         Token {
             len: 2,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "42".to_owned(),
             pos: Position {
@@ -231,6 +244,7 @@ fn test_binary_numbers() {
         tokens.next().unwrap(),
         Token {
             len: 8,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "0b101010".to_owned(),
             pos: Position {
@@ -250,6 +264,7 @@ fn test_octal_numbers() {
         tokens.next().unwrap(),
         Token {
             len: 4,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "0o52".to_owned(),
             pos: Position {
@@ -269,6 +284,7 @@ fn test_hex_numbers() {
         tokens.next().unwrap(),
         Token {
             len: 4,
+            start: 0,
             kind: TokenKind::Literal(Value::Int),
             raw: "0x2A".to_owned(),
             pos: Position {
@@ -288,6 +304,7 @@ fn test_functions() {
         tokens.next().unwrap(),
         Token {
             len: 2,
+            start: 0,
             kind: TokenKind::Keyword(Keyword::Function),
             raw: "fn".to_owned(),
             pos: Position {
@@ -318,6 +335,7 @@ fn fib() {}
         tokens.next().unwrap(),
         Token {
             len: 6,
+            start: 0,
             kind: TokenKind::Comment,
             raw: "// foo".to_owned(),
             pos: Position {
@@ -332,6 +350,7 @@ fn fib() {}
         tokens.next().unwrap(),
         Token {
             len: 2,
+            start: 7,
             kind: TokenKind::Keyword(Keyword::Function),
             raw: "fn".to_owned(),
             pos: Position {
@@ -395,3 +414,26 @@ mod prop_tests {
         }
     }
 }
+
+#[test]
+fn test_ellipsis() {
+    let kinds: Vec<TokenKind> = tokenize("args: ...any a.b")
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .filter(|kind| *kind != TokenKind::Whitespace)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Identifier("args".into()),
+            TokenKind::Colon,
+            TokenKind::Ellipsis,
+            TokenKind::Identifier("any".into()),
+            TokenKind::Identifier("a".into()),
+            TokenKind::Dot,
+            TokenKind::Identifier("b".into()),
+        ]
+    );
+}
@@ -17,7 +17,8 @@ extern crate lazy_static;
 extern crate qbe;
 extern crate rust_embed;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use diagnostic::{Diagnostic, Format, Level};
 use generator::Target;
 use std::path::PathBuf;
 use std::process;
@@ -25,12 +26,12 @@ use std::process;
 mod ast;
 mod builder;
 mod command;
+mod diagnostic;
 mod generator;
 mod lexer;
 mod parser;
 #[cfg(test)]
 mod tests;
-mod util;
 use rust_embed::RustEmbed;
 
 #[derive(RustEmbed)]
@@ -65,36 +66,96 @@ struct Opt {
     /// Target language. Options: c, js, qbe
     #[arg(long, short)]
     target: Option<Target>,
+
+    /// Stop parsing a file after this many syntax errors
+    #[arg(long, default_value_t = parser::DEFAULT_ERROR_LIMIT)]
+    error_limit: usize,
+
+    /// How errors are printed. Options: human, json (one object per line)
+    #[arg(long, default_value = "human")]
+    error_format: Format,
+
+    /// Report the problems a lint finds as warnings. `warnings` selects all lints
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Ignore the problems a lint finds
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report the problems a lint finds as errors, which stops the build
+    #[arg(short = 'D', long = "deny", value_name = "LINT")]
+    deny: Vec<String>,
 }
 
 fn main() {
     if let Err(err) = run() {
-        eprintln!("Error: {}", err);
+        eprintln!("{}", err);
         process::exit(1);
     }
 }
 
 fn run() -> Result<(), String> {
-    let opts = Opt::parse();
+    let matches = Opt::command().get_matches();
+    let opts = Opt::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let mut options = diagnostic::Options {
+        format: opts.error_format,
+        error_limit: opts.error_limit,
+        lints: Vec::new(),
+    };
+    options.lints =
+        lint_levels(&opts, &matches).map_err(|e| command::build::report(e, &options))?;
 
     match opts.command {
         Command::Build { in_file, out_file } => {
             let target = match opts.target {
                 Some(t) => t,
                 None => Target::from_extension(&out_file).ok_or_else(|| {
-                    format!(
-                        "Cannot detect target from output file {}, use --target option to set it explicitly",
+                    let diagnostic = Diagnostic::error(format!(
+                        "Cannot detect target from output file {}",
                         &out_file.to_string_lossy(),
-                    )
+                    ))
+                    .with_help("use the --target option to set it explicitly");
+                    command::build::report(diagnostic, &options)
                 })?,
             };
 
-            command::build::build(&target, &in_file, &out_file)?
+            command::build::build(&target, &in_file, &out_file, &options)?
         }
         Command::Run { in_file, args } => {
-            command::run::run(opts.target.unwrap_or(Target::JS), in_file, args)?
+            command::run::run(opts.target.unwrap_or(Target::JS), in_file, args, &options)?
         }
     };
 
     Ok(())
 }
+
+/// Lint levels in the order they were given, so e.g. `-D warnings -A
+/// unused_variables` denies every lint but one
+fn lint_levels(opts: &Opt, matches: &ArgMatches) -> Result<Vec<(Level, String)>, Diagnostic> {
+    let mut levels = Vec::new();
+    for (id, level, names) in [
+        ("warn", Level::Warn, &opts.warn),
+        ("allow", Level::Allow, &opts.allow),
+        ("deny", Level::Deny, &opts.deny),
+    ] {
+        let indices = matches.indices_of(id).into_iter().flatten();
+        for (index, name) in indices.zip(names) {
+            if name != diagnostic::ALL_LINTS && !parser::LINTS.iter().any(|l| l.name == name) {
+                let known: Vec<String> = parser::LINTS
+                    .iter()
+                    .map(|lint| format!("`{}`, {}", lint.name, lint.description))
+                    .collect();
+                return Err(Diagnostic::error(format!("Unknown lint `{}`", name))
+                    .with_note(format!("the lints are\n  {}", known.join("\n  ")))
+                    .with_help("use `warnings` to select all lints"));
+            }
+            levels.push((index, level, name.clone()));
+        }
+    }
+    levels.sort_by_key(|(index, _, _)| *index);
+    Ok(levels
+        .into_iter()
+        .map(|(_, level, name)| (level, name))
+        .collect())
+}
@@ -0,0 +1,528 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::infer::{
+    builtin_params, enter_block, infer_array, infer_expression, is_castable, is_compatible,
+    leave_block, Env,
+};
+use super::names::{did_you_mean, suggest};
+use crate::ast::hast::{
+    HBinOp, HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement, HStatementKind,
+};
+use crate::ast::span::Span;
+use crate::ast::types::Type;
+use crate::diagnostic::codes::{self, Code};
+use crate::diagnostic::Diagnostic;
+use std::collections::HashMap;
+
+struct Checker<'a> {
+    env: &'a Env,
+    var_map: HashMap<String, Type>,
+    ret_type: Option<Type>,
+    errors: Vec<Diagnostic>,
+}
+
+/// Verifies the types of an inferred module: operands, call arguments,
+/// return values, assignments and conditions. Unlike the earlier passes
+/// this does not stop at the first problem, every mismatch is reported on
+/// a diagnostic of its own.
+pub fn check(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    let env = Env::new(program);
+    let mut errors = Vec::new();
+    for func in &program.func {
+        errors.append(&mut Checker::function(&env, func, None));
+    }
+    for def in &program.structs {
+        for method in &def.methods {
+            let self_ty = Type::Struct(def.name.clone());
+            errors.append(&mut Checker::function(&env, method, Some(self_ty)));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn function(env: &'a Env, func: &HFunction, self_ty: Option<Type>) -> Vec<Diagnostic> {
+        let mut checker = Checker {
+            env,
+            var_map: HashMap::new(),
+            ret_type: func.ret_type.clone(),
+            errors: Vec::new(),
+        };
+        if let Some(ty) = self_ty {
+            checker.var_map.insert("self".to_string(), ty);
+        }
+        for arg in &func.arguments {
+            if let (Some(ty), Some(default)) = (&arg.ty, &arg.default) {
+                checker.value(ty, default);
+            }
+            if let Some(ty) = &arg.ty {
+                checker.var_map.insert(arg.name.clone(), ty.clone());
+            }
+        }
+        checker.statement(&func.body);
+        checker.errors
+    }
+
+    fn error(&mut self, code: Code, span: Span, message: String) {
+        self.errors.push(
+            Diagnostic::error(message)
+                .with_code(code)
+                .with_label(span, ""),
+        );
+    }
+
+    fn type_of(&self, expr: &HExpression) -> Option<Type> {
+        infer_expression(expr, self.env, &self.var_map)
+    }
+
+    /// Checks an expression and that it can be used where `expected` is required
+    fn value(&mut self, expected: &Type, value: &HExpression) {
+        let errors = self.errors.len();
+        self.expected(Some(expected), value);
+        // An array literal already reported the first element that does not fit
+        if matches!(value.kind, HExpressionKind::Array { .. }) && self.errors.len() > errors {
+            return;
+        }
+        if let Some(actual) = self.type_of(value) {
+            if !is_compatible(expected, &actual) {
+                self.errors.push(
+                    Diagnostic::error(format!(
+                        "Mismatched types: expected `{}`, got `{}`",
+                        expected, actual
+                    ))
+                    .with_code(codes::MISMATCHED_TYPES)
+                    .with_label(value.span, format!("expected `{}`", expected))
+                    .with_help(format!("use `as {}` to convert explicitly", expected)),
+                );
+            }
+        }
+    }
+
+    fn unknown_field(&mut self, struct_name: &str, field: &str, span: Span) {
+        let fields = self.env.fields.get(struct_name);
+        let candidate = did_you_mean(field, fields.into_iter().flatten().map(|(f, _)| f.as_str()));
+        self.unknown_member(
+            format!("Struct `{}` has no field `{}`", struct_name, field),
+            field,
+            span,
+            candidate,
+        );
+    }
+
+    /// `span` starts with the name of the member
+    fn unknown_member(&mut self, message: String, name: &str, span: Span, candidate: Option<&str>) {
+        let diagnostic = Diagnostic::error(message)
+            .with_code(codes::UNKNOWN_MEMBER)
+            .with_label(span, "unknown member");
+        let name_span = span.prefix(name.len());
+        self.errors
+            .push(suggest(diagnostic, Some(name_span), candidate));
+    }
+
+    /// Integers are accepted as conditions as well, `0` being false
+    fn condition(&mut self, condition: &HExpression) {
+        self.expression(condition);
+        match self.type_of(condition) {
+            Some(Type::Bool) | Some(Type::Any) | None => {}
+            Some(ty) if ty.clone().erased().is_integer() => {}
+            Some(ty) => self.error(
+                codes::INVALID_OPERATION,
+                condition.span,
+                format!("Condition must be a `bool` or an integer, got `{}`", ty),
+            ),
+        }
+    }
+
+    fn statement(&mut self, stmt: &HStatement) {
+        match &stmt.kind {
+            HStatementKind::Block { statements, .. } => {
+                let hidden = enter_block(statements, &self.var_map);
+                for s in statements {
+                    self.statement(s);
+                }
+                leave_block(&mut self.var_map, hidden);
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                match (&variable.ty, value) {
+                    (Some(ty), Some(value)) => self.value(ty, value),
+                    (None, Some(value)) => self.expression(value),
+                    _ => {}
+                }
+                match &variable.ty {
+                    Some(ty) => self.var_map.insert(variable.name.clone(), ty.clone()),
+                    None => self.var_map.remove(&variable.name),
+                };
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                self.expression(lhs);
+                match self.type_of(lhs) {
+                    Some(ty) => self.value(&ty, rhs),
+                    None => self.expression(rhs),
+                }
+            }
+            HStatementKind::Return(value) => match (&self.ret_type.clone(), value) {
+                (Some(ty), Some(value)) => self.value(ty, value),
+                (Some(ty), None) => self.error(
+                    codes::MISMATCHED_TYPES,
+                    stmt.span,
+                    format!("Expected a return value of type `{}`", ty),
+                ),
+                (None, Some(value)) => self.expression(value),
+                (None, None) => {}
+            },
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.condition(condition);
+                self.statement(body);
+                if let Some(else_stmt) = else_branch {
+                    self.statement(else_stmt);
+                }
+            }
+            HStatementKind::While { condition, body } => {
+                self.condition(condition);
+                self.statement(body);
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.expression(expr);
+                match self.type_of(expr) {
+                    Some(Type::Array(..)) | Some(Type::Any) | None => {}
+                    Some(ty) => self.error(
+                        codes::INVALID_OPERATION,
+                        expr.span,
+                        format!("Cannot iterate over `{}`", ty),
+                    ),
+                }
+                let hidden = vec![(ident.name.clone(), self.var_map.get(&ident.name).cloned())];
+                match &ident.ty {
+                    Some(ty) => self.var_map.insert(ident.name.clone(), ty.clone()),
+                    None => self.var_map.remove(&ident.name),
+                };
+                self.statement(body);
+                leave_block(&mut self.var_map, hidden);
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject);
+                let subject_ty = self.type_of(subject);
+                for arm in arms {
+                    match arm {
+                        HMatchArm::Case(case, s) => {
+                            match &subject_ty {
+                                Some(ty) => self.value(ty, case),
+                                None => self.expression(case),
+                            }
+                            self.statement(s);
+                        }
+                        HMatchArm::Else(s) => self.statement(s),
+                    }
+                }
+            }
+            HStatementKind::Exp(e) => self.expression(e),
+            HStatementKind::Break | HStatementKind::Continue => {}
+        }
+    }
+
+    fn arguments(&mut self, fn_name: &str, args: &[HExpression]) {
+        let (Some(params), Some(callee)) = (
+            self.env.arguments.get(fn_name),
+            self.env.declarations.get(fn_name),
+        ) else {
+            // Functions of the backend runtimes have no declaration to point at
+            let builtin = builtin_params(fn_name).unwrap_or_default();
+            for (i, arg) in args.iter().enumerate() {
+                match builtin.get(i) {
+                    // The runtime cannot tell what an `any` value holds
+                    Some(ty) if self.type_of(arg) == Some(Type::Any) && *ty != Type::Any => {
+                        self.expression(arg);
+                        self.errors.push(
+                            Diagnostic::error(format!(
+                                "Cannot pass a value of type `any` to `{}`",
+                                fn_name
+                            ))
+                            .with_code(codes::MISMATCHED_TYPES)
+                            .with_label(arg.span, format!("expected `{}`", ty))
+                            .with_note("the type of an `any` value is not known at runtime"),
+                        );
+                    }
+                    Some(ty) => self.value(ty, arg),
+                    None => self.expression(arg),
+                }
+            }
+            return;
+        };
+        for (param, arg) in params.iter().zip(args) {
+            match (&param.ty, &arg.kind) {
+                // The remaining arguments of a call are collected into an array
+                (Some(Type::Array(elem_ty, _)), HExpressionKind::Array { elements, .. })
+                    if param.variadic =>
+                {
+                    for element in elements {
+                        self.argument(elem_ty, element, param.span, &callee.name);
+                    }
+                }
+                (Some(ty), _) => self.argument(ty, arg, param.span, &callee.name),
+                (None, _) => self.expression(arg),
+            }
+        }
+    }
+
+    /// Like `value`, but a mismatch also points at the parameter
+    fn argument(&mut self, expected: &Type, arg: &HExpression, param: Span, callee: &str) {
+        let errors = self.errors.len();
+        self.value(expected, arg);
+        // A mismatch of the argument itself is reported after the ones
+        // inside of it
+        let mismatched = self.errors[errors..]
+            .last()
+            .is_some_and(|e| e.primary.as_ref().is_some_and(|l| l.span == arg.span));
+        if !mismatched {
+            return;
+        }
+        if let Some(error) = self.errors.pop() {
+            self.errors
+                .push(error.with_secondary(param, format!("parameter of `{}`", callee)));
+        }
+    }
+
+    /// Checks an expression whose type may be known from where it is used,
+    /// e.g. from the declaration of a variable. The elements of an array
+    /// literal are checked against the declared element type.
+    fn expected(&mut self, expected: Option<&Type>, expr: &HExpression) {
+        match (&expr.kind, expected) {
+            (HExpressionKind::Array { elements, .. }, Some(Type::Array(inner, _))) => {
+                // A type that the elements give the array anyway, e.g. one
+                // that was inferred from them, does not decide it
+                let own = infer_array(elements, self.env, &self.var_map);
+                let declared = match own {
+                    Some(Type::Array(own, _)) if own == *inner => None,
+                    _ => Some(&**inner),
+                };
+                self.elements(elements, declared)
+            }
+            _ => self.expression(expr),
+        }
+    }
+
+    /// Checks that all elements of an array literal have the same type,
+    /// which is `declared` if the type of the array is known. Otherwise the
+    /// elements decide it. Only the first element that does not fit is
+    /// reported.
+    fn elements(&mut self, elements: &[HExpression], declared: Option<&Type>) {
+        for el in elements {
+            self.expected(declared, el);
+        }
+        let expected = match declared {
+            Some(ty) => ty.clone(),
+            None => match infer_array(elements, self.env, &self.var_map) {
+                Some(Type::Array(inner, _)) => *inner,
+                _ => return,
+            },
+        };
+        // Without a declared type, the array holds the type of an element
+        let typed = match declared {
+            Some(_) => None,
+            None => elements
+                .iter()
+                .find(|el| self.type_of(el).is_some_and(|ty| ty == expected)),
+        };
+        let mismatched = elements.iter().find_map(|el| {
+            // Nested literals were checked element by element
+            if declared.is_some() && matches!(el.kind, HExpressionKind::Array { .. }) {
+                return None;
+            }
+            let actual = self.type_of(el)?;
+            (!is_compatible(&expected, &actual)).then_some((el, actual))
+        });
+        if let Some((el, actual)) = mismatched {
+            let mut diagnostic = Diagnostic::error(format!(
+                "Mismatched types in array: expected `{}`, got `{}`",
+                expected, actual
+            ))
+            .with_code(codes::MISMATCHED_TYPES)
+            .with_label(el.span, format!("expected `{}`", expected));
+            if let Some(typed) = typed {
+                diagnostic = diagnostic.with_secondary(
+                    typed.span,
+                    format!("the array holds `{}` because of this element", expected),
+                );
+            }
+            self.errors.push(diagnostic);
+        }
+    }
+
+    fn binop(&mut self, lhs: &HExpression, op: &HBinOp, rhs: &HExpression, span: Span) {
+        self.expression(lhs);
+        let Some(lhs_ty) = self.type_of(lhs) else {
+            self.expression(rhs);
+            return;
+        };
+        let operand = lhs_ty.clone().erased();
+        let accepted = match op {
+            _ if operand == Type::Any => true,
+            HBinOp::Addition | HBinOp::AddAssign => operand.is_integer() || operand == Type::Str,
+            HBinOp::Subtraction
+            | HBinOp::Multiplication
+            | HBinOp::Division
+            | HBinOp::Modulus
+            | HBinOp::SubtractAssign
+            | HBinOp::MultiplyAssign
+            | HBinOp::DivideAssign
+            | HBinOp::LessThan
+            | HBinOp::LessThanOrEqual
+            | HBinOp::GreaterThan
+            | HBinOp::GreaterThanOrEqual => operand.is_integer(),
+            HBinOp::And | HBinOp::Or => operand == Type::Bool,
+            HBinOp::Equal | HBinOp::NotEqual => true,
+        };
+        // The operator is checked first, so `"a" - 1` reports the operator
+        // rather than the mismatched operand
+        if !accepted {
+            self.error(
+                codes::INVALID_OPERATION,
+                span,
+                format!("Operator `{}` cannot be applied to `{}`", op, lhs_ty),
+            );
+            self.expression(rhs);
+            return;
+        }
+        self.value(&lhs_ty, rhs);
+    }
+
+    fn expression(&mut self, expr: &HExpression) {
+        match &expr.kind {
+            HExpressionKind::FunctionCall { fn_name, args, .. } => self.arguments(fn_name, args),
+            HExpressionKind::Array { elements, .. } => self.elements(elements, None),
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr);
+                self.expression(index);
+                match self.type_of(expr) {
+                    Some(Type::Array(..)) | Some(Type::Any) | None => {}
+                    Some(ty) => self.error(
+                        codes::INVALID_OPERATION,
+                        expr.span,
+                        format!("Cannot index into `{}`", ty),
+                    ),
+                }
+                match self.type_of(index) {
+                    Some(ty) if !ty.clone().erased().is_integer() && ty != Type::Any => self.error(
+                        codes::MISMATCHED_TYPES,
+                        index.span,
+                        format!("Array index must be an integer, got `{}`", ty),
+                    ),
+                    _ => {}
+                }
+            }
+            HExpressionKind::BinOp { lhs, op, rhs, .. } => self.binop(lhs, op, rhs, expr.span),
+            HExpressionKind::StructInitialization { name, fields } => {
+                let env = self.env;
+                for (field, value) in fields {
+                    match env.fields.get(name).map(|f| f.get(field)) {
+                        Some(Some(ty)) => self.value(ty, value),
+                        Some(None) => {
+                            self.expression(value);
+                            self.unknown_field(name, field, value.span);
+                        }
+                        None => self.expression(value),
+                    }
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr);
+                let receiver_ty = self.type_of(expr);
+                let field_span = field.span;
+                match (receiver_ty, &field.kind) {
+                    (Some(Type::Struct(name)), HExpressionKind::Variable(field)) => {
+                        if self
+                            .env
+                            .fields
+                            .get(&name)
+                            .is_some_and(|f| !f.contains_key(field))
+                        {
+                            self.unknown_field(&name, field, field_span);
+                        }
+                    }
+                    (
+                        Some(Type::Struct(name)),
+                        HExpressionKind::FunctionCall { fn_name, args, .. },
+                    ) => {
+                        let mangled = format!("{}_{}", name, fn_name);
+                        if self.env.fields.contains_key(&name)
+                            && !self.env.arguments.contains_key(&mangled)
+                        {
+                            let prefix = format!("{}_", name);
+                            let methods = self
+                                .env
+                                .arguments
+                                .keys()
+                                .filter_map(|mangled| mangled.strip_prefix(&prefix));
+                            let candidate = did_you_mean(fn_name, methods);
+                            self.unknown_member(
+                                format!("Struct `{}` has no method `{}`", name, fn_name),
+                                fn_name,
+                                field_span,
+                                candidate,
+                            );
+                        }
+                        self.arguments(&mangled, args);
+                    }
+                    (Some(ty), HExpressionKind::Variable(field)) if ty != Type::Any => {
+                        self.unknown_member(
+                            format!("`{}` has no field `{}`", ty, field),
+                            field,
+                            field_span,
+                            None,
+                        );
+                    }
+                    (_, HExpressionKind::FunctionCall { args, .. }) => {
+                        for arg in args {
+                            self.expression(arg);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            HExpressionKind::Cast {
+                expr: value,
+                from,
+                to,
+            } => {
+                self.expression(value);
+                if let Some(from) = from {
+                    if !is_castable(from, to) {
+                        self.error(
+                            codes::INVALID_OPERATION,
+                            expr.span,
+                            format!("Cannot cast `{}` to `{}`", from, to),
+                        );
+                    }
+                }
+            }
+            HExpressionKind::Int(_)
+            | HExpressionKind::Str(_)
+            | HExpressionKind::Bool(_)
+            | HExpressionKind::Selff
+            | HExpressionKind::Variable(_) => {}
+        }
+    }
+}
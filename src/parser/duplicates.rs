@@ -0,0 +1,88 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::ast::hast::HModule;
+use crate::ast::span::Span;
+use crate::diagnostic::{codes, Diagnostic};
+use std::collections::HashMap;
+
+/// Names that must be unique, e.g. the functions of a module or the fields
+/// of a struct. Maps each name to the span of its first definition.
+type Namespace<'a> = HashMap<&'a str, Span>;
+
+/// Defines `name` in `namespace`, or reports it if it is already defined.
+/// `what` describes the definition, e.g. "Function `main`".
+fn define<'a>(
+    namespace: &mut Namespace<'a>,
+    name: &'a str,
+    span: Span,
+    what: impl FnOnce() -> String,
+    errors: &mut Vec<Diagnostic>,
+) {
+    match namespace.get(name) {
+        Some(first) => errors.push(
+            Diagnostic::error(format!("{} is defined more than once", what()))
+                .with_code(codes::DUPLICATE)
+                .with_label(span, "redefined here")
+                .with_secondary(*first, "first defined here"),
+        ),
+        None => {
+            namespace.insert(name, span);
+        }
+    }
+}
+
+/// Reports functions, structs, types, fields and methods that are defined
+/// more than once. Runs on the merged module, where definitions of all
+/// modules and the standard library end up next to each other.
+pub fn check(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+
+    let mut functions = Namespace::new();
+    for func in &program.func {
+        let what = || format!("Function `{}`", func.name);
+        define(&mut functions, &func.name, func.span, what, &mut errors);
+    }
+
+    // Structs, type aliases and newtypes are all used as types
+    let mut types = Namespace::new();
+    for def in &program.structs {
+        let what = || format!("Struct `{}`", def.name);
+        define(&mut types, &def.name, def.span, what, &mut errors);
+    }
+    for def in &program.types {
+        let what = || format!("Type `{}`", def.name);
+        define(&mut types, &def.name, def.span, what, &mut errors);
+    }
+
+    for def in &program.structs {
+        let mut fields = Namespace::new();
+        for field in &def.fields {
+            let what = || format!("Field `{}` of struct `{}`", field.name, def.name);
+            define(&mut fields, &field.name, field.span, what, &mut errors);
+        }
+        let mut methods = Namespace::new();
+        for method in &def.methods {
+            let what = || format!("Method `{}` of struct `{}`", method.name, def.name);
+            define(&mut methods, &method.name, method.span, what, &mut errors);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
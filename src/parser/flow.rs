@@ -0,0 +1,633 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::infer::{infer_expression, Env};
+use super::patterns;
+use crate::ast::hast::{
+    HBinOp, HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement,
+    HStatementKind, HVariable,
+};
+use crate::ast::span::Span;
+use crate::ast::types::Type;
+use crate::diagnostic::{codes, Diagnostic};
+use std::collections::HashMap;
+
+/// How a variable is bound, which decides whether it can be assigned
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// `let`, which can be assigned once if it is declared without a value
+    Immutable,
+    /// `let mut` and `var`
+    Mutable,
+    Parameter,
+    /// The variable of a `for` loop
+    Item,
+}
+
+/// A variable that is in scope, and whether a value is assigned to it on
+/// every path that reaches the current statement
+#[derive(Clone)]
+struct Binding {
+    name: String,
+    span: Span,
+    ty: Option<Type>,
+    kind: Kind,
+    assigned: bool,
+    /// Whether a value is assigned to it on any path that reaches the
+    /// current statement
+    maybe_assigned: bool,
+}
+
+/// The method that is analyzed
+struct Method {
+    struct_name: String,
+    name: String,
+    mutable_self: bool,
+}
+
+/// The variables in scope at a point of the program
+type State = Vec<Vec<Binding>>;
+
+/// A loop that is analyzed
+struct Loop {
+    /// Number of scopes outside of the loop
+    depth: usize,
+    /// The variables at the `break`s of the loop, merged
+    breaks: Option<State>,
+    /// The variables at the `continue`s of the loop, merged
+    continues: Option<State>,
+}
+
+struct Flow {
+    env: Env,
+    /// Mangled names of the methods that take `mut self`, and where they
+    /// are declared
+    mutating: HashMap<String, Span>,
+    method: Option<Method>,
+    scopes: State,
+    loops: Vec<Loop>,
+    /// The last `match` whose arms all leave the function, but that can
+    /// complete because it is not exhaustive, and the values it misses
+    partial_match: Option<(Span, String)>,
+    errors: Vec<Diagnostic>,
+}
+
+/// Reports functions with a return type that can end without returning a
+/// value, variables that are read before a value is assigned to them, and
+/// assignments to immutable variables. Runs after type inference, when the
+/// return types of all functions are known.
+pub fn check(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    let mutating = program
+        .structs
+        .iter()
+        .flat_map(|def| {
+            def.methods
+                .iter()
+                .filter(|method| method.mutable_self)
+                .map(move |method| (format!("{}_{}", def.name, method.name), method.span))
+        })
+        .collect();
+    let mut flow = Flow {
+        env: Env::new(program),
+        mutating,
+        method: None,
+        scopes: Vec::new(),
+        loops: Vec::new(),
+        partial_match: None,
+        errors: Vec::new(),
+    };
+    for func in &program.func {
+        flow.method = None;
+        flow.function(func, "Function");
+    }
+    for def in &program.structs {
+        for method in &def.methods {
+            flow.method = Some(Method {
+                struct_name: def.name.clone(),
+                name: method.name.clone(),
+                mutable_self: method.mutable_self,
+            });
+            flow.function(method, "Method");
+        }
+    }
+    if flow.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(flow.errors)
+    }
+}
+
+/// Merges the variables of two paths that meet. A variable is only assigned
+/// if it is assigned on both of them, and maybe assigned if it is on either.
+fn merge(state: State, other: State) -> State {
+    state
+        .into_iter()
+        .zip(other)
+        .map(|(scope, other)| {
+            scope
+                .into_iter()
+                .zip(other)
+                .map(|(binding, other)| Binding {
+                    assigned: binding.assigned && other.assigned,
+                    maybe_assigned: binding.maybe_assigned || other.maybe_assigned,
+                    ..binding
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Merges the variables of the paths that reach the end of a statement, or
+/// `None` if no path does
+fn merge_all(states: Vec<State>) -> Option<State> {
+    states.into_iter().reduce(merge)
+}
+
+impl Flow {
+    fn function(&mut self, func: &HFunction, kind: &str) {
+        self.scopes = vec![func
+            .arguments
+            .iter()
+            .map(|arg| Binding {
+                name: arg.name.clone(),
+                span: arg.span,
+                ty: arg.ty.clone(),
+                kind: Kind::Parameter,
+                assigned: true,
+                maybe_assigned: true,
+            })
+            .collect()];
+        self.partial_match = None;
+        let completes = self.statement(&func.body);
+        if let (true, Some(ty)) = (completes, &func.ret_type) {
+            let mut diagnostic = Diagnostic::error(format!(
+                "{} `{}` does not return a value in all code paths",
+                kind, func.name
+            ))
+            .with_code(codes::MISSING_RETURN)
+            .with_label(
+                func.body.span.suffix(1),
+                format!("can end here without returning `{}`", ty),
+            );
+            diagnostic = match self.partial_match.take() {
+                Some((subject, missing)) => diagnostic
+                    .with_secondary(subject, format!("this `match` {}", missing))
+                    .with_help("add the missing arms or an `else` arm to the `match`"),
+                None => diagnostic.with_help("add a `return` statement at the end of the function"),
+            };
+            self.errors.push(diagnostic);
+        }
+        self.scopes.clear();
+    }
+
+    fn declare(&mut self, variable: &HVariable, kind: Kind, assigned: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding {
+                name: variable.name.clone(),
+                span: variable.span,
+                ty: variable.ty.clone(),
+                kind,
+                assigned,
+                maybe_assigned: assigned,
+            });
+        }
+    }
+
+    fn binding(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name)
+    }
+
+    fn read(&mut self, name: &str, span: Span) {
+        let binding = match self.binding(name) {
+            Some(binding) if !binding.assigned => binding,
+            _ => return,
+        };
+        // Reported once, later reads are not errors of their own
+        binding.assigned = true;
+        let declared = binding.span;
+        self.errors.push(
+            Diagnostic::error(format!("Variable `{}` is used before it is assigned", name))
+                .with_code(codes::UNASSIGNED)
+                .with_label(span, "")
+                .with_secondary(declared, "declared here without a value")
+                .with_help(format!(
+                    "assign a value to `{}` where it is declared, or on every path before it is used",
+                    name
+                )),
+        );
+    }
+
+    /// Assigns a value to the variable `name`. An immutable variable can
+    /// only be assigned if it was declared without a value, and only once.
+    fn assign(&mut self, name: &str, span: Span) {
+        let binding = match self.binding(name) {
+            Some(binding) => binding,
+            None => return,
+        };
+        let error = match binding.kind {
+            Kind::Mutable => None,
+            Kind::Immutable if !binding.maybe_assigned => None,
+            Kind::Immutable => Some(
+                Diagnostic::error(format!(
+                    "Cannot assign twice to immutable variable `{}`",
+                    name
+                ))
+                .with_label(span, "cannot assign twice")
+                .with_secondary(binding.span, "declared here")
+                .with_help(format!("make the variable mutable: `let mut {}`", name)),
+            ),
+            Kind::Parameter => Some(
+                Diagnostic::error(format!("Cannot assign to parameter `{}`", name))
+                    .with_label(span, "")
+                    .with_secondary(binding.span, "declared here")
+                    .with_help(format!(
+                        "copy it into a mutable variable: `let mut {0} = {0}`",
+                        name
+                    )),
+            ),
+            Kind::Item => Some(
+                Diagnostic::error(format!("Cannot assign to loop variable `{}`", name))
+                    .with_label(span, "")
+                    .with_secondary(binding.span, "declared here"),
+            ),
+        };
+        binding.assigned = true;
+        binding.maybe_assigned = true;
+        if let Some(error) = error {
+            self.errors.push(error.with_code(codes::IMMUTABLE));
+        }
+    }
+
+    /// Assigns a value to `lhs`, which is a variable, or a field or an
+    /// element of one
+    fn assignment(&mut self, lhs: &HExpression, span: Span) {
+        let action = match &lhs.kind {
+            HExpressionKind::Variable(name) => return self.assign(name, span),
+            HExpressionKind::FieldAccess { .. } => "assign to a field of",
+            HExpressionKind::ArrayAccess { .. } => "assign to an element of",
+            HExpressionKind::Selff => "assign to",
+            _ => return self.expression(lhs),
+        };
+        self.expression(lhs);
+        if let Some(error) = self.immutable_root(lhs, action, span) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Reports `action` on the variable that `place` is part of, e.g. `user`
+    /// for `user.tags[0]`, unless that variable or `self` is mutable.
+    /// Temporary values, like the results of calls, can always be changed.
+    fn immutable_root(
+        &mut self,
+        place: &HExpression,
+        action: &str,
+        span: Span,
+    ) -> Option<Diagnostic> {
+        let mut root = place;
+        while let HExpressionKind::FieldAccess { expr, .. }
+        | HExpressionKind::ArrayAccess { expr, .. } = &root.kind
+        {
+            root = expr;
+        }
+        let error = match &root.kind {
+            HExpressionKind::Variable(name) => {
+                let binding = self.binding(name)?;
+                let (what, help) = match binding.kind {
+                    Kind::Mutable => return None,
+                    Kind::Immutable => (
+                        "immutable variable",
+                        Some(format!("make the variable mutable: `let mut {}`", name)),
+                    ),
+                    Kind::Parameter => ("parameter", None),
+                    Kind::Item => ("loop variable", None),
+                };
+                let error = Diagnostic::error(format!("Cannot {} {} `{}`", action, what, name))
+                    .with_label(span, "")
+                    .with_secondary(binding.span, "declared here");
+                match help {
+                    Some(help) => error.with_help(help),
+                    None => error,
+                }
+            }
+            HExpressionKind::Selff => {
+                let method = self.method.as_ref().filter(|method| !method.mutable_self)?;
+                Diagnostic::error(format!(
+                    "Cannot {} `self` in a method that does not take `mut self`",
+                    action
+                ))
+                .with_label(span, "")
+                .with_help(format!(
+                    "declare the method as `fn {}(mut self, ...)`",
+                    method.name
+                ))
+            }
+            _ => return None,
+        };
+        Some(error.with_code(codes::IMMUTABLE))
+    }
+
+    /// The types of the variables in scope, to find the methods that are
+    /// called
+    fn var_map(&self) -> HashMap<String, Type> {
+        let mut var_map = HashMap::new();
+        if let Some(method) = &self.method {
+            var_map.insert("self".to_string(), Type::Struct(method.struct_name.clone()));
+        }
+        for binding in self.scopes.iter().flatten() {
+            if let Some(ty) = &binding.ty {
+                var_map.insert(binding.name.clone(), ty.clone());
+            }
+        }
+        var_map
+    }
+
+    /// Reports a call of a `mut self` method on a variable that is not mutable
+    fn method_call(&mut self, receiver: &HExpression, method: &str, span: Span) {
+        let struct_name = match infer_expression(receiver, &self.env, &self.var_map()) {
+            Some(Type::Struct(name)) => name,
+            _ => return,
+        };
+        let declared = match self.mutating.get(&format!("{}_{}", struct_name, method)) {
+            Some(declared) => *declared,
+            None => return,
+        };
+        let action = format!("call `mut self` method `{}` on", method);
+        if let Some(error) = self.immutable_root(receiver, &action, span) {
+            let error = error.with_secondary(declared, "method declared here");
+            self.errors.push(error);
+        }
+    }
+
+    /// Analyzes a statement, starting with the variables of the paths that
+    /// reach it. Returns whether the statement can complete, i.e. whether
+    /// execution can continue with the next statement. Otherwise it always
+    /// returns, breaks or continues.
+    fn statement(&mut self, stmt: &HStatement) -> bool {
+        match &stmt.kind {
+            HStatementKind::Block { statements, .. } => {
+                self.scopes.push(Vec::new());
+                // Statements after one that cannot complete are unreachable,
+                // which is a lint
+                let completes = statements.iter().all(|s| self.statement(s));
+                self.scopes.pop();
+                completes
+            }
+            HStatementKind::Declare {
+                variable,
+                value,
+                mutable,
+            } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                // Arrays with a capacity are allocated where they are
+                // declared, so their elements can be assigned right away
+                let allocated = matches!(variable.ty, Some(Type::Array(_, Some(_))));
+                let kind = match mutable {
+                    true => Kind::Mutable,
+                    false => Kind::Immutable,
+                };
+                self.declare(variable, kind, value.is_some() || allocated);
+                true
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                self.expression(rhs);
+                self.assignment(lhs, stmt.span);
+                true
+            }
+            HStatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                false
+            }
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.expression(condition);
+                let before = self.scopes.clone();
+                let mut states = Vec::new();
+                if self.statement(body) {
+                    states.push(std::mem::replace(&mut self.scopes, before.clone()));
+                } else {
+                    self.scopes = before.clone();
+                }
+                match else_branch {
+                    Some(else_branch) => {
+                        if self.statement(else_branch) {
+                            states.push(std::mem::take(&mut self.scopes));
+                        }
+                    }
+                    None => states.push(std::mem::take(&mut self.scopes)),
+                }
+                self.join(states, before)
+            }
+            HStatementKind::While { condition, body } => {
+                self.expression(condition);
+                let before = self.scopes.clone();
+                let breaks = self.body(body);
+                // Only `while true` can run its body forever, so the loop
+                // completes only through a `break`
+                if matches!(condition.kind, HExpressionKind::Bool(true)) {
+                    return self.join(breaks.into_iter().collect(), before);
+                }
+                // The body may not run at all
+                self.scopes = before;
+                true
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.expression(expr);
+                let before = self.scopes.clone();
+                self.scopes.push(Vec::new());
+                self.declare(ident, Kind::Item, true);
+                self.body(body);
+                self.scopes = before;
+                true
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject);
+                let before = self.scopes.clone();
+                let mut states = Vec::new();
+                for arm in arms {
+                    self.scopes = before.clone();
+                    let body = match arm {
+                        HMatchArm::Case(case, body) => {
+                            self.expression(case);
+                            body
+                        }
+                        HMatchArm::Else(body) => body,
+                    };
+                    if self.statement(body) {
+                        states.push(std::mem::take(&mut self.scopes));
+                    }
+                }
+                // Unless the arms cover every value, no arm may match
+                if !patterns::is_exhaustive(arms) {
+                    if states.is_empty() {
+                        let missing = match patterns::missing_values(arms) {
+                            Some(values) => format!("does not cover `{}`", values.join("`, `")),
+                            None => "has no `else` arm".to_string(),
+                        };
+                        self.partial_match = Some((subject.span, missing));
+                    }
+                    states.push(before.clone());
+                }
+                self.join(states, before)
+            }
+            HStatementKind::Break => {
+                if let Some(inner) = self.loops.last_mut() {
+                    let state = self.scopes[..inner.depth].to_vec();
+                    inner.breaks = Some(match inner.breaks.take() {
+                        Some(breaks) => merge(breaks, state),
+                        None => state,
+                    });
+                }
+                false
+            }
+            HStatementKind::Continue => {
+                if let Some(inner) = self.loops.last_mut() {
+                    let state = self.scopes[..inner.depth].to_vec();
+                    inner.continues = Some(match inner.continues.take() {
+                        Some(continues) => merge(continues, state),
+                        None => state,
+                    });
+                }
+                false
+            }
+            HStatementKind::Exp(e) => {
+                self.expression(e);
+                true
+            }
+        }
+    }
+
+    /// Analyzes the body of a loop. Returns the variables at its `break`s.
+    fn body(&mut self, body: &HStatement) -> Option<State> {
+        let before = self.scopes.clone();
+        let errors = self.errors.len();
+        let (completes, inner) = self.iteration(body);
+        // Variables that are assigned in one iteration keep their values in
+        // the next one, so the body is analyzed again with the variables at
+        // its end and at its `continue`s. Only this second pass is reported.
+        let mut repeated = inner.continues.into_iter().collect::<Vec<_>>();
+        if completes {
+            repeated.push(std::mem::take(&mut self.scopes));
+        }
+        match merge_all(repeated) {
+            Some(state) => {
+                self.errors.truncate(errors);
+                self.scopes = merge(before, state);
+                self.iteration(body).1.breaks
+            }
+            None => inner.breaks,
+        }
+    }
+
+    /// Analyzes one iteration of the body of a loop. Returns whether the
+    /// body can complete, and its `break`s and `continue`s.
+    fn iteration(&mut self, body: &HStatement) -> (bool, Loop) {
+        self.loops.push(Loop {
+            depth: self.scopes.len(),
+            breaks: None,
+            continues: None,
+        });
+        let completes = self.statement(body);
+        let inner = self.loops.pop().expect("the loop was pushed above");
+        (completes, inner)
+    }
+
+    /// Continues with the merged variables of the paths that complete a
+    /// statement. Returns false if there are none.
+    fn join(&mut self, states: Vec<State>, before: State) -> bool {
+        match merge_all(states) {
+            Some(state) => {
+                self.scopes = state;
+                true
+            }
+            None => {
+                self.scopes = before;
+                false
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &HExpression) {
+        match &expr.kind {
+            HExpressionKind::Variable(name) => self.read(name, expr.span),
+            HExpressionKind::FunctionCall { args, named, .. } => {
+                for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                    self.expression(arg);
+                }
+            }
+            HExpressionKind::Array { elements, .. } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr);
+                self.expression(index);
+            }
+            HExpressionKind::BinOp { lhs, op, rhs, .. } => {
+                self.expression(rhs);
+                match op {
+                    HBinOp::AddAssign
+                    | HBinOp::SubtractAssign
+                    | HBinOp::MultiplyAssign
+                    | HBinOp::DivideAssign => {
+                        // The variable is read before it is assigned
+                        if let HExpressionKind::Variable(name) = &lhs.kind {
+                            self.read(name, lhs.span);
+                        }
+                        self.assignment(lhs, expr.span);
+                    }
+                    _ => self.expression(lhs),
+                }
+            }
+            HExpressionKind::StructInitialization { fields, .. } => {
+                for value in fields.values() {
+                    self.expression(value);
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr);
+                // Fields are not variables, only the arguments of a method
+                // call are read
+                if let HExpressionKind::FunctionCall {
+                    fn_name,
+                    args,
+                    named,
+                } = &field.kind
+                {
+                    for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                        self.expression(arg);
+                    }
+                    self.method_call(expr, fn_name, field.span);
+                }
+            }
+            HExpressionKind::Cast { expr, .. } => self.expression(expr),
+            HExpressionKind::Int(_)
+            | HExpressionKind::Str(_)
+            | HExpressionKind::Bool(_)
+            | HExpressionKind::Selff => {}
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::ast::hast::{HBinOp, HExpression, HMatchArm, HModule, HStatement};
+use crate::ast::hast::{
+    HArgument, HBinOp, HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement,
+    HStatementKind, HVariable,
+};
+use crate::ast::span::Span;
 /**
  * Copyright 2021 Garrit Franke
  *
@@ -16,111 +20,1111 @@ use crate::ast::hast::{HBinOp, HExpression, HMatchArm, HModule, HStatement};
  */
 use crate::ast::types::Type;
 use crate::ast::SymbolTable;
+use crate::diagnostic::{codes, Diagnostic};
+use std::cell::RefCell;
 use std::collections::HashMap;
 
-/// Try to infer types of variables
+/// Module-wide type information that is available while inferring a function body
+pub(super) struct Env {
+    /// Return types of all functions and (mangled) methods
+    pub(super) table: SymbolTable,
+    /// Parameter types of all functions and (mangled) methods
+    pub(super) params: HashMap<String, Vec<Option<Type>>>,
+    /// Parameter names and default values of all functions and (mangled) methods
+    pub(super) arguments: HashMap<String, Vec<HArgument>>,
+    /// Where all functions and (mangled) methods are declared
+    pub(super) declarations: HashMap<String, Declaration>,
+    /// Struct name -> field name -> field type
+    pub(super) fields: HashMap<String, HashMap<String, Type>>,
+    /// Struct name -> (field name, struct name) of every embedded struct
+    embeds: HashMap<String, Vec<(String, String)>>,
+    /// Calls with missing, superfluous or unknown arguments. Inference goes
+    /// on after them, so every such call of a function is reported.
+    call_errors: RefCell<Vec<Diagnostic>>,
+}
+
+/// A function or method as it is shown in diagnostics about its calls
+pub(super) struct Declaration {
+    /// `name` for functions and `Struct.name` for methods
+    pub(super) name: String,
+    pub(super) span: Span,
+}
+
+impl Env {
+    pub(super) fn new(program: &HModule) -> Self {
+        let fields = program
+            .structs
+            .iter()
+            .map(|def| {
+                let fields = def
+                    .fields
+                    .iter()
+                    .filter_map(|f| Some((f.name.clone(), f.ty.clone()?)))
+                    .collect();
+                (def.name.clone(), fields)
+            })
+            .collect();
+
+        let embeds = program
+            .structs
+            .iter()
+            .map(|def| {
+                let embeds = def
+                    .fields
+                    .iter()
+                    .filter(|f| def.embeds.contains(&f.name))
+                    .filter_map(|f| match &f.ty {
+                        Some(Type::Struct(inner)) => Some((f.name.clone(), inner.clone())),
+                        _ => None,
+                    })
+                    .collect();
+                (def.name.clone(), embeds)
+            })
+            .collect();
+
+        let mut arguments = HashMap::new();
+        let mut declarations = HashMap::new();
+        for func in &program.func {
+            arguments.insert(func.name.clone(), func.arguments.clone());
+            let declaration = Declaration {
+                name: func.name.clone(),
+                span: func.span,
+            };
+            declarations.insert(func.name.clone(), declaration);
+        }
+        for def in &program.structs {
+            for method in &def.methods {
+                let mangled = format!("{}_{}", def.name, method.name);
+                arguments.insert(mangled.clone(), method.arguments.clone());
+                let declaration = Declaration {
+                    name: format!("{}.{}", def.name, method.name),
+                    span: method.span,
+                };
+                declarations.insert(mangled, declaration);
+            }
+        }
+        let params = arguments
+            .iter()
+            .map(|(name, args)| (name.clone(), args.iter().map(|a| a.ty.clone()).collect()))
+            .collect();
+
+        Env {
+            table: program.get_symbol_table(),
+            params,
+            arguments,
+            declarations,
+            fields,
+            embeds,
+            call_errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Checks that only structs are embedded, and that no struct ends up
+    /// containing itself
+    fn check_embeds(&self, program: &HModule) -> Result<(), Diagnostic> {
+        for def in &program.structs {
+            for field in def.fields.iter().filter(|f| def.embeds.contains(&f.name)) {
+                match &field.ty {
+                    Some(Type::Struct(inner)) if self.fields.contains_key(inner) => {}
+                    _ => {
+                        return Err(Diagnostic::error(format!(
+                            "Cannot embed `{}` in struct `{}`, only structs can be embedded",
+                            field.name, def.name
+                        ))
+                        .with_code(codes::DEFINITION)
+                        .with_label(field.span, "not a struct"))
+                    }
+                }
+            }
+            let mut pending = vec![def.name.as_str()];
+            let mut seen = Vec::new();
+            while let Some(name) = pending.pop() {
+                for (_, inner) in self.embeds.get(name).into_iter().flatten() {
+                    if inner == &def.name {
+                        return Err(Diagnostic::error(format!(
+                            "Struct `{}` embeds itself",
+                            def.name
+                        ))
+                        .with_code(codes::DEFINITION)
+                        .with_label(def.span, "recursive struct"));
+                    }
+                    if !seen.contains(&inner) {
+                        seen.push(inner);
+                        pending.push(inner);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the embedded fields leading to a promoted field or method,
+    /// e.g. `["User"]` for `admin.name` if `AdminUser` embeds `User`. Like
+    /// in Go, a shallower member hides a deeper one, and two members at the
+    /// same depth are ambiguous. The path is empty if the struct has the
+    /// member itself, or if no embedded struct has it.
+    fn promotion_path(&self, struct_name: &str, member: &str) -> Result<Vec<String>, Diagnostic> {
+        let has_member = |name: &str| {
+            self.fields
+                .get(name)
+                .is_some_and(|f| f.contains_key(member))
+                || self.params.contains_key(&format!("{}_{}", name, member))
+        };
+        if has_member(struct_name) {
+            return Ok(Vec::new());
+        }
+        let mut level = vec![(Vec::new(), struct_name.to_string())];
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for (path, name) in &level {
+                for (field, inner) in self.embeds.get(name).into_iter().flatten() {
+                    let mut path = path.clone();
+                    path.push(field.clone());
+                    next.push((path, inner.clone()));
+                }
+            }
+            let found: Vec<_> = next.iter().filter(|(_, name)| has_member(name)).collect();
+            match found.as_slice() {
+                [] => level = next,
+                [(path, _)] => return Ok(path.clone()),
+                [(first, _), (second, _), ..] => {
+                    return Err(Diagnostic::error(format!(
+                        "`{}` is ambiguous on struct `{}`",
+                        member, struct_name
+                    ))
+                    .with_code(codes::UNKNOWN_MEMBER)
+                    .with_note(format!(
+                        "it is promoted from both `{}` and `{}`",
+                        first.join("."),
+                        second.join(".")
+                    )))
+                }
+            }
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Try to infer types of variables.
 ///
-/// TODO: Global symbol table is passed around randomly.
-/// This could probably be cleaned up.
-pub fn infer(program: &mut HModule) {
-    let table = &program.get_symbol_table();
+/// Whether the inferred types fit together is checked afterwards by
+/// `check::check`, this only fails if a call or a member cannot be resolved.
+/// Every function is inferred, and the errors of all of them are reported.
+pub fn infer(program: &mut HModule) -> Result<(), Vec<Diagnostic>> {
+    infer_return_types(program);
+    let env = &Env::new(program);
+    env.check_embeds(program)?;
+    let mut errors = Vec::new();
+    let mut infer = |func: &mut HFunction, self_ty: Option<Type>| {
+        let result = infer_function(func, env, self_ty);
+        errors.append(&mut env.call_errors.borrow_mut());
+        if let Err(e) = result {
+            errors.push(e.located_at(func.span));
+        }
+    };
     for func in &mut program.func {
-        let mut var_map: HashMap<String, Type> = HashMap::new();
-        // Seed with parameter types
-        for arg in &func.arguments {
-            if let Some(ty) = &arg.ty {
-                var_map.insert(arg.name.clone(), ty.clone());
+        infer(func, None);
+    }
+    for def in &mut program.structs {
+        for method in &mut def.methods {
+            infer(method, Some(Type::Struct(def.name.clone())));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Infers the return types of functions and methods without an annotation
+/// from their `return` statements. A return value may come from a function
+/// whose return type is only inferred later, e.g. in mutually recursive
+/// functions, so this repeats until no more return types are found.
+/// Functions that never return a value stay void.
+fn infer_return_types(program: &mut HModule) {
+    loop {
+        let env = &Env::new(program);
+        let mut changed = false;
+        for func in program.func.iter_mut().filter(|f| f.ret_type.is_none()) {
+            func.ret_type = return_type(func, env, None);
+            changed |= func.ret_type.is_some();
+        }
+        for def in &mut program.structs {
+            let self_ty = Type::Struct(def.name.clone());
+            for method in def.methods.iter_mut().filter(|m| m.ret_type.is_none()) {
+                method.ret_type = return_type(method, env, Some(self_ty.clone()));
+                changed |= method.ret_type.is_some();
+            }
+        }
+        if !changed {
+            return;
+        }
+    }
+}
+
+/// The type of the first `return` in a function whose value has a known
+/// type. Returns nested in conditionals and loops count as well.
+fn return_type(func: &HFunction, env: &Env, self_ty: Option<Type>) -> Option<Type> {
+    let mut var_map: HashMap<String, Type> = HashMap::new();
+    if let Some(ty) = self_ty {
+        var_map.insert("self".to_string(), ty);
+    }
+    for arg in &func.arguments {
+        if let Some(ty) = &arg.ty {
+            var_map.insert(arg.name.clone(), ty.clone());
+        }
+    }
+    find_return_type(&func.body, env, &mut var_map)
+}
+
+fn find_return_type(
+    stmt: &HStatement,
+    env: &Env,
+    var_map: &mut HashMap<String, Type>,
+) -> Option<Type> {
+    match &stmt.kind {
+        HStatementKind::Block { statements, .. } => {
+            let hidden = enter_block(statements, var_map);
+            let ty = statements
+                .iter()
+                .find_map(|s| find_return_type(s, env, var_map));
+            leave_block(var_map, hidden);
+            ty
+        }
+        HStatementKind::Declare {
+            variable, value, ..
+        } => {
+            let ty = match (&variable.ty, value) {
+                (Some(ty), _) => Some(ty.clone()),
+                (None, Some(value)) => infer_expression(value, env, var_map),
+                (None, None) => None,
+            };
+            if let Some(ty) = ty {
+                var_map.insert(variable.name.clone(), ty);
             }
+            None
+        }
+        HStatementKind::Return(Some(e)) => infer_expression(e, env, var_map),
+        HStatementKind::If {
+            body, else_branch, ..
+        } => find_return_type(body, env, var_map).or_else(|| {
+            else_branch
+                .as_ref()
+                .and_then(|s| find_return_type(s, env, var_map))
+        }),
+        HStatementKind::While { body, .. } => find_return_type(body, env, var_map),
+        HStatementKind::For { ident, expr, body } => {
+            let ty = ident
+                .ty
+                .clone()
+                .or_else(|| match infer_expression(expr, env, var_map) {
+                    Some(Type::Array(elem_ty, _)) => Some(*elem_ty),
+                    _ => None,
+                });
+            let hidden = vec![(ident.name.clone(), var_map.get(&ident.name).cloned())];
+            if let Some(ty) = ty {
+                var_map.insert(ident.name.clone(), ty);
+            }
+            let ty = find_return_type(body, env, var_map);
+            leave_block(var_map, hidden);
+            ty
+        }
+        HStatementKind::Match { arms, .. } => arms.iter().find_map(|arm| match arm {
+            HMatchArm::Case(_, s) | HMatchArm::Else(s) => find_return_type(s, env, var_map),
+        }),
+        HStatementKind::Assign { .. }
+        | HStatementKind::Return(None)
+        | HStatementKind::Exp(_)
+        | HStatementKind::Break
+        | HStatementKind::Continue => None,
+    }
+}
+
+/// A variable that a block declares hides the variables of the same name
+/// until the end of the block. Returns the types of the hidden variables,
+/// which `leave_block` restores.
+pub(super) fn enter_block(
+    statements: &[HStatement],
+    var_map: &HashMap<String, Type>,
+) -> Vec<(String, Option<Type>)> {
+    statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            HStatementKind::Declare { variable, .. } => Some(variable.name.clone()),
+            _ => None,
+        })
+        .map(|name| {
+            let ty = var_map.get(&name).cloned();
+            (name, ty)
+        })
+        .collect()
+}
+
+/// Makes the variables that a block hid visible again, once it ends
+pub(super) fn leave_block(
+    var_map: &mut HashMap<String, Type>,
+    hidden: Vec<(String, Option<Type>)>,
+) {
+    // A name that the block declares twice was saved twice, the first
+    // time with the type from outside of the block
+    for (name, ty) in hidden.into_iter().rev() {
+        match ty {
+            Some(ty) => var_map.insert(name, ty),
+            None => var_map.remove(&name),
+        };
+    }
+}
+
+fn infer_function(
+    func: &mut HFunction,
+    env: &Env,
+    self_ty: Option<Type>,
+) -> Result<(), Diagnostic> {
+    let mut var_map: HashMap<String, Type> = HashMap::new();
+    if let Some(ty) = self_ty {
+        var_map.insert("self".to_string(), ty);
+    }
+    // Seed with parameter types
+    for arg in &func.arguments {
+        if let Some(ty) = &arg.ty {
+            var_map.insert(arg.name.clone(), ty.clone());
         }
-        infer_statement(&mut func.body, table, &mut var_map);
     }
+    check_parameters(&mut func.arguments, env)?;
+    infer_statement(&mut func.body, env, &mut var_map, &func.ret_type)
 }
 
 fn infer_statement(
     stmt: &mut HStatement,
-    table: &SymbolTable,
+    env: &Env,
     var_map: &mut HashMap<String, Type>,
-) {
-    match stmt {
-        HStatement::Block { statements, .. } => {
+    ret_type: &Option<Type>,
+) -> Result<(), Diagnostic> {
+    match &mut stmt.kind {
+        HStatementKind::Block { statements, .. } => {
+            let hidden = enter_block(statements, var_map);
+            for s in statements.iter_mut() {
+                infer_statement(s, env, var_map, ret_type)?;
+            }
             for s in statements {
-                infer_statement(s, table, var_map);
+                if let HStatementKind::Declare {
+                    variable, value, ..
+                } = &mut s.kind
+                {
+                    infer_empty_array(variable, value, var_map)?;
+                }
             }
+            leave_block(var_map, hidden);
         }
-        HStatement::Declare { variable, value } => {
-            if variable.ty.is_none() {
-                if let Some(e) = value {
-                    variable.ty = infer_expression(e, table, var_map);
-                    #[cfg(debug_assertions)]
-                    if variable.ty.is_none() {
-                        println!("Type of {} could not be infered: {:?}", &variable.name, e);
+        HStatementKind::Declare {
+            variable, value, ..
+        } => {
+            if let Some(e) = value {
+                check_expression(e, env, var_map)?;
+                match &variable.ty {
+                    Some(ty) => convert_to(e, ty, env, var_map)?,
+                    None => variable.ty = infer_expression(e, env, var_map),
+                }
+            }
+            match &variable.ty {
+                Some(ty) => var_map.insert(variable.name.clone(), ty.clone()),
+                // Hides a variable of an outer scope
+                None => var_map.remove(&variable.name),
+            };
+        }
+        HStatementKind::Assign { lhs, rhs } => {
+            check_expression(lhs, env, var_map)?;
+            check_expression(rhs, env, var_map)?;
+            match infer_expression(lhs, env, var_map) {
+                Some(ty) => convert_to(rhs, &ty, env, var_map)?,
+                // The first assignment to an array that was declared empty,
+                // e.g. `let names = []`, tells its type
+                None => {
+                    let (name, ty) = match &lhs.kind {
+                        HExpressionKind::Variable(name) => {
+                            (name, infer_expression(rhs, env, var_map))
+                        }
+                        HExpressionKind::ArrayAccess { expr, .. } => match &expr.kind {
+                            HExpressionKind::Variable(name) => (
+                                name,
+                                infer_expression(rhs, env, var_map)
+                                    .map(|ty| Type::Array(Box::new(ty), None)),
+                            ),
+                            _ => return Ok(()),
+                        },
+                        _ => return Ok(()),
+                    };
+                    if let Some(ty) = ty {
+                        var_map.insert(name.clone(), ty);
                     }
                 }
             }
-            if let Some(ty) = &variable.ty {
-                var_map.insert(variable.name.clone(), ty.clone());
+        }
+        HStatementKind::Return(Some(e)) => {
+            check_expression(e, env, var_map)?;
+            if let Some(ty) = ret_type {
+                convert_to(e, ty, env, var_map)?;
             }
         }
-        HStatement::If {
-            body, else_branch, ..
+        HStatementKind::If {
+            condition,
+            body,
+            else_branch,
         } => {
-            infer_statement(body, table, var_map);
+            check_expression(condition, env, var_map)?;
+            infer_statement(body, env, var_map, ret_type)?;
             if let Some(else_stmt) = else_branch {
-                infer_statement(else_stmt, table, var_map);
+                infer_statement(else_stmt, env, var_map, ret_type)?;
             }
         }
-        HStatement::While { body, .. } => infer_statement(body, table, var_map),
-        HStatement::For { ident, expr, body } => {
+        HStatementKind::While { condition, body } => {
+            check_expression(condition, env, var_map)?;
+            infer_statement(body, env, var_map, ret_type)?;
+        }
+        HStatementKind::For { ident, expr, body } => {
+            check_expression(expr, env, var_map)?;
             if ident.ty.is_none() {
-                if let Some(Type::Array(elem_ty, _)) = infer_expression(expr, table, var_map) {
+                if let Some(Type::Array(elem_ty, _)) = infer_expression(expr, env, var_map) {
                     ident.ty = Some(*elem_ty);
                 }
             }
-            if let Some(ty) = &ident.ty {
-                var_map.insert(ident.name.clone(), ty.clone());
-            }
-            infer_statement(body, table, var_map);
+            let hidden = vec![(ident.name.clone(), var_map.get(&ident.name).cloned())];
+            match &ident.ty {
+                Some(ty) => var_map.insert(ident.name.clone(), ty.clone()),
+                None => var_map.remove(&ident.name),
+            };
+            infer_statement(body, env, var_map, ret_type)?;
+            leave_block(var_map, hidden);
         }
-        HStatement::Match { arms, .. } => {
+        HStatementKind::Match { subject, arms } => {
+            check_expression(subject, env, var_map)?;
+            let subject_ty = infer_expression(subject, env, var_map);
             for arm in arms {
                 match arm {
-                    HMatchArm::Case(_, s) | HMatchArm::Else(s) => {
-                        infer_statement(s, table, var_map);
+                    HMatchArm::Case(case, s) => {
+                        check_expression(case, env, var_map)?;
+                        if let Some(ty) = &subject_ty {
+                            type_literal(case, ty)?;
+                        }
+                        infer_statement(s, env, var_map, ret_type)?;
                     }
+                    HMatchArm::Else(s) => infer_statement(s, env, var_map, ret_type)?,
                 }
             }
         }
+        HStatementKind::Exp(e) => check_expression(e, env, var_map)?,
+        HStatementKind::Return(None) | HStatementKind::Break | HStatementKind::Continue => {}
+    }
+    Ok(())
+}
+
+/// Gives a variable that is declared as an empty array, e.g. `let names =
+/// []`, the type that the first assignment to it tells. Runs at the end of
+/// the block that declares it.
+fn infer_empty_array(
+    variable: &mut HVariable,
+    value: &Option<HExpression>,
+    var_map: &HashMap<String, Type>,
+) -> Result<(), Diagnostic> {
+    let empty = matches!(
+        value.as_ref().map(|v| &v.kind),
+        Some(HExpressionKind::Array { elements, .. }) if elements.is_empty()
+    );
+    if !empty || variable.ty.is_some() {
+        return Ok(());
+    }
+    variable.ty = var_map.get(&variable.name).cloned();
+    if variable.ty.is_none() {
+        return Err(Diagnostic::error(format!(
+            "Cannot infer the type of the empty array `{}`",
+            variable.name
+        ))
+        .with_code(codes::INFERENCE)
+        .with_label(variable.span, "")
+        .with_help(format!(
+            "specify the type of its elements, e.g. `{}: int[]`",
+            variable.name
+        )));
+    }
+    Ok(())
+}
+
+/// Values are never converted implicitly. `any` accepts every type,
+/// everything else has to match exactly, so newtypes are only compatible
+/// with themselves.
+pub(super) fn is_compatible(expected: &Type, actual: &Type) -> bool {
+    match (expected, actual) {
+        (Type::Any, _) | (_, Type::Any) => true,
+        (Type::Array(a, _), Type::Array(b, _)) => is_compatible(a, b),
+        (a, b) => a == b,
+    }
+}
+
+/// Gives untyped integer literals the sized integer type they are used as,
+/// e.g. `let x: u8 = 5`
+fn type_literal(expr: &mut HExpression, expected: &Type) -> Result<(), Diagnostic> {
+    match (&mut expr.kind, expected) {
+        (HExpressionKind::Int(value), ty) if ty.is_sized_integer() => {
+            let value = *value;
+            if ty.int_max().is_some_and(|max| value as u64 > max) {
+                return Err(Diagnostic::error(format!(
+                    "Literal `{}` does not fit into `{}`",
+                    value, ty
+                ))
+                .with_code(codes::LITERAL)
+                .with_label(expr.span, ""));
+            }
+            expr.kind = HExpressionKind::Cast {
+                expr: Box::new(HExpression::new(HExpressionKind::Int(value), expr.span)),
+                from: Some(Type::Int),
+                to: ty.clone(),
+            };
+        }
+        (HExpressionKind::Array { elements, .. }, Type::Array(inner, _)) => {
+            for el in elements {
+                type_literal(el, inner)?;
+            }
+        }
         _ => {}
     }
+    Ok(())
+}
+
+/// Checks the order of parameters with a default value and of a variadic
+/// parameter. Default values are copied into every call that leaves them
+/// out, so they may not refer to other parameters or call functions.
+fn check_parameters(arguments: &mut [HArgument], env: &Env) -> Result<(), Diagnostic> {
+    fn is_constant(expr: &HExpression) -> bool {
+        match &expr.kind {
+            HExpressionKind::Int(_) | HExpressionKind::Str(_) | HExpressionKind::Bool(_) => true,
+            HExpressionKind::Cast { expr, .. } => is_constant(expr),
+            HExpressionKind::Array { elements, .. } => elements.iter().all(is_constant),
+            _ => false,
+        }
+    }
+
+    let count = arguments.len();
+    let mut with_default: Option<&str> = None;
+    for (index, arg) in arguments.iter_mut().enumerate() {
+        if arg.variadic {
+            if index + 1 != count {
+                return Err(Diagnostic::error(format!(
+                    "Variadic parameter `{}` must be the last parameter",
+                    arg.name
+                ))
+                .with_code(codes::DEFINITION)
+                .with_label(arg.span, ""));
+            }
+            if arg.default.is_some() {
+                return Err(Diagnostic::error(format!(
+                    "Variadic parameter `{}` cannot have a default value",
+                    arg.name
+                ))
+                .with_code(codes::DEFINITION)
+                .with_label(arg.span, ""));
+            }
+            continue;
+        }
+        match (&mut arg.default, with_default) {
+            (Some(default), _) => {
+                if !is_constant(default) {
+                    return Err(Diagnostic::error(format!(
+                        "Default value of parameter `{}` must be a literal",
+                        arg.name
+                    ))
+                    .with_code(codes::DEFINITION)
+                    .with_label(default.span, ""));
+                }
+                check_expression(default, env, &HashMap::new())?;
+                if let Some(ty) = &arg.ty {
+                    type_literal(default, ty)?;
+                }
+                with_default = Some(&arg.name);
+            }
+            (None, Some(previous)) => {
+                return Err(Diagnostic::error(format!(
+                    "Parameter `{}` needs a default value, because it follows `{}` which has one",
+                    arg.name, previous
+                ))
+                .with_code(codes::DEFINITION)
+                .with_label(arg.span, ""))
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Walks an expression, resolves calls and members and records the operand
+/// type of every cast
+fn check_expression(
+    expr: &mut HExpression,
+    env: &Env,
+    var_map: &HashMap<String, Type>,
+) -> Result<(), Diagnostic> {
+    let span = expr.span;
+    match &mut expr.kind {
+        HExpressionKind::FunctionCall {
+            fn_name,
+            args,
+            named,
+        } => {
+            check_arguments(fn_name, args, named, span, env, var_map)?;
+        }
+        HExpressionKind::Array { elements, .. } => {
+            for el in elements.iter_mut() {
+                check_expression(el, env, var_map)?;
+            }
+            if let Some(Type::Array(elem_ty, _)) = infer_array(elements, env, var_map) {
+                for el in elements {
+                    type_literal(el, &elem_ty)?;
+                }
+            }
+        }
+        HExpressionKind::ArrayAccess { expr, index } => {
+            check_expression(expr, env, var_map)?;
+            check_expression(index, env, var_map)?;
+        }
+        HExpressionKind::BinOp { lhs, op, rhs, ty } => {
+            check_expression(lhs, env, var_map)?;
+            check_expression(rhs, env, var_map)?;
+            // `x + 1` takes the type of `x`, `1 + x` as well
+            if let Some(ty) = infer_expression(rhs, env, var_map) {
+                type_literal(lhs, &ty)?;
+            }
+            let lhs_ty = infer_expression(lhs, env, var_map);
+            if let Some(lhs_ty) = &lhs_ty {
+                type_literal(rhs, lhs_ty)?;
+            }
+            if op.is_arithmetic() {
+                *ty = lhs_ty.filter(|t| t.clone().erased().is_integer());
+            }
+        }
+        HExpressionKind::StructInitialization { name, fields } => {
+            for (field, value) in fields {
+                check_expression(value, env, var_map)?;
+                if let Some(ty) = env.fields.get(name).and_then(|f| f.get(field)) {
+                    type_literal(value, ty)?;
+                }
+            }
+        }
+        HExpressionKind::FieldAccess {
+            expr: receiver,
+            field,
+        } => {
+            check_expression(receiver, env, var_map)?;
+            let mut receiver_ty = infer_expression(receiver, env, var_map);
+            if let Some(Type::Struct(struct_name)) = &receiver_ty {
+                let member = match &field.kind {
+                    HExpressionKind::Variable(name) => name,
+                    HExpressionKind::FunctionCall { fn_name, .. } => fn_name,
+                    _ => return Ok(()),
+                };
+                // `admin.name` is a shorthand for `admin.User.name`
+                let path = env
+                    .promotion_path(struct_name, member)
+                    .map_err(|e| e.located_at(field.span))?;
+                if !path.is_empty() {
+                    for embedded in path {
+                        let placeholder = HExpression::new(HExpressionKind::Selff, receiver.span);
+                        let inner = std::mem::replace(receiver.as_mut(), placeholder);
+                        receiver.kind = HExpressionKind::FieldAccess {
+                            expr: Box::new(inner),
+                            field: Box::new(HExpression::new(
+                                HExpressionKind::Variable(embedded),
+                                field.span,
+                            )),
+                        };
+                    }
+                    receiver_ty = infer_expression(receiver, env, var_map);
+                }
+            }
+            let field_span = field.span;
+            if let HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            } = &mut field.kind
+            {
+                match receiver_ty {
+                    Some(Type::Struct(struct_name)) => {
+                        let mangled = format!("{}_{}", struct_name, fn_name);
+                        check_arguments(&mangled, args, named, span, env, var_map)?;
+                    }
+                    Some(ty) => {
+                        let target = ufcs_target(fn_name, &ty, env).ok_or_else(|| {
+                            Diagnostic::error(format!(
+                                "No function `{}` can be called on `{}`",
+                                fn_name, ty
+                            ))
+                            .with_code(codes::UNKNOWN_MEMBER)
+                            .with_label(field_span, "")
+                        })?;
+                        let placeholder = HExpression::new(HExpressionKind::Selff, receiver.span);
+                        let mut call_args = vec![std::mem::replace(receiver.as_mut(), placeholder)];
+                        call_args.append(args);
+                        expr.kind = HExpressionKind::FunctionCall {
+                            fn_name: target,
+                            args: call_args,
+                            named: std::mem::take(named),
+                        };
+                        return check_expression(expr, env, var_map);
+                    }
+                    None => {
+                        for arg in args.iter_mut().chain(named.iter_mut().map(|(_, arg)| arg)) {
+                            check_expression(arg, env, var_map)?;
+                        }
+                    }
+                }
+            }
+        }
+        HExpressionKind::Cast { expr, from, to } => {
+            check_expression(expr, env, var_map)?;
+            let operand = infer_expression(expr, env, var_map).ok_or_else(|| {
+                Diagnostic::error(format!(
+                    "Could not infer the type of the value cast to `{}`",
+                    to
+                ))
+                .with_code(codes::INFERENCE)
+                .with_label(expr.span, "")
+            })?;
+            *from = Some(operand);
+        }
+        HExpressionKind::Int(_)
+        | HExpressionKind::Str(_)
+        | HExpressionKind::Bool(_)
+        | HExpressionKind::Selff
+        | HExpressionKind::Variable(_) => {}
+    }
+    Ok(())
+}
+
+/// Uniform function call syntax: `x.f(args)` calls `f(x, args)` if the first
+/// parameter of `f` accepts `x`. Functions for strings, arrays and ints may
+/// carry a prefix instead, so `name.len()` also finds `str_len(name)`.
+fn ufcs_target(name: &str, receiver: &Type, env: &Env) -> Option<String> {
+    let prefix = match receiver {
+        Type::Str => Some("str_"),
+        Type::Array(..) => Some("array_"),
+        Type::Int => Some("int_"),
+        _ => None,
+    };
+    std::iter::once(name.to_string())
+        .chain(prefix.map(|prefix| format!("{}{}", prefix, name)))
+        .find(
+            |candidate| match env.params.get(candidate).map(|p| p.first()) {
+                Some(Some(Some(param))) => is_compatible(param, receiver),
+                Some(Some(None)) => true,
+                _ => false,
+            },
+        )
+}
+
+fn check_arguments(
+    fn_name: &str,
+    args: &mut Vec<HExpression>,
+    named: &mut Vec<(String, HExpression)>,
+    span: Span,
+    env: &Env,
+    var_map: &HashMap<String, Type>,
+) -> Result<(), Diagnostic> {
+    let params = env.arguments.get(fn_name);
+    let mut errors = env.call_errors.borrow_mut();
+    match (params, env.declarations.get(fn_name)) {
+        (Some(params), Some(callee)) => {
+            errors.extend(resolve_arguments(callee, params, args, named, span))
+        }
+        _ => {
+            if let Some((name, _)) = named.first() {
+                errors.push(
+                    Diagnostic::error(format!(
+                        "Cannot pass `{}` by name, the parameters of `{}` are unknown",
+                        name, fn_name
+                    ))
+                    .with_code(codes::ARGUMENTS)
+                    .with_label(span, ""),
+                );
+                named.clear();
+            }
+            match builtin_params(fn_name) {
+                Some(params) if params.len() != args.len() => {
+                    errors.push(
+                        Diagnostic::error(format!(
+                            "`{}` takes {} argument{}, but {} {} given",
+                            fn_name,
+                            params.len(),
+                            if params.len() == 1 { "" } else { "s" },
+                            args.len(),
+                            if args.len() == 1 { "was" } else { "were" }
+                        ))
+                        .with_code(codes::ARGUMENTS)
+                        .with_label(span, "")
+                        .with_note(format!("`{}` is provided by the backend runtime", fn_name)),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+    drop(errors);
+    for arg in args.iter_mut() {
+        check_expression(arg, env, var_map)?;
+    }
+    let rest_ty = match params.and_then(|params| params.last()) {
+        Some(HArgument {
+            variadic: true,
+            ty: Some(Type::Array(elem_ty, _)),
+            ..
+        }) => Some(elem_ty.as_ref()),
+        _ => None,
+    };
+    let rest = args.last_mut().map(|arg| &mut arg.kind);
+    if let (Some(elem_ty), Some(HExpressionKind::Array { elements, .. })) = (rest_ty, rest) {
+        for element in elements {
+            match elem_ty {
+                Type::Str => to_printable(element, env, var_map)?,
+                Type::Any => to_any(element, env, var_map),
+                _ => {}
+            }
+            type_literal(element, elem_ty)?;
+        }
+    }
+    if let Some(params) = env.params.get(fn_name) {
+        for (param, arg) in params.iter().zip(args.iter_mut()) {
+            if let Some(ty) = param {
+                convert_to(arg, ty, env, var_map)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Turns named arguments into positional ones and fills in the defaults of
+/// parameters that are left out, e.g. `file_open(path, mode: "w")` becomes
+/// `file_open(path, "w")`. The array of variadic arguments takes the span
+/// of the call. Returns every argument that does not fit the parameters.
+fn resolve_arguments(
+    callee: &Declaration,
+    params: &[HArgument],
+    args: &mut Vec<HExpression>,
+    named: &mut Vec<(String, HExpression)>,
+    span: Span,
+) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    // A variadic parameter takes every positional argument that is left over
+    let (params, rest) = match params.split_last() {
+        Some((last, fixed)) if last.variadic => {
+            let rest = args.split_off(fixed.len().min(args.len()));
+            (fixed, Some((last, rest)))
+        }
+        _ => (params, None),
+    };
+    if args.len() > params.len() {
+        errors.push(
+            Diagnostic::error(format!(
+                "`{}` takes {} argument{}, but {} {} given",
+                callee.name,
+                params.len(),
+                if params.len() == 1 { "" } else { "s" },
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" }
+            ))
+            .with_code(codes::ARGUMENTS)
+            .with_label(span, "")
+            .with_secondary(callee.span, "defined here"),
+        );
+    }
+    let mut slots: Vec<Option<HExpression>> = args.drain(..).map(Some).collect();
+    slots.resize(params.len(), None);
+    for (name, value) in named.drain(..) {
+        if rest.as_ref().is_some_and(|(param, _)| param.name == name) {
+            errors.push(
+                Diagnostic::error(format!(
+                    "Variadic parameter `{}` of `{}` cannot be passed by name",
+                    name, callee.name
+                ))
+                .with_code(codes::ARGUMENTS)
+                .with_label(value.span, "")
+                .with_secondary(callee.span, "defined here"),
+            );
+            continue;
+        }
+        let Some(index) = params.iter().position(|param| param.name == name) else {
+            errors.push(
+                Diagnostic::error(format!(
+                    "`{}` has no parameter named `{}`",
+                    callee.name, name
+                ))
+                .with_code(codes::ARGUMENTS)
+                .with_label(value.span, "")
+                .with_secondary(callee.span, "defined here"),
+            );
+            continue;
+        };
+        if slots[index].is_some() {
+            errors.push(
+                Diagnostic::error(format!(
+                    "Argument `{}` of `{}` is given more than once",
+                    name, callee.name
+                ))
+                .with_code(codes::ARGUMENTS)
+                .with_label(value.span, ""),
+            );
+            continue;
+        }
+        slots[index] = Some(value);
+    }
+    for (param, slot) in params.iter().zip(slots) {
+        match slot.or_else(|| param.default.clone()) {
+            Some(value) => args.push(value),
+            None => errors.push(
+                Diagnostic::error(format!(
+                    "Missing argument `{}` in call to `{}`",
+                    param.name, callee.name
+                ))
+                .with_code(codes::ARGUMENTS)
+                .with_label(span, "")
+                .with_secondary(param.span, "parameter without a default value"),
+            ),
+        }
+    }
+    if let Some((_, elements)) = rest {
+        args.push(HExpression::new(
+            HExpressionKind::Array {
+                capacity: elements.len(),
+                elements,
+            },
+            span,
+        ));
+    }
+    errors
+}
+
+/// A value passed to a `...string` parameter is converted with `as
+/// string`. This is what lets `println("Total: ", count)` print an `int`.
+/// The type of an `any` value is not known at runtime, so it cannot be
+/// converted.
+fn to_printable(
+    value: &mut HExpression,
+    env: &Env,
+    var_map: &HashMap<String, Type>,
+) -> Result<(), Diagnostic> {
+    match infer_expression(value, env, var_map) {
+        Some(Type::Str) | None => Ok(()),
+        Some(Type::Any) => Err(Diagnostic::error("Cannot convert `any` to `string`")
+            .with_code(codes::MISMATCHED_TYPES)
+            .with_label(value.span, "")
+            .with_note("the type of an `any` value is not known at runtime")),
+        Some(ty) if is_castable(&ty, &Type::Str) => {
+            let placeholder = HExpression::new(HExpressionKind::Selff, value.span);
+            let inner = std::mem::replace(value, placeholder);
+            value.kind = HExpressionKind::Cast {
+                expr: Box::new(inner),
+                from: Some(ty),
+                to: Type::Str,
+            };
+            Ok(())
+        }
+        Some(ty) => Err(
+            Diagnostic::error(format!("Cannot convert `{}` to `string`", ty))
+                .with_code(codes::MISMATCHED_TYPES)
+                .with_label(value.span, "")
+                .with_note("only values that can be cast to `string` are accepted"),
+        ),
+    }
+}
+
+/// Values are converted when they are passed as `any`, so that every `any`
+/// value has the same representation, e.g. 64 bits in QBE
+fn to_any(value: &mut HExpression, env: &Env, var_map: &HashMap<String, Type>) {
+    let ty = match infer_expression(value, env, var_map) {
+        Some(Type::Any) | None => return,
+        Some(ty) => ty,
+    };
+    let placeholder = HExpression::new(HExpressionKind::Selff, value.span);
+    let inner = std::mem::replace(value, placeholder);
+    value.kind = HExpressionKind::Cast {
+        expr: Box::new(inner),
+        from: Some(ty),
+        to: Type::Any,
+    };
+}
+
+/// Prepares a value that is used where `expected` is required: literals
+/// take the sized integer type, values used as `any` are converted
+fn convert_to(
+    value: &mut HExpression,
+    expected: &Type,
+    env: &Env,
+    var_map: &HashMap<String, Type>,
+) -> Result<(), Diagnostic> {
+    match (expected, &mut value.kind) {
+        (Type::Any, _) => to_any(value, env, var_map),
+        (Type::Array(elem_ty, _), HExpressionKind::Array { elements, .. })
+            if **elem_ty == Type::Any =>
+        {
+            for element in elements {
+                to_any(element, env, var_map);
+            }
+        }
+        _ => type_literal(value, expected)?,
+    }
+    Ok(())
+}
+
+/// Conversions that are allowed with `as`. Newtypes convert like their
+/// underlying type, and casting a value to its own type is always allowed.
+/// "integer" is `int` or any sized integer type.
+///
+/// | from      | to        |                                           |
+/// |-----------|-----------|-------------------------------------------|
+/// | integer   | integer   | wraps around if the value does not fit    |
+/// | integer   | `string`  | decimal representation                    |
+/// | `bool`    | `string`  | `"true"` or `"false"`                     |
+/// | `string`  | integer   | leading decimal digits, else `0`          |
+/// | `bool`    | integer   | `1` or `0`                                |
+/// | integer   | `bool`    | `true` unless `0`                         |
+/// | any type  | `any`     | unchanged, see `to_any`                   |
+pub(super) fn is_castable(from: &Type, to: &Type) -> bool {
+    match (from.clone().erased(), to.clone().erased()) {
+        (Type::Array(a, _), Type::Array(b, _)) => a == b,
+        (a, b) if a.is_integer() && (b.is_integer() || b == Type::Str || b == Type::Bool) => true,
+        (Type::Str, b) | (Type::Bool, b) if b.is_integer() => true,
+        (Type::Bool, Type::Str) => true,
+        (_, Type::Any) => true,
+        (Type::Any, _) => false,
+        (a, b) => a == b,
+    }
 }
 
 /// Function table is needed to infer possible function calls
-fn infer_expression(
+pub(super) fn infer_expression(
     expr: &HExpression,
-    table: &SymbolTable,
+    env: &Env,
     var_map: &HashMap<String, Type>,
 ) -> Option<Type> {
-    match expr {
-        HExpression::Int(_) => Some(Type::Int),
-        HExpression::Bool(_) => Some(Type::Bool),
-        HExpression::Str(_) => Some(Type::Str),
-        HExpression::StructInitialization { name, fields: _ } => {
+    match &expr.kind {
+        HExpressionKind::Int(_) => Some(Type::Int),
+        HExpressionKind::Bool(_) => Some(Type::Bool),
+        HExpressionKind::Str(_) => Some(Type::Str),
+        HExpressionKind::StructInitialization { name, fields: _ } => {
             Some(Type::Struct(name.to_string()))
         }
-        HExpression::FunctionCall { fn_name, args: _ } => infer_function_call(fn_name, table),
-        HExpression::Array {
+        HExpressionKind::FunctionCall { fn_name, .. } => infer_function_call(fn_name, &env.table),
+        HExpressionKind::Array {
             capacity: _,
             elements,
-        } => infer_array(elements, table, var_map),
-        HExpression::Variable(name) => var_map.get(name).cloned(),
-        HExpression::ArrayAccess { name, .. } => {
-            // Infer element type from the array variable's type
-            match var_map.get(name) {
-                Some(Type::Array(elem_ty, _)) => Some(*elem_ty.clone()),
+        } => infer_array(elements, env, var_map),
+        HExpressionKind::Variable(name) => var_map.get(name).cloned(),
+        HExpressionKind::ArrayAccess { expr, .. } => {
+            // Infer element type from the type of the indexed expression
+            match infer_expression(expr, env, var_map) {
+                Some(Type::Array(elem_ty, _)) => Some(*elem_ty),
                 _ => None,
             }
         }
-        HExpression::BinOp { lhs, op, rhs } => match op {
+        HExpressionKind::BinOp { lhs, op, rhs, .. } => match op {
             HBinOp::Equal
             | HBinOp::NotEqual
             | HBinOp::LessThan
@@ -129,40 +1133,64 @@ fn infer_expression(
             | HBinOp::GreaterThanOrEqual
             | HBinOp::And
             | HBinOp::Or => Some(Type::Bool),
-            _ => infer_expression(lhs, table, var_map)
-                .or_else(|| infer_expression(rhs, table, var_map))
+            _ => infer_expression(lhs, env, var_map)
+                .or_else(|| infer_expression(rhs, env, var_map))
                 .or(Some(Type::Int)),
         },
-        HExpression::FieldAccess { expr, field } => {
-            if let HExpression::FunctionCall { fn_name, .. } = field.as_ref() {
-                // Method call: obj.method() — resolve receiver type, look up mangled name
-                if let Some(Type::Struct(struct_name)) = infer_expression(expr, table, var_map) {
+        HExpressionKind::FieldAccess { expr, field } => {
+            let struct_name = match infer_expression(expr, env, var_map) {
+                Some(Type::Struct(struct_name)) => struct_name,
+                _ => return None,
+            };
+            match &field.kind {
+                // Method call: obj.method() — look up the mangled name
+                HExpressionKind::FunctionCall { fn_name, .. } => {
                     let mangled = format!("{}_{}", struct_name, fn_name);
-                    return infer_function_call(&mangled, table);
+                    infer_function_call(&mangled, &env.table)
+                }
+                HExpressionKind::Variable(field_name) => {
+                    env.fields.get(&struct_name)?.get(field_name).cloned()
                 }
+                _ => None,
             }
-            None
         }
-        _ => None,
+        HExpressionKind::Selff => var_map.get("self").cloned(),
+        HExpressionKind::Cast { to, .. } => Some(to.clone()),
     }
 }
 
-fn infer_array(
+/// The type of an array literal, which is the type of its first element.
+/// Untyped integer literals adapt to the other elements, so `[1, x]` is a
+/// `u8[]` if `x` is a `u8`, and `any` values adapt to everything. Whether
+/// all elements fit that type is checked by `check::check`.
+pub(super) fn infer_array(
     elements: &[HExpression],
-    table: &SymbolTable,
+    env: &Env,
     var_map: &HashMap<String, Type>,
 ) -> Option<Type> {
-    let types: Vec<Option<Type>> = elements
-        .iter()
-        .map(|el| infer_expression(el, table, var_map))
-        .collect();
-
-    // TODO: This approach only relies on the first element.
-    // It will not catch that types are possibly inconsistent.
-    types
-        .first()
-        .and_then(|ty| ty.to_owned())
-        .map(|ty| Type::Array(Box::new(ty), Some(types.len())))
+    let mut first: Option<(Type, bool)> = None;
+    let mut sized = None;
+    let mut any = false;
+    for el in elements {
+        let literal = matches!(el.kind, HExpressionKind::Int(_));
+        match infer_expression(el, env, var_map) {
+            None => {}
+            Some(Type::Any) => any = true,
+            Some(ty) => {
+                if !literal && sized.is_none() && ty.is_sized_integer() {
+                    sized = Some(ty.clone());
+                }
+                first.get_or_insert((ty, literal));
+            }
+        }
+    }
+    let ty = match (first, sized) {
+        (Some((Type::Int, true)), Some(sized)) => sized,
+        (Some((ty, _)), _) => ty,
+        (None, _) if any => Type::Any,
+        (None, _) => return None,
+    };
+    Some(Type::Array(Box::new(ty), Some(elements.len())))
 }
 
 fn infer_function_call(name: &str, table: &SymbolTable) -> Option<Type> {
@@ -172,12 +1200,33 @@ fn infer_function_call(name: &str, table: &SymbolTable) -> Option<Type> {
     }
 }
 
+/// Parameter types of the functions that the backend runtimes provide, e.g.
+/// `_printf(msg: string)`. Other functions starting with `_` are unknown to
+/// the compiler and their arguments are not checked.
+pub(super) fn builtin_params(name: &str) -> Option<Vec<Type>> {
+    let params = match name {
+        "_read_line" | "_argc" => vec![],
+        "_printf" | "_strlen" | "_parse_int" | "_parse_long" | "_fread_all" | "_fclose" => {
+            vec![Type::Str]
+        }
+        "_exit" | "_int_to_str" | "_malloc" | "_argv" => vec![Type::Int],
+        "_str_concat" | "_str_split" | "_strcmp" | "_fopen" | "_fwrite_str" => {
+            vec![Type::Str, Type::Str]
+        }
+        "_str_char_at" => vec![Type::Str, Type::Int],
+        "_str_substr" => vec![Type::Str, Type::Int, Type::Int],
+        _ => return None,
+    };
+    Some(params)
+}
+
 fn infer_builtin(name: &str) -> Option<Type> {
     match name {
         "len" | "_strlen" | "_parse_int" | "_strcmp" | "_fclose" | "_fwrite_str" | "_argc"
         | "argc" => Some(Type::Int),
         "_str_concat" | "_int_to_str" | "_read_line" | "_str_char_at" | "_str_substr"
         | "_malloc" | "_fopen" | "_fread_all" | "_argv" | "argv" => Some(Type::Str),
+        "_str_split" => Some(Type::Array(Box::new(Type::Str), None)),
         "_printf" | "_exit" => None, // void return
         _ => None,
     }
@@ -187,20 +1236,20 @@ fn infer_builtin(name: &str) -> Option<Type> {
 mod tests {
     use super::*;
     use crate::ast::hast::{HFunction, HVariable};
-    use std::collections::HashSet;
 
     fn module(funcs: Vec<HFunction>) -> HModule {
         HModule {
             func: funcs,
             structs: vec![],
+            types: vec![],
             globals: vec![],
-            imports: HashSet::new(),
+            imports: HashMap::new(),
         }
     }
 
     fn func(
         name: &str,
-        arguments: Vec<HVariable>,
+        arguments: Vec<HArgument>,
         body: HStatement,
         ret_type: Option<Type>,
     ) -> HFunction {
@@ -209,6 +1258,8 @@ mod tests {
             arguments,
             body,
             ret_type,
+            mutable_self: false,
+            span: Span::default(),
         }
     }
 
@@ -216,42 +1267,50 @@ mod tests {
         HVariable {
             name: name.to_string(),
             ty,
+            span: Span::default(),
         }
     }
 
     fn block(stmts: Vec<HStatement>) -> HStatement {
-        HStatement::Block {
+        HStatementKind::Block {
             statements: stmts,
             scope: vec![],
         }
+        .into()
     }
 
     fn declare(name: &str, ty: Option<Type>, value: Option<HExpression>) -> HStatement {
-        HStatement::Declare {
+        HStatementKind::Declare {
             variable: var(name, ty),
             value,
+            mutable: false,
         }
+        .into()
     }
 
     /// Helper: run inference and return the body statements of the first function
     fn infer_and_get_stmts(m: &mut HModule) -> Vec<HStatement> {
-        infer(m);
-        match &m.func[0].body {
-            HStatement::Block { statements, .. } => statements.clone(),
-            other => vec![other.clone()],
+        infer(m).unwrap();
+        match &m.func[0].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
+            _ => vec![m.func[0].body.clone()],
         }
     }
 
     fn get_declared_type(stmt: &HStatement) -> Option<Type> {
-        match stmt {
-            HStatement::Declare { variable, .. } => variable.ty.clone(),
+        match &stmt.kind {
+            HStatementKind::Declare { variable, .. } => variable.ty.clone(),
             _ => panic!("expected Declare statement"),
         }
     }
 
     #[test]
     fn test_infer_int_literal() {
-        let body = block(vec![declare("x", None, Some(HExpression::Int(42)))]);
+        let body = block(vec![declare(
+            "x",
+            None,
+            Some(HExpressionKind::Int(42).into()),
+        )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
         assert_eq!(get_declared_type(&stmts[0]), Some(Type::Int));
@@ -259,7 +1318,11 @@ mod tests {
 
     #[test]
     fn test_infer_bool_literal() {
-        let body = block(vec![declare("x", None, Some(HExpression::Bool(true)))]);
+        let body = block(vec![declare(
+            "x",
+            None,
+            Some(HExpressionKind::Bool(true).into()),
+        )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
         assert_eq!(get_declared_type(&stmts[0]), Some(Type::Bool));
@@ -270,7 +1333,7 @@ mod tests {
         let body = block(vec![declare(
             "x",
             None,
-            Some(HExpression::Str("hello".into())),
+            Some(HExpressionKind::Str("hello".into()).into()),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -282,9 +1345,15 @@ mod tests {
         let body = block(vec![declare(
             "y",
             None,
-            Some(HExpression::Variable("x".into())),
+            Some(HExpressionKind::Variable("x".into()).into()),
         )]);
-        let args = vec![var("x", Some(Type::Int))];
+        let args = vec![HArgument {
+            name: "x".into(),
+            ty: Some(Type::Int),
+            default: None,
+            variadic: false,
+            span: Span::default(),
+        }];
         let mut m = module(vec![func("main", args, body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
         assert_eq!(get_declared_type(&stmts[0]), Some(Type::Int));
@@ -295,14 +1364,17 @@ mod tests {
         let body = block(vec![declare(
             "a",
             None,
-            Some(HExpression::Array {
-                capacity: 3,
-                elements: vec![
-                    HExpression::Int(1),
-                    HExpression::Int(2),
-                    HExpression::Int(3),
-                ],
-            }),
+            Some(
+                HExpressionKind::Array {
+                    capacity: 3,
+                    elements: vec![
+                        HExpressionKind::Int(1).into(),
+                        HExpressionKind::Int(2).into(),
+                        HExpressionKind::Int(3).into(),
+                    ],
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -317,19 +1389,23 @@ mod tests {
         let body = block(vec![declare(
             "x",
             None,
-            Some(HExpression::FunctionCall {
-                fn_name: "foo".into(),
-                args: vec![],
-            }),
+            Some(
+                HExpressionKind::FunctionCall {
+                    fn_name: "foo".into(),
+                    args: vec![],
+                    named: vec![],
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![
             func("foo", vec![], block(vec![]), Some(Type::Int)),
             func("main", vec![], body, None),
         ]);
-        infer(&mut m);
+        infer(&mut m).unwrap();
         // main is func[1] after adding foo
-        let stmts = match &m.func[1].body {
-            HStatement::Block { statements, .. } => statements.clone(),
+        let stmts = match &m.func[1].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
             _ => panic!(),
         };
         assert_eq!(get_declared_type(&stmts[0]), Some(Type::Int));
@@ -340,10 +1416,14 @@ mod tests {
         let body = block(vec![declare(
             "x",
             None,
-            Some(HExpression::FunctionCall {
-                fn_name: "len".into(),
-                args: vec![HExpression::Variable("a".into())],
-            }),
+            Some(
+                HExpressionKind::FunctionCall {
+                    fn_name: "len".into(),
+                    args: vec![HExpressionKind::Variable("a".into()).into()],
+                    named: vec![],
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -355,11 +1435,15 @@ mod tests {
         let body = block(vec![declare(
             "x",
             None,
-            Some(HExpression::BinOp {
-                lhs: Box::new(HExpression::Int(1)),
-                op: HBinOp::Equal,
-                rhs: Box::new(HExpression::Int(2)),
-            }),
+            Some(
+                HExpressionKind::BinOp {
+                    lhs: Box::new(HExpressionKind::Int(1).into()),
+                    op: HBinOp::Equal,
+                    rhs: Box::new(HExpressionKind::Int(2).into()),
+                    ty: None,
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -371,11 +1455,15 @@ mod tests {
         let body = block(vec![declare(
             "x",
             None,
-            Some(HExpression::BinOp {
-                lhs: Box::new(HExpression::Int(1)),
-                op: HBinOp::Addition,
-                rhs: Box::new(HExpression::Int(2)),
-            }),
+            Some(
+                HExpressionKind::BinOp {
+                    lhs: Box::new(HExpressionKind::Int(1).into()),
+                    op: HBinOp::Addition,
+                    rhs: Box::new(HExpressionKind::Int(2).into()),
+                    ty: None,
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -387,10 +1475,16 @@ mod tests {
         let body = block(vec![declare(
             "s",
             None,
-            Some(HExpression::StructInitialization {
-                name: "Point".into(),
-                fields: HashMap::from([("x".to_string(), Box::new(HExpression::Int(1)))]),
-            }),
+            Some(
+                HExpressionKind::StructInitialization {
+                    name: "Point".into(),
+                    fields: HashMap::from([(
+                        "x".to_string(),
+                        Box::new(HExpressionKind::Int(1).into()),
+                    )]),
+                }
+                .into(),
+            ),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
@@ -403,20 +1497,21 @@ mod tests {
     #[test]
     fn test_infer_for_loop_element() {
         let arr_ty = Type::Array(Box::new(Type::Int), Some(3));
-        let for_stmt = HStatement::For {
+        let for_stmt = HStatementKind::For {
             ident: var("i", None),
-            expr: HExpression::Variable("arr".into()),
+            expr: HExpressionKind::Variable("arr".into()).into(),
             body: Box::new(block(vec![])),
-        };
+        }
+        .into();
         let body = block(vec![declare("arr", Some(arr_ty), None), for_stmt]);
         let mut m = module(vec![func("main", vec![], body, None)]);
-        infer(&mut m);
-        let stmts = match &m.func[0].body {
-            HStatement::Block { statements, .. } => statements.clone(),
+        infer(&mut m).unwrap();
+        let stmts = match &m.func[0].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
             _ => panic!(),
         };
-        match &stmts[1] {
-            HStatement::For { ident, .. } => {
+        match &stmts[1].kind {
+            HStatementKind::For { ident, .. } => {
                 assert_eq!(ident.ty, Some(Type::Int));
             }
             _ => panic!("expected For statement"),
@@ -431,16 +1526,19 @@ mod tests {
             declare(
                 "x",
                 None,
-                Some(HExpression::ArrayAccess {
-                    name: "arr".into(),
-                    index: Box::new(HExpression::Int(0)),
-                }),
+                Some(
+                    HExpressionKind::ArrayAccess {
+                        expr: Box::new(HExpressionKind::Variable("arr".into()).into()),
+                        index: Box::new(HExpressionKind::Int(0).into()),
+                    }
+                    .into(),
+                ),
             ),
         ]);
         let mut m = module(vec![func("main", vec![], body, None)]);
-        infer(&mut m);
-        let stmts = match &m.func[0].body {
-            HStatement::Block { statements, .. } => statements.clone(),
+        infer(&mut m).unwrap();
+        let stmts = match &m.func[0].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
             _ => panic!(),
         };
         assert_eq!(get_declared_type(&stmts[1]), Some(Type::Int));
@@ -448,35 +1546,40 @@ mod tests {
 
     #[test]
     fn test_infer_nested_if() {
-        let if_body = block(vec![declare("x", None, Some(HExpression::Int(1)))]);
+        let if_body = block(vec![declare(
+            "x",
+            None,
+            Some(HExpressionKind::Int(1).into()),
+        )]);
         let else_body = block(vec![declare(
             "y",
             None,
-            Some(HExpression::Str("hi".into())),
+            Some(HExpressionKind::Str("hi".into()).into()),
         )]);
-        let if_stmt = HStatement::If {
-            condition: HExpression::Bool(true),
+        let if_stmt = HStatementKind::If {
+            condition: HExpressionKind::Bool(true).into(),
             body: Box::new(if_body),
             else_branch: Some(Box::new(else_body)),
-        };
+        }
+        .into();
         let body = block(vec![if_stmt]);
         let mut m = module(vec![func("main", vec![], body, None)]);
-        infer(&mut m);
-        let stmts = match &m.func[0].body {
-            HStatement::Block { statements, .. } => statements.clone(),
+        infer(&mut m).unwrap();
+        let stmts = match &m.func[0].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
             _ => panic!(),
         };
-        match &stmts[0] {
-            HStatement::If {
+        match &stmts[0].kind {
+            HStatementKind::If {
                 body, else_branch, ..
             } => {
-                let if_stmts = match body.as_ref() {
-                    HStatement::Block { statements, .. } => statements,
+                let if_stmts = match &body.kind {
+                    HStatementKind::Block { statements, .. } => statements,
                     _ => panic!(),
                 };
                 assert_eq!(get_declared_type(&if_stmts[0]), Some(Type::Int));
-                let else_stmts = match else_branch.as_ref().unwrap().as_ref() {
-                    HStatement::Block { statements, .. } => statements,
+                let else_stmts = match &else_branch.as_ref().unwrap().kind {
+                    HStatementKind::Block { statements, .. } => statements,
                     _ => panic!(),
                 };
                 assert_eq!(get_declared_type(&else_stmts[0]), Some(Type::Str));
@@ -487,32 +1590,50 @@ mod tests {
 
     #[test]
     fn test_infer_match_arms() {
-        let arm_body = block(vec![declare("x", None, Some(HExpression::Int(1)))]);
-        let else_body = block(vec![declare("y", None, Some(HExpression::Bool(false)))]);
-        let match_stmt = HStatement::Match {
-            subject: HExpression::Int(1),
+        let arm_body = block(vec![declare(
+            "x",
+            None,
+            Some(HExpressionKind::Int(1).into()),
+        )]);
+        let else_body = block(vec![declare(
+            "y",
+            None,
+            Some(HExpressionKind::Bool(false).into()),
+        )]);
+        let match_stmt = HStatementKind::Match {
+            subject: HExpressionKind::Int(1).into(),
             arms: vec![
-                HMatchArm::Case(HExpression::Int(1), arm_body),
+                HMatchArm::Case(HExpressionKind::Int(1).into(), arm_body),
                 HMatchArm::Else(else_body),
             ],
-        };
+        }
+        .into();
         let body = block(vec![match_stmt]);
         let mut m = module(vec![func("main", vec![], body, None)]);
-        infer(&mut m);
-        let stmts = match &m.func[0].body {
-            HStatement::Block { statements, .. } => statements.clone(),
+        infer(&mut m).unwrap();
+        let stmts = match &m.func[0].body.kind {
+            HStatementKind::Block { statements, .. } => statements.clone(),
             _ => panic!(),
         };
-        match &stmts[0] {
-            HStatement::Match { arms, .. } => {
+        match &stmts[0].kind {
+            HStatementKind::Match { arms, .. } => {
                 match &arms[0] {
-                    HMatchArm::Case(_, HStatement::Block { statements, .. }) => {
+                    HMatchArm::Case(
+                        _,
+                        HStatement {
+                            kind: HStatementKind::Block { statements, .. },
+                            ..
+                        },
+                    ) => {
                         assert_eq!(get_declared_type(&statements[0]), Some(Type::Int));
                     }
                     _ => panic!(),
                 }
                 match &arms[1] {
-                    HMatchArm::Else(HStatement::Block { statements, .. }) => {
+                    HMatchArm::Else(HStatement {
+                        kind: HStatementKind::Block { statements, .. },
+                        ..
+                    }) => {
                         assert_eq!(get_declared_type(&statements[0]), Some(Type::Bool));
                     }
                     _ => panic!(),
@@ -524,14 +1645,126 @@ mod tests {
 
     #[test]
     fn test_explicit_type_not_overwritten() {
+        let body = block(vec![declare(
+            "x",
+            Some(Type::Any),
+            Some(HExpressionKind::Int(42).into()),
+        )]);
+        let mut m = module(vec![func("main", vec![], body, None)]);
+        let stmts = infer_and_get_stmts(&mut m);
+        assert_eq!(get_declared_type(&stmts[0]), Some(Type::Any));
+    }
+
+    #[test]
+    fn test_no_implicit_conversion() {
         let body = block(vec![declare(
             "x",
             Some(Type::Str),
-            Some(HExpression::Int(42)),
+            Some(HExpressionKind::Int(42).into()),
         )]);
         let mut m = module(vec![func("main", vec![], body, None)]);
+        assert!(infer(&mut m)
+            .and_then(|_| crate::parser::check_types(&m))
+            .is_err());
+    }
+
+    #[test]
+    fn test_cast_records_operand_type() {
+        let cast = HExpressionKind::Cast {
+            expr: Box::new(HExpressionKind::Int(42).into()),
+            from: None,
+            to: Type::Str,
+        }
+        .into();
+        let body = block(vec![declare("x", None, Some(cast))]);
+        let mut m = module(vec![func("main", vec![], body, None)]);
         let stmts = infer_and_get_stmts(&mut m);
         assert_eq!(get_declared_type(&stmts[0]), Some(Type::Str));
+        let HStatementKind::Declare {
+            value:
+                Some(HExpression {
+                    kind: HExpressionKind::Cast { from, .. },
+                    ..
+                }),
+            ..
+        } = &stmts[0].kind
+        else {
+            panic!("expected a cast");
+        };
+        assert_eq!(from, &Some(Type::Int));
+    }
+
+    #[test]
+    fn test_invalid_cast() {
+        let cast = HExpressionKind::Cast {
+            expr: Box::new(HExpressionKind::Str("yes".into()).into()),
+            from: None,
+            to: Type::Bool,
+        }
+        .into();
+        let body = block(vec![declare("x", None, Some(cast))]);
+        let mut m = module(vec![func("main", vec![], body, None)]);
+        assert!(infer(&mut m)
+            .and_then(|_| crate::parser::check_types(&m))
+            .is_err());
+    }
+
+    #[test]
+    fn test_integer_literal_takes_declared_type() {
+        let body = block(vec![declare(
+            "x",
+            Some(Type::U16),
+            Some(HExpressionKind::Int(7).into()),
+        )]);
+        let mut m = module(vec![func("main", vec![], body, None)]);
+        let stmts = infer_and_get_stmts(&mut m);
+        let HStatementKind::Declare {
+            value:
+                Some(HExpression {
+                    kind: HExpressionKind::Cast { from, to, .. },
+                    ..
+                }),
+            ..
+        } = &stmts[0].kind
+        else {
+            panic!("expected a cast");
+        };
+        assert_eq!((from, to), (&Some(Type::Int), &Type::U16));
+    }
+
+    #[test]
+    fn test_arithmetic_records_integer_type() {
+        let body = block(vec![
+            declare("x", Some(Type::I64), Some(HExpressionKind::Int(1).into())),
+            declare(
+                "y",
+                None,
+                Some(
+                    HExpressionKind::BinOp {
+                        lhs: Box::new(HExpressionKind::Variable("x".into()).into()),
+                        op: HBinOp::Multiplication,
+                        rhs: Box::new(HExpressionKind::Int(2).into()),
+                        ty: None,
+                    }
+                    .into(),
+                ),
+            ),
+        ]);
+        let mut m = module(vec![func("main", vec![], body, None)]);
+        let stmts = infer_and_get_stmts(&mut m);
+        assert_eq!(get_declared_type(&stmts[1]), Some(Type::I64));
+        let HStatementKind::Declare {
+            value:
+                Some(HExpression {
+                    kind: HExpressionKind::BinOp { ty, .. },
+                    ..
+                }),
+            ..
+        } = &stmts[1].kind
+        else {
+            panic!("expected a binary operation");
+        };
+        assert_eq!(ty, &Some(Type::I64));
     }
 
     #[test]
@@ -0,0 +1,567 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::patterns;
+use crate::ast::hast::{
+    HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement, HStatementKind,
+};
+use crate::ast::span::{FileId, Span};
+use crate::ast::types::Type;
+use crate::diagnostic::codes::{self, Code};
+use crate::diagnostic::{Diagnostic, Level, Options, Severity};
+use std::collections::{HashMap, HashSet};
+
+/// A check for code that compiles, but is likely a mistake
+pub struct Lint {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The code of the diagnostics the lint reports
+    pub code: Code,
+}
+
+pub const UNUSED_VARIABLES: Lint = Lint {
+    name: "unused_variables",
+    description: "variables that are declared but never read",
+    code: codes::UNUSED_VARIABLE,
+};
+pub const UNUSED_PARAMETERS: Lint = Lint {
+    name: "unused_parameters",
+    description: "function parameters that are never read",
+    code: codes::UNUSED_PARAMETER,
+};
+pub const UNUSED_IMPORTS: Lint = Lint {
+    name: "unused_imports",
+    description: "imported modules that nothing is used from",
+    code: codes::UNUSED_IMPORT,
+};
+pub const UNUSED_FUNCTIONS: Lint = Lint {
+    name: "unused_functions",
+    description: "functions that are never called",
+    code: codes::UNUSED_FUNCTION,
+};
+pub const UNREACHABLE_CODE: Lint = Lint {
+    name: "unreachable_code",
+    description: "statements after `return`, `break` or `continue`",
+    code: codes::UNREACHABLE_CODE,
+};
+pub const SHADOWED_VARIABLES: Lint = Lint {
+    name: "shadowed_variables",
+    description: "variables that shadow a variable of an outer scope",
+    code: codes::SHADOWED_VARIABLE,
+};
+pub const UNREACHABLE_PATTERNS: Lint = Lint {
+    name: "unreachable_patterns",
+    description: "`match` arms that can never be chosen",
+    code: codes::UNREACHABLE_PATTERN,
+};
+pub const NON_EXHAUSTIVE_MATCH: Lint = Lint {
+    name: "non_exhaustive_match",
+    description: "`match` on a `bool` that does not cover both values",
+    code: codes::NON_EXHAUSTIVE_MATCH,
+};
+
+pub const LINTS: &[Lint] = &[
+    UNUSED_VARIABLES,
+    UNUSED_PARAMETERS,
+    UNUSED_IMPORTS,
+    UNUSED_FUNCTIONS,
+    UNREACHABLE_CODE,
+    SHADOWED_VARIABLES,
+    UNREACHABLE_PATTERNS,
+    NON_EXHAUSTIVE_MATCH,
+];
+
+/// An `import` of a module, `module` is the file it was read from
+pub struct Import {
+    pub path: String,
+    pub span: Span,
+    pub module: FileId,
+}
+
+/// A variable or parameter that is in scope
+struct Binding {
+    name: String,
+    span: Span,
+    parameter: bool,
+    used: bool,
+}
+
+struct Linter<'a> {
+    options: &'a Options,
+    /// Only code in these files is linted, the standard library is not
+    files: &'a HashSet<FileId>,
+    scopes: Vec<Vec<Binding>>,
+    /// Names of the functions that are called anywhere
+    called: HashSet<String>,
+    /// Functions, structs and types that the code of each file refers to
+    used: HashMap<FileId, HashSet<String>>,
+    /// File of the function that is linted
+    file: FileId,
+    warnings: Vec<Diagnostic>,
+}
+
+/// Reports code in `files` that is likely a mistake, as warnings or as
+/// errors if the lint is denied. Runs after type checking, when method call
+/// syntax has been resolved to function calls.
+pub fn lint(
+    program: &HModule,
+    files: &HashSet<FileId>,
+    imports: &[Import],
+    options: &Options,
+) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        options,
+        files,
+        scopes: Vec::new(),
+        called: HashSet::new(),
+        used: HashMap::new(),
+        file: FileId::default(),
+        warnings: Vec::new(),
+    };
+    for func in &program.func {
+        linter.function(func);
+    }
+    for def in &program.structs {
+        linter.file = def.span.file;
+        for field in &def.fields {
+            linter.optional_type(&field.ty);
+        }
+        for embed in &def.embeds {
+            linter.uses(embed);
+        }
+        for method in &def.methods {
+            linter.function(method);
+        }
+    }
+    for def in &program.types {
+        linter.file = def.span.file;
+        linter.ty(&def.ty);
+    }
+
+    for func in &program.func {
+        if func.name != "main" && !func.name.starts_with('_') && !linter.called.contains(&func.name)
+        {
+            linter.emit(
+                &UNUSED_FUNCTIONS,
+                Diagnostic::warning(format!("Function `{}` is never used", func.name))
+                    .with_label(func.span, ""),
+            );
+        }
+    }
+    linter.imports(program, imports);
+    // In the order of the source, not of the scopes they were found in
+    linter.warnings.sort_by_key(|w| {
+        w.primary
+            .as_ref()
+            .map(|label| (label.span.file.0, label.span.start))
+    });
+    linter.warnings
+}
+
+impl Linter<'_> {
+    /// Reports a problem at the level of its lint
+    fn emit(&mut self, lint: &Lint, diagnostic: Diagnostic) {
+        let in_files = diagnostic
+            .primary
+            .as_ref()
+            .is_some_and(|label| self.files.contains(&label.span.file));
+        if !in_files {
+            return;
+        }
+        let diagnostic = diagnostic.with_code(lint.code);
+        let diagnostic = match self.options.level(lint.name) {
+            Level::Allow => return,
+            Level::Warn => diagnostic.with_note(format!(
+                "`{}` is on by default, use `-A {}` to allow it",
+                lint.name, lint.name
+            )),
+            Level::Deny => Diagnostic {
+                severity: Severity::Error,
+                ..diagnostic.with_note(format!("`{}` is denied", lint.name))
+            },
+        };
+        self.warnings.push(diagnostic);
+    }
+
+    /// Records that the current file refers to a function, struct or type
+    fn uses(&mut self, name: &str) {
+        self.used
+            .entry(self.file)
+            .or_default()
+            .insert(name.to_string());
+    }
+
+    fn declare(&mut self, name: &str, span: Span, parameter: bool) {
+        if !parameter {
+            let outer = self
+                .scopes
+                .iter()
+                .rev()
+                .skip(1)
+                .flatten()
+                .find(|binding| binding.name == name)
+                .map(|binding| binding.span);
+            if let Some(outer) = outer {
+                self.emit(
+                    &SHADOWED_VARIABLES,
+                    Diagnostic::warning(format!(
+                        "Variable `{}` shadows a variable of an outer scope",
+                        name
+                    ))
+                    .with_label(span, "")
+                    .with_secondary(outer, "shadowed variable"),
+                );
+            }
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding {
+                name: name.to_string(),
+                span,
+                parameter,
+                used: false,
+            });
+        }
+    }
+
+    fn read(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name);
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Leaves a scope and reports its bindings that were never read.
+    /// Names starting with `_` are unused on purpose.
+    fn pop_scope(&mut self) {
+        for binding in self.scopes.pop().into_iter().flatten() {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+            // The span of a parameter includes its type, the name comes first
+            let name_span = Span {
+                end: binding.span.start + binding.name.len(),
+                ..binding.span
+            };
+            let (lint, kind) = if binding.parameter {
+                (&UNUSED_PARAMETERS, "Parameter")
+            } else {
+                (&UNUSED_VARIABLES, "Variable")
+            };
+            self.emit(
+                lint,
+                Diagnostic::warning(format!("{} `{}` is never used", kind, binding.name))
+                    .with_label(binding.span, "")
+                    .with_help(format!(
+                        "prefix it with an underscore if this is intended: `_{}`",
+                        binding.name
+                    ))
+                    .with_suggestion(name_span, format!("_{}", binding.name)),
+            );
+        }
+    }
+
+    fn function(&mut self, func: &HFunction) {
+        self.file = func.span.file;
+        self.push_scope();
+        for arg in &func.arguments {
+            self.optional_type(&arg.ty);
+            if let Some(default) = &arg.default {
+                self.expression(default);
+            }
+            self.declare(&arg.name, arg.span, true);
+        }
+        self.optional_type(&func.ret_type);
+        self.statement(&func.body);
+        self.pop_scope();
+    }
+
+    fn optional_type(&mut self, ty: &Option<Type>) {
+        if let Some(ty) = ty {
+            self.ty(ty);
+        }
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Struct(name) => self.uses(name),
+            Type::Newtype(name, inner) => {
+                self.uses(name);
+                self.ty(inner);
+            }
+            Type::Array(inner, _) => self.ty(inner),
+            _ => {}
+        }
+    }
+
+    fn statement(&mut self, stmt: &HStatement) {
+        match &stmt.kind {
+            HStatementKind::Block { statements, .. } => {
+                self.push_scope();
+                let mut end = None;
+                for s in statements {
+                    if let Some(end) = end.take() {
+                        self.emit(
+                            &UNREACHABLE_CODE,
+                            Diagnostic::warning("Unreachable statement")
+                                .with_label(s.span, "")
+                                .with_secondary(end, "any code following this is unreachable"),
+                        );
+                    }
+                    self.statement(s);
+                    if matches!(
+                        s.kind,
+                        HStatementKind::Return(_)
+                            | HStatementKind::Break
+                            | HStatementKind::Continue
+                    ) {
+                        end = Some(s.span);
+                    }
+                }
+                self.pop_scope();
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                self.optional_type(&variable.ty);
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.declare(&variable.name, variable.span, false);
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                // Assigning to a variable does not read it
+                if !matches!(lhs.kind, HExpressionKind::Variable(_)) {
+                    self.expression(lhs);
+                }
+                self.expression(rhs);
+            }
+            HStatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.statement(body);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            HStatementKind::While { condition, body } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.expression(expr);
+                self.optional_type(&ident.ty);
+                self.push_scope();
+                self.declare(&ident.name, ident.span, false);
+                self.statement(body);
+                self.pop_scope();
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject);
+                self.arms(subject, arms);
+                for arm in arms {
+                    match arm {
+                        HMatchArm::Case(case, body) => {
+                            self.expression(case);
+                            self.statement(body);
+                        }
+                        HMatchArm::Else(body) => self.statement(body),
+                    }
+                }
+            }
+            HStatementKind::Exp(e) => self.expression(e),
+            HStatementKind::Break | HStatementKind::Continue => {}
+        }
+    }
+
+    /// Reports arms of a `match` that are never chosen, because an earlier
+    /// arm already matches, and `bool` values that no arm covers
+    fn arms(&mut self, subject: &HExpression, arms: &[HMatchArm]) {
+        let mut seen: Vec<(String, Span)> = Vec::new();
+        let mut else_arm: Option<Span> = None;
+        for (index, arm) in arms.iter().enumerate() {
+            let diagnostic = Diagnostic::warning("Unreachable match arm");
+            let diagnostic = match (arm, else_arm) {
+                (HMatchArm::Case(case, _), Some(else_arm)) => diagnostic
+                    .with_label(case.span, "")
+                    .with_secondary(else_arm, "the `else` arm matches every value first"),
+                (HMatchArm::Case(case, _), None) => {
+                    let Some(value) = patterns::literal(case) else {
+                        continue;
+                    };
+                    match seen.iter().find(|(seen, _)| *seen == value) {
+                        Some((_, first)) => diagnostic
+                            .with_label(case.span, "")
+                            .with_secondary(*first, format!("`{}` is matched here first", value)),
+                        None => {
+                            seen.push((value, case.span));
+                            continue;
+                        }
+                    }
+                }
+                (HMatchArm::Else(body), _) => {
+                    else_arm = Some(body.span);
+                    if !patterns::is_exhaustive(&arms[..index]) {
+                        continue;
+                    }
+                    diagnostic.with_label(body.span, "every value is matched by an earlier arm")
+                }
+            };
+            self.emit(&UNREACHABLE_PATTERNS, diagnostic);
+        }
+        if let Some(missing) = patterns::missing_values(arms).filter(|m| !m.is_empty()) {
+            let missing = missing.join("`, `");
+            self.emit(
+                &NON_EXHAUSTIVE_MATCH,
+                Diagnostic::warning(format!("`match` does not cover `{}`", missing))
+                    .with_label(subject.span, format!("`{}` not covered", missing))
+                    .with_help(format!("add an arm for `{}` or an `else` arm", missing)),
+            );
+        }
+    }
+
+    fn expression(&mut self, expr: &HExpression) {
+        match &expr.kind {
+            HExpressionKind::Variable(name) => self.read(name),
+            HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            } => {
+                self.called.insert(fn_name.clone());
+                self.uses(fn_name);
+                for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                    self.expression(arg);
+                }
+            }
+            HExpressionKind::Array { elements, .. } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr);
+                self.expression(index);
+            }
+            HExpressionKind::BinOp { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            HExpressionKind::StructInitialization { name, fields } => {
+                self.uses(name);
+                for value in fields.values() {
+                    self.expression(value);
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr);
+                // Fields are not variables, only the arguments of a method
+                // call are read
+                if let HExpressionKind::FunctionCall { args, named, .. } = &field.kind {
+                    for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                        self.expression(arg);
+                    }
+                }
+            }
+            HExpressionKind::Cast { expr, to, .. } => {
+                self.expression(expr);
+                self.ty(to);
+            }
+            HExpressionKind::Int(_)
+            | HExpressionKind::Str(_)
+            | HExpressionKind::Bool(_)
+            | HExpressionKind::Selff => {}
+        }
+    }
+
+    /// Reports imports that nothing is used from. As all modules are merged,
+    /// a module provides what it imports itself, and an import is also used
+    /// by the files that import the importing file.
+    fn imports(&mut self, program: &HModule, imports: &[Import]) {
+        let mut defined: HashMap<FileId, HashSet<&str>> = HashMap::new();
+        let definitions = program
+            .func
+            .iter()
+            .map(|func| (func.span.file, func.name.as_str()))
+            .chain(
+                program
+                    .structs
+                    .iter()
+                    .map(|s| (s.span.file, s.name.as_str())),
+            )
+            .chain(program.types.iter().map(|t| (t.span.file, t.name.as_str())));
+        for (file, name) in definitions {
+            defined.entry(file).or_default().insert(name);
+        }
+
+        for import in imports {
+            let provided = reachable(import.module, imports, |i| (i.span.file, i.module));
+            let users = reachable(import.span.file, imports, |i| (i.module, i.span.file));
+            let is_used = users
+                .iter()
+                .filter_map(|user| self.used.get(user))
+                .any(|used| {
+                    provided
+                        .iter()
+                        .filter_map(|module| defined.get(module))
+                        .flatten()
+                        .any(|name| used.contains(*name))
+                });
+            if !is_used {
+                self.emit(
+                    &UNUSED_IMPORTS,
+                    Diagnostic::warning(format!("Unused import `{}`", import.path))
+                        .with_label(import.span, ""),
+                );
+            }
+        }
+    }
+}
+
+/// `file` and the files reachable from it over imports. `edge` returns the
+/// file an import leads from and the file it leads to.
+fn reachable(
+    file: FileId,
+    imports: &[Import],
+    edge: impl Fn(&Import) -> (FileId, FileId),
+) -> Vec<FileId> {
+    let mut files = vec![file];
+    let mut i = 0;
+    while let Some(current) = files.get(i).copied() {
+        for (from, to) in imports.iter().map(&edge) {
+            if from == current && !files.contains(&to) {
+                files.push(to);
+            }
+        }
+        i += 1;
+    }
+    files
+}
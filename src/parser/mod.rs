@@ -13,22 +13,103 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+mod check;
+mod duplicates;
+mod flow;
 mod infer;
+mod lints;
+mod names;
 // TODO: Resolve this lint by renaming the module
 #[allow(clippy::module_inception)]
 mod parser;
+mod patterns;
+mod resolve;
 mod rules;
+mod scopes;
 use crate::ast::hast::HModule;
+use crate::ast::span::FileId;
+use crate::diagnostic::{self, Diagnostic};
 use crate::lexer::Token;
+pub use lints::{Import, LINTS};
+use std::collections::HashSet;
 #[cfg(test)]
 mod tests;
 
-pub fn parse(tokens: Vec<Token>, raw: Option<String>) -> Result<HModule, String> {
-    let mut parser = parser::Parser::new(tokens, raw);
-    parser.parse()
+/// Parses source that is not read from a file, the spans of the module
+/// point into the default file
+#[cfg(test)]
+pub fn parse(tokens: Vec<Token>) -> Result<HModule, Vec<Diagnostic>> {
+    match parse_file(tokens, FileId::default(), DEFAULT_ERROR_LIMIT) {
+        (module, errors) if errors.is_empty() => Ok(module),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Number of syntax errors after which parsing stops
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+/// Parses a file, the spans of the module point into `file`. Parsing
+/// recovers from syntax errors, so all of them are reported together with
+/// the definitions that could be parsed. At most `error_limit` errors are
+/// collected.
+pub fn parse_file(
+    tokens: Vec<Token>,
+    file: FileId,
+    error_limit: usize,
+) -> (HModule, Vec<Diagnostic>) {
+    parser::Parser::new(tokens, file, error_limit).parse()
+}
+
+/// Report functions, structs, types, fields and methods that are defined
+/// more than once. Runs on the merged module, so definitions that clash
+/// across modules or with the standard library are found.
+pub fn check_duplicates(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    duplicates::check(program)
+}
+
+/// Report uses of variables, functions and types that are not defined.
+/// Runs on the merged module, so functions of other modules and the
+/// standard library are known. `stdlib` lists the functions of a standard
+/// library that is not merged, to explain why they cannot be used.
+pub fn resolve_names(program: &HModule, stdlib: &[String]) -> Result<(), Vec<Diagnostic>> {
+    names::resolve_names(program, stdlib)
+}
+
+/// Resolve type declarations and run type inference on a merged module
+/// so the full symbol table is available.
+pub fn infer_types(program: &mut HModule) -> Result<(), Vec<Diagnostic>> {
+    resolve::resolve(program)?;
+    infer::infer(program)
+}
+
+/// Verify that the inferred types fit together. Every mismatch in the
+/// module is reported.
+pub fn check_types(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    check::check(program)
+}
+
+/// Verify that functions with a return type return a value on every path,
+/// and that variables are assigned before they are read. Runs after type
+/// inference, so inferred return types are checked as well.
+pub fn check_flow(program: &HModule) -> Result<(), Vec<Diagnostic>> {
+    flow::check(program)
+}
+
+/// Give every variable declaration in a function a unique name, so that
+/// backends see the same bindings regardless of the scoping rules of their
+/// target. Runs after all checks.
+pub fn rename_shadowed(program: &mut HModule) {
+    scopes::rename_shadowed(program)
 }
 
-/// Run type inference on a merged module so the full symbol table is available.
-pub fn infer_types(program: &mut HModule) {
-    infer::infer(program);
+/// Report code in `files` that compiles but is likely a mistake, like unused
+/// variables. Runs on the checked, merged module. Denied lints are reported
+/// as errors.
+pub fn lint(
+    program: &HModule,
+    files: &HashSet<FileId>,
+    imports: &[Import],
+    options: &diagnostic::Options,
+) -> Vec<Diagnostic> {
+    lints::lint(program, files, imports, options)
 }
@@ -0,0 +1,365 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use super::resolve::is_builtin_type;
+use crate::ast::hast::{
+    HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement, HStatementKind,
+};
+use crate::ast::span::Span;
+use crate::ast::types::Type;
+use crate::diagnostic::{codes, Diagnostic};
+use std::collections::HashSet;
+
+struct NameResolver<'a> {
+    functions: &'a HashSet<&'a str>,
+    /// Functions of the standard library that is not part of the module
+    stdlib: &'a [String],
+    /// Structs, type aliases and newtypes
+    types: &'a HashSet<&'a str>,
+    /// Variables of the enclosing blocks, innermost last
+    scopes: Vec<Vec<String>>,
+    errors: Vec<Diagnostic>,
+}
+
+/// Checks that every variable, function, struct and type that is used is
+/// defined. Functions starting with `_`, such as `_printf`, are provided by
+/// the runtime of a backend and are not checked. Every unknown name is
+/// reported, together with a similar name if there is one.
+///
+/// `stdlib` names the functions of the standard library if it has not been
+/// merged into the module, so their use can be explained.
+pub fn resolve_names(program: &HModule, stdlib: &[String]) -> Result<(), Vec<Diagnostic>> {
+    let functions: HashSet<&str> = program.func.iter().map(|f| f.name.as_str()).collect();
+    let types: HashSet<&str> = program
+        .structs
+        .iter()
+        .map(|def| def.name.as_str())
+        .chain(program.types.iter().map(|def| def.name.as_str()))
+        .collect();
+    let globals: Vec<String> = program.globals.clone();
+
+    let mut errors = Vec::new();
+    let mut resolve = |func: &HFunction, self_allowed: bool| {
+        let mut resolver = NameResolver {
+            functions: &functions,
+            stdlib,
+            types: &types,
+            scopes: vec![globals.clone()],
+            errors: Vec::new(),
+        };
+        resolver.function(func, self_allowed);
+        errors.append(&mut resolver.errors);
+    };
+    for func in &program.func {
+        resolve(func, false);
+    }
+    for def in &program.structs {
+        for method in &def.methods {
+            resolve(method, true);
+        }
+    }
+    for def in &program.structs {
+        let mut resolver = NameResolver {
+            functions: &functions,
+            stdlib,
+            types: &types,
+            scopes: Vec::new(),
+            errors: Vec::new(),
+        };
+        for field in &def.fields {
+            resolver.optional_type(&field.ty, field.span);
+        }
+        errors.append(&mut resolver.errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Number of single character insertions, deletions and substitutions that
+/// turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The candidate that is closest to `name`, if any is close enough to be a
+/// likely typo
+pub(super) fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let closest = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min();
+    closest.map(|(_, candidate)| candidate)
+}
+
+/// Suggests `candidate` instead of a misspelled name. It replaces the name
+/// at `name_span`, if its exact location is known.
+pub(super) fn suggest(
+    diagnostic: Diagnostic,
+    name_span: Option<Span>,
+    candidate: Option<&str>,
+) -> Diagnostic {
+    let Some(candidate) = candidate else {
+        return diagnostic;
+    };
+    let diagnostic = diagnostic.with_help(format!("did you mean `{}`?", candidate));
+    match name_span {
+        Some(span) => diagnostic.with_suggestion(span, candidate),
+        None => diagnostic,
+    }
+}
+
+impl NameResolver<'_> {
+    /// `name_span` is the span of just the name, if it is known
+    fn unknown(
+        &mut self,
+        kind: &str,
+        name: &str,
+        span: Span,
+        name_span: Option<Span>,
+        candidate: Option<&str>,
+    ) {
+        let diagnostic = Diagnostic::error(format!("Unknown {} `{}`", kind, name))
+            .with_code(codes::UNKNOWN_NAME)
+            .with_label(span, "not found in this scope");
+        self.errors.push(suggest(diagnostic, name_span, candidate));
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name.to_string());
+        }
+    }
+
+    fn function(&mut self, func: &HFunction, self_allowed: bool) {
+        let mut params = Vec::new();
+        if self_allowed {
+            params.push("self".to_string());
+        }
+        for arg in &func.arguments {
+            self.optional_type(&arg.ty, arg.span);
+            if let Some(default) = &arg.default {
+                self.expression(default);
+            }
+            params.push(arg.name.clone());
+        }
+        self.optional_type(&func.ret_type, func.span);
+        self.scopes.push(params);
+        self.statement(&func.body);
+        self.scopes.pop();
+    }
+
+    /// Types have no span of their own, `span` is the span of the
+    /// declaration they are part of
+    fn optional_type(&mut self, ty: &Option<Type>, span: Span) {
+        if let Some(ty) = ty {
+            self.ty(ty, span);
+        }
+    }
+
+    fn ty(&mut self, ty: &Type, span: Span) {
+        match ty {
+            Type::Struct(name) if !self.types.contains(name.as_str()) => {
+                let candidate = did_you_mean(name, self.types.iter().copied());
+                self.unknown("type", name, span, None, candidate);
+            }
+            Type::Array(inner, _) | Type::Newtype(_, inner) => self.ty(inner, span),
+            _ => {}
+        }
+    }
+
+    fn statement(&mut self, stmt: &HStatement) {
+        match &stmt.kind {
+            HStatementKind::Block { statements, .. } => {
+                self.scopes.push(Vec::new());
+                for s in statements {
+                    self.statement(s);
+                }
+                self.scopes.pop();
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                self.optional_type(&variable.ty, variable.span);
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.declare(&variable.name);
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            HStatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.statement(body);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            HStatementKind::While { condition, body } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.expression(expr);
+                self.optional_type(&ident.ty, ident.span);
+                self.scopes.push(vec![ident.name.clone()]);
+                self.statement(body);
+                self.scopes.pop();
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject);
+                for arm in arms {
+                    match arm {
+                        HMatchArm::Case(case, body) => {
+                            self.expression(case);
+                            self.statement(body);
+                        }
+                        HMatchArm::Else(body) => self.statement(body),
+                    }
+                }
+            }
+            HStatementKind::Exp(e) => self.expression(e),
+            HStatementKind::Break | HStatementKind::Continue => {}
+        }
+    }
+
+    fn arguments<'e>(
+        &mut self,
+        args: impl IntoIterator<Item = &'e HExpression>,
+        named: &'e [(String, HExpression)],
+    ) {
+        for arg in args.into_iter().chain(named.iter().map(|(_, arg)| arg)) {
+            self.expression(arg);
+        }
+    }
+
+    fn expression(&mut self, expr: &HExpression) {
+        match &expr.kind {
+            HExpressionKind::Variable(name) => {
+                if !self.scopes.iter().flatten().any(|var| var == name) {
+                    let candidate =
+                        did_you_mean(name, self.scopes.iter().flatten().map(String::as_str))
+                            .map(str::to_string);
+                    self.unknown(
+                        "variable",
+                        name,
+                        expr.span,
+                        Some(expr.span),
+                        candidate.as_deref(),
+                    );
+                }
+            }
+            HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            } => {
+                // Type names convert their argument, e.g. `int(meters)`
+                let known = fn_name.starts_with('_')
+                    || self.functions.contains(fn_name.as_str())
+                    || self.types.contains(fn_name.as_str())
+                    || is_builtin_type(fn_name);
+                if !known && self.stdlib.contains(fn_name) {
+                    self.errors.push(
+                        Diagnostic::error(format!("Unknown function `{}`", fn_name))
+                            .with_code(codes::UNKNOWN_NAME)
+                            .with_label(expr.span, "not available for this target")
+                            .with_note(format!(
+                                "`{}` is part of the standard library, which is not available for this target",
+                                fn_name
+                            )),
+                    );
+                } else if !known {
+                    let candidate = did_you_mean(fn_name, self.functions.iter().copied());
+                    let name_span = expr.span.prefix(fn_name.len());
+                    self.unknown("function", fn_name, expr.span, Some(name_span), candidate);
+                }
+                self.arguments(args, named);
+            }
+            HExpressionKind::StructInitialization { name, fields } => {
+                if !self.types.contains(name.as_str()) {
+                    let candidate = did_you_mean(name, self.types.iter().copied());
+                    self.unknown("struct", name, expr.span, None, candidate);
+                }
+                for value in fields.values() {
+                    self.expression(value);
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr);
+                // Fields and methods depend on the type of `expr`, they are
+                // checked once types are known
+                if let HExpressionKind::FunctionCall { args, named, .. } = &field.kind {
+                    self.arguments(args, named);
+                }
+            }
+            HExpressionKind::Array { elements, .. } => self.arguments(elements, &[]),
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr);
+                self.expression(index);
+            }
+            HExpressionKind::BinOp { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            HExpressionKind::Cast {
+                expr: value, to, ..
+            } => {
+                self.expression(value);
+                self.ty(to, expr.span);
+            }
+            HExpressionKind::Int(_) | HExpressionKind::Str(_) | HExpressionKind::Bool(_) => {}
+            HExpressionKind::Selff => {
+                if !self.scopes.iter().flatten().any(|var| var == "self") {
+                    self.errors.push(
+                        Diagnostic::error("`self` can only be used in methods")
+                            .with_code(codes::UNKNOWN_NAME)
+                            .with_label(expr.span, "not inside a method"),
+                    );
+                }
+            }
+        }
+    }
+}
@@ -14,24 +14,74 @@
  * limitations under the License.
  */
 use crate::ast::hast::*;
+use crate::ast::span::{FileId, Span};
+use crate::diagnostic::{codes, Diagnostic};
 use crate::lexer::Keyword;
-use crate::lexer::Position;
 use crate::lexer::{Token, TokenKind};
-use crate::util::string_util::highlight_position_in_file;
 use std::iter::Peekable;
 use std::vec::IntoIter;
 
+const TOO_MANY_ERRORS: &str = "Too many syntax errors";
+
+/// Tokens that start a top-level definition
+fn is_definition(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Keyword(
+            Keyword::Function
+                | Keyword::Struct
+                | Keyword::Import
+                | Keyword::Type
+                | Keyword::Newtype
+        )
+    )
+}
+
+/// How a token changes the number of open braces
+fn brace_depth(kind: &TokenKind) -> isize {
+    match kind {
+        TokenKind::CurlyBracesOpen => 1,
+        TokenKind::CurlyBracesClose => -1,
+        _ => 0,
+    }
+}
+
+fn starts_statement(keyword: &Keyword) -> bool {
+    matches!(
+        keyword,
+        Keyword::Let
+            | Keyword::Var
+            | Keyword::Return
+            | Keyword::If
+            | Keyword::While
+            | Keyword::For
+            | Keyword::Match
+            | Keyword::Break
+            | Keyword::Continue
+    )
+}
+
 pub struct Parser {
     tokens: Peekable<IntoIter<Token>>,
     peeked: Vec<Token>,
     current: Option<Token>,
     prev: Option<Token>,
-    raw: Option<String>,
+    file: FileId,
+    /// End of the last consumed token
+    end: usize,
+    /// Ends of the tokens consumed before it, restored when a token is pushed back
+    previous_ends: Vec<usize>,
+    /// Number of `{` minus the number of `}` that have been consumed
+    braces: isize,
+    /// Syntax errors that have been recovered from
+    errors: Vec<Diagnostic>,
+    /// Parsing stops once this many errors are found
+    error_limit: usize,
 }
 
 impl Parser {
     #[allow(clippy::needless_collect)] // TODO
-    pub fn new(tokens: Vec<Token>, raw: Option<String>) -> Parser {
+    pub fn new(tokens: Vec<Token>, file: FileId, error_limit: usize) -> Parser {
         let tokens_without_whitespace: Vec<Token> = tokens
             .into_iter()
             .filter(|token| token.kind != TokenKind::Whitespace && token.kind != TokenKind::Comment)
@@ -41,16 +91,23 @@ impl Parser {
             peeked: vec![],
             current: None,
             prev: None,
-            raw,
+            file,
+            end: 0,
+            previous_ends: Vec::new(),
+            braces: 0,
+            errors: Vec::new(),
+            error_limit: error_limit.max(1),
         }
     }
 
-    pub fn parse(&mut self) -> Result<HModule, String> {
-        let program = self.parse_module()?;
-        Ok(program)
+    /// Parses the module as far as possible. Returns it together with every
+    /// syntax error that was found, the parts with errors are left out.
+    pub fn parse(mut self) -> (HModule, Vec<Diagnostic>) {
+        let program = self.parse_module();
+        (program, self.errors)
     }
 
-    pub(super) fn next(&mut self) -> Result<Token, String> {
+    pub(super) fn next(&mut self) -> Result<Token, Diagnostic> {
         self.prev = self.current.to_owned();
         let item = if self.peeked.is_empty() {
             self.tokens.next()
@@ -58,94 +115,209 @@ impl Parser {
             self.peeked.pop()
         };
 
+        if let Some(token) = &item {
+            self.previous_ends.push(self.end);
+            self.end = token.start + token.len;
+            self.braces += brace_depth(&token.kind);
+        }
         self.current = item.to_owned();
-        item.ok_or_else(|| "Expected token".into())
+        item.ok_or_else(|| {
+            Diagnostic::error("Unexpected end of file")
+                .with_code(codes::SYNTAX)
+                .with_label(
+                    Span::new(self.file, self.end, self.end),
+                    "expected more code",
+                )
+        })
     }
 
-    pub(super) fn peek(&mut self) -> Result<Token, String> {
+    pub(super) fn peek(&mut self) -> Result<Token, Diagnostic> {
         let token = self.next()?;
         self.push(token.to_owned());
         Ok(token)
     }
 
     pub(super) fn push(&mut self, token: Token) {
+        self.end = self.previous_ends.pop().unwrap_or_default();
+        self.braces -= brace_depth(&token.kind);
         self.peeked.push(token);
     }
 
+    /// Offset of the next token, where a node that is parsed next starts
+    pub(super) fn start(&mut self) -> usize {
+        match self.peek() {
+            Ok(token) => token.start,
+            Err(_) => self.end,
+        }
+    }
+
+    /// Span from `start` to the end of the last consumed token
+    pub(super) fn span_from(&self, start: usize) -> Span {
+        Span::new(self.file, start, self.end)
+    }
+
+    /// Number of tokens that have been consumed
+    pub(super) fn consumed(&self) -> usize {
+        self.previous_ends.len()
+    }
+
+    /// Number of braces that have been opened and not closed yet
+    pub(super) fn braces(&self) -> isize {
+        self.braces
+    }
+
+    /// Records a syntax error to recover from. Returns false once the error
+    /// limit is reached and parsing should stop.
+    pub(super) fn record(&mut self, error: Diagnostic) -> bool {
+        if self.errors.len() < self.error_limit {
+            self.errors.push(error);
+        }
+        if self.errors.len() < self.error_limit {
+            return true;
+        }
+        if !self.errors.iter().any(|e| e.message == TOO_MANY_ERRORS) {
+            self.errors.push(self.too_many_errors());
+        }
+        false
+    }
+
+    /// Unwinds the parser once the error limit is reached
+    pub(super) fn too_many_errors(&self) -> Diagnostic {
+        Diagnostic::error(TOO_MANY_ERRORS).with_note(format!(
+            "parsing stopped after {} errors, use `--error-limit` to see more",
+            self.error_limit
+        ))
+    }
+
+    /// Skips the rest of a definition that could not be parsed, up to the
+    /// next `fn`, `struct`, `import`, `type` or `newtype`. At least one token
+    /// is skipped if the definition did not consume any, so parsing always
+    /// makes progress.
+    pub(super) fn synchronize_top_level(&mut self, consumed: usize) {
+        self.unconsume(consumed, is_definition);
+        if self.consumed() == consumed {
+            let _ = self.next();
+        }
+        while let Ok(token) = self.peek() {
+            if is_definition(&token.kind) {
+                return;
+            }
+            let _ = self.next();
+        }
+    }
+
+    /// Skips the rest of a statement that could not be parsed, up to the
+    /// next statement keyword, an identifier on a new line or the `}` that
+    /// closes the block. The statement started after `consumed` tokens, when
+    /// `braces` braces were open. Nested braces are skipped as a whole,
+    /// including those the statement opened before the error, e.g. of a
+    /// struct literal. Returns false if a definition is found instead, which
+    /// ends the block.
+    pub(super) fn synchronize_statement(&mut self, consumed: usize, braces: isize) -> bool {
+        self.unconsume(consumed, |kind| match kind {
+            TokenKind::Keyword(keyword) => starts_statement(keyword) || is_definition(kind),
+            TokenKind::CurlyBracesClose => true,
+            _ => false,
+        });
+        if self.consumed() == consumed {
+            let _ = self.next();
+        }
+        let mut depth = (self.braces - braces).max(0);
+        let line = self.peek().map_or(0, |token| token.pos.line);
+        while let Ok(token) = self.peek() {
+            match &token.kind {
+                kind if is_definition(kind) => return false,
+                TokenKind::CurlyBracesOpen => depth += 1,
+                TokenKind::CurlyBracesClose if depth == 0 => return true,
+                TokenKind::CurlyBracesClose => depth -= 1,
+                TokenKind::Keyword(keyword) if depth == 0 && starts_statement(keyword) => {
+                    return true
+                }
+                TokenKind::Identifier(_) | TokenKind::Keyword(Keyword::Selff)
+                    if depth == 0 && token.pos.line > line =>
+                {
+                    return true
+                }
+                _ => {}
+            }
+            let _ = self.next();
+        }
+        true
+    }
+
+    /// Puts the last consumed token back if parsing can resume at it. An
+    /// error is often found only after consuming the token that caused it,
+    /// e.g. a `let` where an expression is expected. The token is kept if it
+    /// is the only one consumed since `consumed`, so parsing makes progress.
+    fn unconsume(&mut self, consumed: usize, resumes_at: impl Fn(&TokenKind) -> bool) {
+        if !self.peeked.is_empty() || self.consumed() <= consumed + 1 {
+            return;
+        }
+        if let Some(token) = self.current.clone() {
+            if resumes_at(&token.kind) {
+                self.push(token);
+            }
+        }
+    }
+
     pub(super) fn has_more(&mut self) -> bool {
         !self.peeked.is_empty() || self.tokens.peek().is_some()
     }
 
-    pub(super) fn match_token(&mut self, token_kind: TokenKind) -> Result<Token, String> {
+    /// Span of a token that has been read
+    pub(super) fn token_span(&self, token: &Token) -> Span {
+        Span::new(self.file, token.start, token.start + token.len)
+    }
+
+    pub(super) fn match_token(&mut self, token_kind: TokenKind) -> Result<Token, Diagnostic> {
         match self.next()? {
             token if token.kind == token_kind => Ok(token),
             other => Err(self.make_error(token_kind, other)),
         }
     }
 
-    pub(super) fn peek_token(&mut self, token_kind: TokenKind) -> Result<Token, String> {
+    pub(super) fn peek_token(&mut self, token_kind: TokenKind) -> Result<Token, Diagnostic> {
         match self.peek()? {
             token if token.kind == token_kind => Ok(token),
             other => Err(self.make_error(token_kind, other)),
         }
     }
 
-    pub(super) fn match_keyword(&mut self, keyword: Keyword) -> Result<(), String> {
+    pub(super) fn match_keyword(&mut self, keyword: Keyword) -> Result<(), Diagnostic> {
         let token = self.next()?;
         match &token.kind {
             TokenKind::Keyword(ref k) if k == &keyword => Ok(()),
-            _ => {
-                let mut error = self
-                    .make_error_msg(token.pos, format!("Expected keyword, found {}", token.raw));
-                let hint = self.make_hint_msg(format!(
-                    "replace the symbol `{}` with the appropriate keyword. ",
+            _ => Err(self
+                .error_at(&token, format!("Expected keyword, found `{}`", token.raw))
+                .with_help(format!(
+                    "replace `{}` with the appropriate keyword",
                     token.raw
-                ));
-                error.push_str(&hint);
-                Err(error)
-            }
+                ))),
         }
     }
 
-    pub(super) fn match_identifier(&mut self) -> Result<String, String> {
+    pub(super) fn match_identifier(&mut self) -> Result<String, Diagnostic> {
         let token = self.next()?;
         match &token.kind {
             TokenKind::Identifier(n) => Ok(n.to_string()),
-            other => {
-                let mut error = self
-                    .make_error_msg(token.pos, format!("Expected Identifier, found `{other}`",));
-                let hint = self.make_hint_msg(format!(
-                    "replace the symbol `{other}` with an identifier. Example `Foo`"
-                ));
-                error.push_str(&hint);
-                Err(error)
-            }
+            other => Err(self
+                .error_at(&token, format!("Expected identifier, found `{other}`"))
+                .with_help(format!("replace `{other}` with an identifier, e.g. `Foo`"))),
         }
     }
 
-    pub(super) fn make_error(&mut self, token_kind: TokenKind, other: Token) -> String {
+    pub(super) fn make_error(&mut self, token_kind: TokenKind, other: Token) -> Diagnostic {
         let other_kind = &other.kind;
-        self.make_error_msg(
-            other.pos,
-            format!("Token `{token_kind}` not found, found `{other_kind}`"),
+        self.error_at(
+            &other,
+            format!("Expected `{token_kind}`, found `{other_kind}`"),
         )
     }
 
-    pub(super) fn make_error_msg(&mut self, pos: Position, msg: String) -> String {
-        match &self.raw {
-            Some(raw_file) => format!(
-                "{}:{}: {}\n{}",
-                pos.line,
-                pos.offset,
-                msg,
-                highlight_position_in_file(raw_file.to_string(), pos)
-            ),
-            None => format!("{}:{}: {}", pos.line, pos.offset, msg),
-        }
-    }
-
-    pub(super) fn make_hint_msg(&mut self, msg: String) -> String {
-        let new_lines = "\n".repeat(3);
-        format!("{new_lines}Hint: {}\n", msg)
+    /// A syntax error that points at `token`
+    pub(super) fn error_at(&self, token: &Token, msg: String) -> Diagnostic {
+        Diagnostic::error(msg)
+            .with_code(codes::SYNTAX)
+            .with_label(self.token_span(token), "")
     }
 }
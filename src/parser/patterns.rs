@@ -0,0 +1,64 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::ast::hast::{HExpression, HExpressionKind, HMatchArm};
+
+/// The value that a `match` arm compares against as it is written in the
+/// source, if it is a literal. Integer literals that were given a sized
+/// type are still literals.
+pub(super) fn literal(case: &HExpression) -> Option<String> {
+    match &case.kind {
+        HExpressionKind::Int(value) => Some(value.to_string()),
+        HExpressionKind::Str(value) => Some(format!("\"{}\"", value)),
+        HExpressionKind::Bool(value) => Some(value.to_string()),
+        HExpressionKind::Cast { expr, .. } => match expr.kind {
+            HExpressionKind::Int(value) => Some(value.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The values of its subject that no arm of a `match` covers, or `None` if
+/// they cannot be listed. Only the values of a `bool` can be listed, it is
+/// recognized by `true` and `false` arms, because type checking makes sure
+/// that the arms have the type of the subject. An `else` arm covers every
+/// value.
+pub(super) fn missing_values(arms: &[HMatchArm]) -> Option<Vec<&'static str>> {
+    let mut cases = Vec::new();
+    for arm in arms {
+        match arm {
+            HMatchArm::Case(case, _) => cases.push(&case.kind),
+            HMatchArm::Else(_) => return Some(Vec::new()),
+        }
+    }
+    if !cases
+        .iter()
+        .any(|case| matches!(case, HExpressionKind::Bool(_)))
+    {
+        return None;
+    }
+    let missing = [(true, "true"), (false, "false")]
+        .iter()
+        .filter(|(value, _)| !cases.contains(&&HExpressionKind::Bool(*value)))
+        .map(|(_, name)| *name)
+        .collect();
+    Some(missing)
+}
+
+/// Whether one of the arms of a `match` is always chosen
+pub(super) fn is_exhaustive(arms: &[HMatchArm]) -> bool {
+    missing_values(arms).is_some_and(|missing| missing.is_empty())
+}
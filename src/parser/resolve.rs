@@ -0,0 +1,262 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::ast::hast::{
+    HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement, HStatementKind,
+    HTypeDef,
+};
+use crate::ast::types::Type;
+use crate::diagnostic::{codes, Diagnostic};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+struct Resolver<'a> {
+    defs: HashMap<&'a str, &'a HTypeDef>,
+    functions: HashSet<String>,
+}
+
+/// Replaces every use of a `type` alias with its target and every use of a
+/// `newtype` with `Type::Newtype`. Calls to a type name, such as `Meters(5)`,
+/// are turned into explicit conversions.
+pub fn resolve(program: &mut HModule) -> Result<(), Diagnostic> {
+    let types = program.types.clone();
+    let mut defs = HashMap::new();
+    for def in &types {
+        if let Ok(Type::Struct(_)) = Type::try_from(def.name.clone()) {
+            defs.insert(def.name.as_str(), def);
+        } else {
+            return Err(
+                Diagnostic::error(format!("Cannot redefine builtin type `{}`", def.name))
+                    .with_code(codes::DEFINITION)
+                    .with_label(def.span, "redefined here"),
+            );
+        }
+    }
+    let functions = program.func.iter().map(|f| f.name.clone()).collect();
+    let resolver = Resolver { defs, functions };
+
+    for func in &mut program.func {
+        resolver.function(func)?;
+    }
+    for def in &mut program.structs {
+        for field in &mut def.fields {
+            resolver.optional_type(&mut field.ty)?;
+        }
+        for method in &mut def.methods {
+            resolver.function(method)?;
+        }
+    }
+    for def in &mut program.types {
+        def.ty = resolver.resolve_type(&def.ty, &mut Vec::new())?;
+    }
+    Ok(())
+}
+
+impl Resolver<'_> {
+    fn resolve_type<'b>(&'b self, ty: &Type, seen: &mut Vec<&'b str>) -> Result<Type, Diagnostic> {
+        match ty {
+            Type::Struct(name) => match self.defs.get(name.as_str()) {
+                Some(def) => {
+                    if seen.contains(&def.name.as_str()) {
+                        return Err(Diagnostic::error(format!(
+                            "Type `{}` is defined in terms of itself",
+                            name
+                        ))
+                        .with_code(codes::DEFINITION)
+                        .with_label(def.span, "recursive type"));
+                    }
+                    seen.push(&def.name);
+                    let target = self.resolve_type(&def.ty, seen)?;
+                    seen.pop();
+                    if def.newtype {
+                        Ok(Type::Newtype(def.name.clone(), Box::new(target)))
+                    } else {
+                        Ok(target)
+                    }
+                }
+                None => Ok(ty.clone()),
+            },
+            Type::Array(inner, capacity) => Ok(Type::Array(
+                Box::new(self.resolve_type(inner, seen)?),
+                *capacity,
+            )),
+            _ => Ok(ty.clone()),
+        }
+    }
+
+    fn optional_type(&self, ty: &mut Option<Type>) -> Result<(), Diagnostic> {
+        if let Some(t) = ty {
+            *t = self.resolve_type(t, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn function(&self, func: &mut HFunction) -> Result<(), Diagnostic> {
+        for arg in &mut func.arguments {
+            self.optional_type(&mut arg.ty)?;
+            if let Some(default) = &mut arg.default {
+                self.expression(default)?;
+            }
+        }
+        self.optional_type(&mut func.ret_type)?;
+        self.statement(&mut func.body)
+    }
+
+    fn statement(&self, stmt: &mut HStatement) -> Result<(), Diagnostic> {
+        match &mut stmt.kind {
+            HStatementKind::Block { statements, scope } => {
+                for var in scope {
+                    self.optional_type(&mut var.ty)?;
+                }
+                for s in statements {
+                    self.statement(s)?;
+                }
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                self.optional_type(&mut variable.ty)?;
+                if let Some(e) = value {
+                    self.expression(e)?;
+                }
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                self.expression(lhs)?;
+                self.expression(rhs)?;
+            }
+            HStatementKind::Return(value) => {
+                if let Some(e) = value {
+                    self.expression(e)?;
+                }
+            }
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.expression(condition)?;
+                self.statement(body)?;
+                if let Some(e) = else_branch {
+                    self.statement(e)?;
+                }
+            }
+            HStatementKind::While { condition, body } => {
+                self.expression(condition)?;
+                self.statement(body)?;
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.optional_type(&mut ident.ty)?;
+                self.expression(expr)?;
+                self.statement(body)?;
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject)?;
+                for arm in arms {
+                    match arm {
+                        HMatchArm::Case(e, s) => {
+                            self.expression(e)?;
+                            self.statement(s)?;
+                        }
+                        HMatchArm::Else(s) => self.statement(s)?,
+                    }
+                }
+            }
+            HStatementKind::Exp(e) => self.expression(e)?,
+            HStatementKind::Break | HStatementKind::Continue => {}
+        }
+        Ok(())
+    }
+
+    fn expression(&self, expr: &mut HExpression) -> Result<(), Diagnostic> {
+        match &mut expr.kind {
+            HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            } => {
+                for arg in args.iter_mut().chain(named.iter_mut().map(|(_, arg)| arg)) {
+                    self.expression(arg)?;
+                }
+                if self.is_conversion(fn_name) {
+                    if args.len() != 1 || !named.is_empty() {
+                        return Err(Diagnostic::error(format!(
+                            "Conversion to `{}` takes exactly one argument, got {}",
+                            fn_name,
+                            args.len() + named.len()
+                        ))
+                        .with_code(codes::ARGUMENTS)
+                        .with_label(expr.span, ""));
+                    }
+                    let ty =
+                        self.resolve_type(&Type::try_from(fn_name.clone())?, &mut Vec::new())?;
+                    expr.kind = HExpressionKind::Cast {
+                        expr: Box::new(args.remove(0)),
+                        from: None,
+                        to: ty,
+                    };
+                }
+            }
+            HExpressionKind::Array { elements, .. } => {
+                for el in elements {
+                    self.expression(el)?;
+                }
+            }
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr)?;
+                self.expression(index)?;
+            }
+            HExpressionKind::BinOp { lhs, rhs, .. } => {
+                self.expression(lhs)?;
+                self.expression(rhs)?;
+            }
+            HExpressionKind::StructInitialization { fields, .. } => {
+                for value in fields.values_mut() {
+                    self.expression(value)?;
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr)?;
+                // A method call is not a conversion, only visit its arguments
+                if let HExpressionKind::FunctionCall { args, named, .. } = &mut field.kind {
+                    for arg in args.iter_mut().chain(named.iter_mut().map(|(_, arg)| arg)) {
+                        self.expression(arg)?;
+                    }
+                }
+            }
+            HExpressionKind::Cast { expr, to, .. } => {
+                self.expression(expr)?;
+                *to = self.resolve_type(to, &mut Vec::new())?;
+            }
+            HExpressionKind::Int(_)
+            | HExpressionKind::Str(_)
+            | HExpressionKind::Bool(_)
+            | HExpressionKind::Selff
+            | HExpressionKind::Variable(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Every type name, except for struct names, can be called like a
+    /// function to convert a value, e.g. `int(meters)`
+    fn is_conversion(&self, name: &str) -> bool {
+        !self.functions.contains(name) && (self.defs.contains_key(name) || is_builtin_type(name))
+    }
+}
+
+/// Whether `name` is a builtin type, like `int` or `string`, which can be
+/// called like a function to convert a value
+pub(super) fn is_builtin_type(name: &str) -> bool {
+    !matches!(Type::try_from(name.to_string()), Ok(Type::Struct(_)))
+}
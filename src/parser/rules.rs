@@ -16,64 +16,119 @@
 use super::parser::Parser;
 use crate::ast::hast::*;
 use crate::ast::types::Type;
+use crate::diagnostic::{codes, Diagnostic};
 use crate::lexer::Keyword;
 use crate::lexer::{TokenKind, Value};
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::convert::TryFrom;
 
 impl Parser {
-    pub fn parse_module(&mut self) -> Result<HModule, String> {
+    /// Parses the whole module. A definition with a syntax error is left
+    /// out and parsing goes on with the next one, see `Parser::record`.
+    pub fn parse_module(&mut self) -> HModule {
         let mut functions = Vec::new();
         let mut structs = Vec::new();
-        let mut imports = HashSet::new();
+        let mut types = Vec::new();
+        let mut imports = HashMap::new();
         let globals = Vec::new();
 
         while self.has_more() {
-            let next = self.peek()?;
-            match next.kind {
-                TokenKind::Keyword(Keyword::Function) => functions.push(self.parse_function()?),
-                TokenKind::Keyword(Keyword::Import) => {
-                    imports.insert(self.parse_import()?);
-                }
-                TokenKind::Keyword(Keyword::Struct) => {
-                    structs.push(self.parse_struct_definition()?)
+            let consumed = self.consumed();
+            let result = match self.peek() {
+                Ok(next) => match next.kind {
+                    TokenKind::Keyword(Keyword::Function) => {
+                        self.parse_function(false).map(|f| functions.push(f))
+                    }
+                    TokenKind::Keyword(Keyword::Import) => {
+                        let start = self.start();
+                        self.parse_import().map(|i| {
+                            imports.entry(i).or_insert(self.span_from(start));
+                        })
+                    }
+                    TokenKind::Keyword(Keyword::Struct) => {
+                        self.parse_struct_definition().map(|s| structs.push(s))
+                    }
+                    TokenKind::Keyword(Keyword::Type) | TokenKind::Keyword(Keyword::Newtype) => {
+                        self.parse_type_definition().map(|t| types.push(t))
+                    }
+                    _ => Err(self
+                        .error_at(&next, format!("Unexpected `{}`", next.raw))
+                        .with_note(
+                        "only functions, structs, types and imports may appear at the top level",
+                    )),
+                },
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                if !self.record(error) {
+                    break;
                 }
-                _ => return Err(format!("Unexpected token: {}", next.raw)),
+                self.synchronize_top_level(consumed);
             }
         }
 
         // TODO: Populate imports
 
-        Ok(HModule {
+        HModule {
             func: functions,
             structs,
+            types,
             globals,
             imports,
+        }
+    }
+
+    /// Parses `type Name = <type>` and `newtype Name = <type>`
+    fn parse_type_definition(&mut self) -> Result<HTypeDef, Diagnostic> {
+        let start = self.start();
+        let newtype = matches!(self.next()?.kind, TokenKind::Keyword(Keyword::Newtype));
+        let name = self.match_identifier()?;
+        self.match_token(TokenKind::Assign)?;
+        let ty = self.parse_type_name()?;
+        Ok(HTypeDef {
+            name,
+            ty,
+            newtype,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_struct_definition(&mut self) -> Result<HStructDef, String> {
+    fn parse_struct_definition(&mut self) -> Result<HStructDef, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Struct)?;
         let name = self.match_identifier()?;
 
         self.match_token(TokenKind::CurlyBracesOpen)?;
         let mut fields = Vec::new();
         let mut methods = Vec::new();
+        let mut embeds = Vec::new();
         while self.peek_token(TokenKind::CurlyBracesClose).is_err() {
             let next = self.peek()?;
             match next.kind {
                 TokenKind::Keyword(Keyword::Function) => {
-                    methods.push(self.parse_function()?);
+                    methods.push(self.parse_function(true)?);
+                }
+                TokenKind::Identifier(name) => {
+                    let field_start = self.start();
+                    self.next()?;
+                    if self.peek_token(TokenKind::Colon).is_ok() {
+                        let ty = Some(self.parse_type()?);
+                        let span = self.span_from(field_start);
+                        fields.push(HVariable { name, ty, span });
+                    } else {
+                        // A struct name without a type embeds that struct
+                        fields.push(HVariable {
+                            name: name.clone(),
+                            ty: Some(Type::Struct(name.clone())),
+                            span: self.span_from(field_start),
+                        });
+                        embeds.push(name);
+                    }
                 }
-                TokenKind::Identifier(_) => fields.push(self.parse_typed_variable()?),
                 _ => {
-                    let mut error =
-                        self.make_error_msg(next.pos, "Expected struct field or method".into());
-                    let hint =
-                        self.make_hint_msg(format!("remove the following symbol `{}`", next.raw));
-                    error.push_str(&hint);
-                    return Err(error);
+                    return Err(self
+                        .error_at(&next, "Expected struct field or method".into())
+                        .with_help(format!("remove `{}`", next.raw)));
                 }
             }
         }
@@ -82,52 +137,88 @@ impl Parser {
             name,
             fields,
             methods,
+            embeds,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_typed_variable_list(&mut self) -> Result<Vec<HVariable>, String> {
+    fn parse_argument_list(&mut self) -> Result<Vec<HArgument>, Diagnostic> {
         let mut args = Vec::new();
 
         // If there is an argument
         if let TokenKind::Identifier(_) = self.peek()?.kind {
             // Parse first argument
-            args.push(self.parse_typed_variable()?);
+            args.push(self.parse_argument()?);
             // Then continue to parse arguments
             // as long as a comma token is found
             while self.peek_token(TokenKind::Comma).is_ok() {
                 self.match_token(TokenKind::Comma)?;
-                args.push(self.parse_typed_variable()?);
+                args.push(self.parse_argument()?);
             }
         }
 
         Ok(args)
     }
 
-    fn parse_typed_variable(&mut self) -> Result<HVariable, String> {
+    fn parse_argument(&mut self) -> Result<HArgument, Diagnostic> {
+        let start = self.start();
         let next = self.next()?;
         if let TokenKind::Identifier(name) = next.kind {
-            return Ok(HVariable {
+            self.match_token(TokenKind::Colon)?;
+            let variadic = self.peek_token(TokenKind::Ellipsis).is_ok();
+            if variadic {
+                self.next()?;
+            }
+            let mut ty = self.parse_type_name()?;
+            if variadic {
+                ty = Type::Array(Box::new(ty), None);
+            }
+            let default = match self.peek_token(TokenKind::Assign) {
+                Ok(_) => {
+                    self.next()?;
+                    Some(self.parse_expression()?)
+                }
+                Err(_) => None,
+            };
+            return Ok(HArgument {
                 name,
-                ty: Some(self.parse_type()?),
+                ty: Some(ty),
+                default,
+                variadic,
+                span: self.span_from(start),
             });
         }
 
-        Err(format!("Argument could not be parsed: {}", next.raw))
+        Err(self.error_at(&next, format!("Expected a parameter, found `{}`", next.raw)))
     }
 
-    fn parse_block(&mut self) -> Result<HStatement, String> {
+    fn parse_block(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_token(TokenKind::CurlyBracesOpen)?;
 
         let mut statements = vec![];
         let mut scope = vec![];
 
         // Parse statements until a curly brace is encountered
-        while self.peek_token(TokenKind::CurlyBracesClose).is_err() {
-            let statement = self.parse_statement()?;
+        while self.has_more() && self.peek_token(TokenKind::CurlyBracesClose).is_err() {
+            let (consumed, braces) = (self.consumed(), self.braces());
+            let statement = match self.parse_statement() {
+                Ok(statement) => statement,
+                Err(error) => {
+                    if !self.record(error) {
+                        return Err(self.too_many_errors());
+                    }
+                    // A definition that follows means the `}` of the block is missing
+                    if !self.synchronize_statement(consumed, braces) {
+                        break;
+                    }
+                    continue;
+                }
+            };
 
             // If the current statement is a variable declaration,
             // let the scope know
-            if let HStatement::Declare { variable, value: _ } = &statement {
+            if let HStatementKind::Declare { variable, .. } = &statement.kind {
                 // TODO: Not sure if we should clone here
                 scope.push(variable.to_owned());
             }
@@ -137,21 +228,27 @@ impl Parser {
 
         self.match_token(TokenKind::CurlyBracesClose)?;
 
-        Ok(HStatement::Block { statements, scope })
+        Ok(HStatement::new(
+            HStatementKind::Block { statements, scope },
+            self.span_from(start),
+        ))
     }
 
     /// To reduce code duplication, this method can be either be used to parse a function or a method.
     /// If a function is parsed, the `fn` keyword is matched.
     /// If a method is parsed, `fn` will be omitted
-    fn parse_function(&mut self) -> Result<HFunction, String> {
+    fn parse_function(&mut self, method: bool) -> Result<HFunction, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Function)?;
         let name = self.match_identifier()?;
 
         self.match_token(TokenKind::BraceOpen)?;
 
-        let arguments: Vec<HVariable> = match self.peek()? {
+        let mutable_self = self.parse_receiver(method)?;
+
+        let arguments: Vec<HArgument> = match self.peek()? {
             t if t.kind == TokenKind::BraceClose => Vec::new(),
-            _ => self.parse_typed_variable_list()?,
+            _ => self.parse_argument_list()?,
         };
 
         self.match_token(TokenKind::BraceClose)?;
@@ -167,17 +264,15 @@ impl Parser {
             TokenKind::Assign => self.parse_inline_function()?,
             _ => {
                 let token = self.peek()?;
-                let token_kind = token.kind;
-                let mut error = self.make_error_msg(
-                    token.pos,
-                    format!("Expected `{{` or `=`, got `{token_kind}`",),
-                );
-
-                let hint = self.make_hint_msg(format!(
-                    "Try the following:\nfn {name}(...) = expression\nOr\nfn {name}(...) {{ ... }}"
-                ));
-                error.push_str(&hint);
-                return Err(error);
+                let token_kind = &token.kind;
+                return Err(self
+                    .error_at(
+                        &token,
+                        format!("Expected `{{` or `=`, found `{token_kind}`"),
+                    )
+                    .with_help(format!(
+                        "write `fn {name}(...) = expression` or `fn {name}(...) {{ ... }}`"
+                    )));
             }
         };
 
@@ -186,41 +281,86 @@ impl Parser {
             arguments,
             body,
             ret_type: ty,
+            mutable_self,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_inline_function(&mut self) -> Result<HStatement, String> {
+    /// Parses the optional `self` or `mut self` that a method may take
+    /// before its parameters. Methods without it take `self`. Returns
+    /// whether the method takes `mut self`.
+    fn parse_receiver(&mut self, method: bool) -> Result<bool, Diagnostic> {
+        let token = self.peek()?;
+        let mutable = match token.kind {
+            TokenKind::Keyword(Keyword::Mut) => true,
+            TokenKind::Keyword(Keyword::Selff) => false,
+            _ => return Ok(false),
+        };
+        if !method {
+            return Err(self
+                .error_at(&token, "Only methods can take `self`".into())
+                .with_help("move the function into a struct, or pass the struct as a parameter"));
+        }
+        if mutable {
+            self.match_keyword(Keyword::Mut)?;
+        }
+        self.match_keyword(Keyword::Selff)?;
+        if self.peek_token(TokenKind::Comma).is_ok() {
+            self.match_token(TokenKind::Comma)?;
+        }
+        Ok(mutable)
+    }
+
+    fn parse_inline_function(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.next()?;
         let expr = self.parse_expression()?;
-        let return_statment = HStatement::Return(Some(expr));
+        let span = expr.span;
+        let return_statment = HStatement::new(HStatementKind::Return(Some(expr)), span);
         let statements = vec![return_statment];
         let scope = vec![];
-        Ok(HStatement::Block { statements, scope })
+        Ok(HStatement::new(
+            HStatementKind::Block { statements, scope },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_import(&mut self) -> Result<String, String> {
+    fn parse_import(&mut self) -> Result<String, Diagnostic> {
         self.match_keyword(Keyword::Import)?;
         let token = self.next()?;
-        let path = match token.kind {
-            TokenKind::Literal(Value::Str(path)) => path,
+        let path = match &token.kind {
+            TokenKind::Literal(Value::Str(path)) => path.clone(),
             other => {
-                return Err(
-                    self.make_error_msg(token.pos, format!("Expected string, got {:?}", other))
-                )
+                return Err(self.error_at(
+                    &token,
+                    format!(
+                        "Expected the path of a module as a string, found `{}`",
+                        other
+                    ),
+                ))
             }
         };
 
         Ok(path)
     }
 
-    fn parse_type(&mut self) -> Result<Type, String> {
+    fn parse_type(&mut self) -> Result<Type, Diagnostic> {
         self.match_token(TokenKind::Colon)?;
+        self.parse_type_name()
+    }
+
+    fn parse_type_name(&mut self) -> Result<Type, Diagnostic> {
         let next = self.peek()?;
-        let typ = match next.kind {
-            TokenKind::Identifier(_) => Type::try_from(self.next()?.raw),
-            _ => Err("Expected type".into()),
-        }?;
-        if self.peek_token(TokenKind::SquareBraceOpen).is_ok() {
+        let mut typ = match next.kind {
+            TokenKind::Identifier(_) => {
+                let token = self.next()?;
+                Type::try_from(token.raw.clone()).map_err(|e| self.error_at(&token, e))?
+            }
+            _ => return Err(self.error_at(&next, format!("Expected type, found `{}`", next.raw))),
+        };
+        // Every `[]` suffix wraps the type parsed so far, so `int[][]` is an
+        // array of `int[]` and `int[2][3]` holds three `int[2]` arrays.
+        while self.peek_token(TokenKind::SquareBraceOpen).is_ok() {
             self.match_token(TokenKind::SquareBraceOpen)?;
             let capacity = match self.peek_token(TokenKind::Literal(Value::Int)) {
                 Ok(val) => {
@@ -230,19 +370,22 @@ impl Parser {
                 Err(_) => None,
             };
             self.match_token(TokenKind::SquareBraceClose)?;
-            Ok(Type::Array(Box::new(typ), capacity))
-        } else {
-            Ok(typ)
+            typ = Type::Array(Box::new(typ), capacity);
         }
+        Ok(typ)
     }
 
-    fn parse_statement(&mut self) -> Result<HStatement, String> {
+    fn parse_statement(&mut self) -> Result<HStatement, Diagnostic> {
         let token = self.peek()?;
+        let exp = |expr: HExpression| {
+            let span = expr.span;
+            HStatement::new(HStatementKind::Exp(expr), span)
+        };
 
         match &token.kind {
             TokenKind::CurlyBracesOpen => self.parse_block(),
             TokenKind::Keyword(keyword) => match keyword {
-                Keyword::Let => self.parse_declare(),
+                Keyword::Let | Keyword::Var => self.parse_declare(),
                 Keyword::Return => self.parse_return(),
                 Keyword::If => self.parse_conditional_statement(),
                 Keyword::While => self.parse_while_loop(),
@@ -250,9 +393,10 @@ impl Parser {
                 Keyword::Continue => self.parse_continue(),
                 Keyword::For => self.parse_for_loop(),
                 Keyword::Match => self.parse_match_statement(),
-                Keyword::Struct => {
-                    Err("Struct definitions inside functions are not allowed".to_string())
-                }
+                Keyword::Struct => Err(self.error_at(
+                    &token,
+                    "Struct definitions inside functions are not allowed".into(),
+                )),
                 Keyword::Selff => {
                     let expr = self.parse_expression()?;
                     if self.has_more() {
@@ -260,49 +404,33 @@ impl Parser {
                             return self.parse_assignent(Some(expr));
                         }
                     }
-                    Ok(HStatement::Exp(expr))
+                    Ok(exp(expr))
                 }
-                _ => Ok(HStatement::Exp(self.parse_expression()?)),
+                _ => Ok(exp(self.parse_expression()?)),
             },
-            TokenKind::BraceOpen => Ok(HStatement::Exp(self.parse_expression()?)),
+            TokenKind::BraceOpen => Ok(exp(self.parse_expression()?)),
             TokenKind::Identifier(_) => {
-                let ident = self.match_identifier()?;
-
-                // Handle initial expression which could be field access or just a variable
-                let expr = if self.peek_token(TokenKind::Dot).is_ok() {
-                    self.parse_field_access(HExpression::Variable(ident.clone()))?
-                } else {
-                    HExpression::Variable(ident.clone())
-                };
-
-                // Look ahead to determine statement type
-                match self.peek()?.kind {
-                    TokenKind::BraceOpen => {
-                        let state = self.parse_function_call(Some(ident))?;
-                        Ok(HStatement::Exp(state))
-                    }
-                    TokenKind::Assign => self.parse_assignent(Some(expr)),
-                    TokenKind::SquareBraceOpen => {
-                        let array_expr = self.parse_array_access(Some(ident))?;
-                        match self.peek()?.kind {
-                            TokenKind::Assign => self.parse_assignent(Some(array_expr)),
-                            _ => Ok(HStatement::Exp(array_expr)),
-                        }
-                    }
-                    kind if HBinOp::try_from(kind.clone()).is_ok() => {
-                        Ok(HStatement::Exp(self.parse_bin_op(Some(expr))?))
+                let expr = self.parse_expression()?;
+                if self.has_more() {
+                    if let TokenKind::Assign = self.peek()?.kind {
+                        return self.parse_assignent(Some(expr));
                     }
-                    TokenKind::Dot => Ok(HStatement::Exp(self.parse_field_access(expr)?)),
-                    _ => Ok(HStatement::Exp(expr)),
                 }
+                Ok(exp(expr))
             }
-            TokenKind::Literal(_) => Ok(HStatement::Exp(self.parse_expression()?)),
-            _ => Err(self.make_error_msg(token.pos, "Failed to parse statement".to_string())),
+            TokenKind::Literal(_) => Ok(exp(self.parse_expression()?)),
+            _ => Err(self.error_at(&token, format!("Expected statement, found `{}`", token.raw))),
         }
     }
 
     /// Parses a function call from tokens.
-    fn parse_function_call(&mut self, func_name: Option<String>) -> Result<HExpression, String> {
+    /// `start` is the offset of the function name, which has already been
+    /// consumed if it is given
+    fn parse_function_call(
+        &mut self,
+        func_name: Option<String>,
+        start: usize,
+    ) -> Result<HExpression, Diagnostic> {
         let fn_name = match func_name {
             Some(name) => name,
             None => self.match_identifier()?,
@@ -311,6 +439,7 @@ impl Parser {
         self.match_token(TokenKind::BraceOpen)?;
 
         let mut args = Vec::new();
+        let mut named = Vec::new();
 
         loop {
             let next = self.peek()?;
@@ -320,38 +449,60 @@ impl Parser {
                     let _ = self.next();
                     continue;
                 }
-                TokenKind::Identifier(_) | TokenKind::Literal(_) => {
-                    args.push(self.parse_expression()?)
-                }
-                TokenKind::Keyword(Keyword::Boolean) | TokenKind::Keyword(Keyword::New) => {
-                    args.push(self.parse_expression()?)
+                TokenKind::Identifier(name) => {
+                    let ident = self.next()?;
+                    // `name: value` passes an argument by name
+                    if self.peek_token(TokenKind::Colon).is_ok() {
+                        self.next()?;
+                        named.push((name.clone(), self.parse_expression()?));
+                        continue;
+                    }
+                    self.push(ident);
                 }
-                TokenKind::SquareBraceOpen => args.push(self.parse_expression()?),
+                TokenKind::Literal(_)
+                | TokenKind::Keyword(Keyword::Boolean)
+                | TokenKind::Keyword(Keyword::New)
+                | TokenKind::SquareBraceOpen
+                | TokenKind::BraceOpen => {}
                 _ => {
                     return Err(self.make_error(TokenKind::BraceClose, next));
                 }
             };
+            if !named.is_empty() {
+                return Err(self.error_at(
+                    &next,
+                    "Positional arguments must come before named arguments".into(),
+                ));
+            }
+            args.push(self.parse_expression()?);
         }
 
         self.match_token(TokenKind::BraceClose)?;
-        let expr = HExpression::FunctionCall { fn_name, args };
-        match self.peek()?.kind {
-            TokenKind::Dot => self.parse_field_access(expr),
-            _ if HBinOp::try_from(self.peek()?.kind).is_ok() => self.parse_bin_op(Some(expr)),
-            _ => Ok(expr),
-        }
+        Ok(HExpression::new(
+            HExpressionKind::FunctionCall {
+                fn_name,
+                args,
+                named,
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_return(&mut self) -> Result<HStatement, String> {
+    fn parse_return(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Return)?;
         let peeked = self.peek()?;
-        match peeked.kind {
-            TokenKind::SemiColon => Ok(HStatement::Return(None)),
-            _ => Ok(HStatement::Return(Some(self.parse_expression()?))),
-        }
+        let value = match peeked.kind {
+            TokenKind::SemiColon => None,
+            _ => Some(self.parse_expression()?),
+        };
+        Ok(HStatement::new(
+            HStatementKind::Return(value),
+            self.span_from(start),
+        ))
     }
 
-    fn parse_expression(&mut self) -> Result<HExpression, String> {
+    fn parse_expression(&mut self) -> Result<HExpression, Diagnostic> {
         let primary = self.parse_primary()?;
         self.parse_binop_rhs(primary, 0)
     }
@@ -363,7 +514,7 @@ impl Parser {
         &mut self,
         mut lhs: HExpression,
         min_prec: u8,
-    ) -> Result<HExpression, String> {
+    ) -> Result<HExpression, Diagnostic> {
         loop {
             if !self.has_more() {
                 break;
@@ -380,174 +531,197 @@ impl Parser {
             // Collect higher-precedence operators into rhs
             let rhs = self.parse_binop_rhs(primary_rhs, op.precedence() + 1)?;
 
-            lhs = HExpression::BinOp {
-                lhs: Box::new(lhs),
-                op,
-                rhs: Box::new(rhs),
-            };
+            let span = lhs.span.to(rhs.span);
+            lhs = HExpression::new(
+                HExpressionKind::BinOp {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs: Box::new(rhs),
+                    ty: None,
+                },
+                span,
+            );
         }
         Ok(lhs)
     }
 
     /// Parses a primary expression (literal, identifier, function call, etc.)
     /// without consuming any trailing binary operators.
-    fn parse_primary(&mut self) -> Result<HExpression, String> {
+    fn parse_primary(&mut self) -> Result<HExpression, Diagnostic> {
         let token = self.peek()?;
+        let start = token.start;
 
-        let expr = match token.kind {
+        let kind = match token.kind {
             // (1 + 2)
             TokenKind::BraceOpen => {
                 self.match_token(TokenKind::BraceOpen)?;
                 let expr = self.parse_expression()?;
                 self.match_token(TokenKind::BraceClose)?;
-                expr
+                expr.kind
             }
             // true | false
             TokenKind::Keyword(Keyword::Boolean) => {
                 let token = self.next()?;
-                HExpression::Bool(token.raw.parse::<bool>().map_err(|e| e.to_string())?)
+                let value = token.raw.parse::<bool>();
+                HExpressionKind::Bool(value.map_err(|e| self.error_at(&token, e.to_string()))?)
             }
             // 5
             TokenKind::Literal(Value::Int) => {
                 let token = self.next()?;
                 let clean_str = token.raw.replace('_', "");
-                let val = match clean_str {
+                // Split off a type suffix such as `u8`. Neither `i` nor `u` are hex digits.
+                let (digits, suffix) =
+                    clean_str.split_at(clean_str.find(['i', 'u']).unwrap_or(clean_str.len()));
+                let val = match digits {
                     c if c.starts_with("0b") => {
-                        usize::from_str_radix(token.raw.trim_start_matches("0b"), 2)
-                            .map_err(|e| e.to_string())?
+                        usize::from_str_radix(c.trim_start_matches("0b"), 2)
+                            .map_err(|e| self.error_at(&token, e.to_string()))?
                     }
                     c if c.starts_with("0o") => {
-                        usize::from_str_radix(token.raw.trim_start_matches("0o"), 8)
-                            .map_err(|e| e.to_string())?
+                        usize::from_str_radix(c.trim_start_matches("0o"), 8)
+                            .map_err(|e| self.error_at(&token, e.to_string()))?
                     }
                     c if c.starts_with("0x") => {
-                        usize::from_str_radix(token.raw.trim_start_matches("0x"), 16)
-                            .map_err(|e| e.to_string())?
+                        usize::from_str_radix(c.trim_start_matches("0x"), 16)
+                            .map_err(|e| self.error_at(&token, e.to_string()))?
                     }
-                    c => c.parse::<usize>().map_err(|e| e.to_string())?,
+                    c => c
+                        .parse::<usize>()
+                        .map_err(|e| self.error_at(&token, e.to_string()))?,
                 };
-                HExpression::Int(val)
+                if suffix.is_empty() {
+                    HExpressionKind::Int(val)
+                } else {
+                    let ty =
+                        Type::try_from(suffix.to_string()).map_err(|e| self.error_at(&token, e))?;
+                    match ty.int_max() {
+                        Some(max) if ty.is_sized_integer() && val as u64 <= max => {}
+                        Some(_) if ty.is_sized_integer() => {
+                            return Err(self
+                                .error_at(
+                                    &token,
+                                    format!("Literal `{}` does not fit into `{}`", digits, ty),
+                                )
+                                .with_code(codes::LITERAL))
+                        }
+                        _ => {
+                            return Err(self
+                                .error_at(&token, format!("Invalid integer suffix `{}`", suffix)))
+                        }
+                    }
+                    HExpressionKind::Cast {
+                        expr: Box::new(HExpression::new(
+                            HExpressionKind::Int(val),
+                            self.span_from(start),
+                        )),
+                        from: Some(Type::Int),
+                        to: ty,
+                    }
+                }
             }
             // "A string"
             TokenKind::Literal(Value::Str(string)) => {
                 self.next()?;
-                HExpression::Str(string)
+                HExpressionKind::Str(string)
             }
             // self
             TokenKind::Keyword(Keyword::Selff) => {
                 self.next()?;
-                HExpression::Selff
+                HExpressionKind::Selff
             }
             TokenKind::Identifier(_) => {
                 let val = self.match_identifier()?;
                 if !self.has_more() {
-                    return Ok(HExpression::Variable(val));
+                    return Ok(HExpression::new(
+                        HExpressionKind::Variable(val),
+                        self.span_from(start),
+                    ));
                 }
-                let next = self.peek()?;
-                match &next.kind {
+                match self.peek()?.kind {
                     // foo()
-                    TokenKind::BraceOpen => self.parse_function_call(Some(val))?,
-                    // arr[0]
-                    TokenKind::SquareBraceOpen => self.parse_array_access(Some(val))?,
+                    TokenKind::BraceOpen => self.parse_function_call(Some(val), start)?.kind,
                     // some_var
-                    _ => HExpression::Variable(val),
+                    _ => HExpressionKind::Variable(val),
                 }
             }
             // [1, 2, 3]
-            TokenKind::SquareBraceOpen => self.parse_array()?,
+            TokenKind::SquareBraceOpen => self.parse_array()?.kind,
             // new Foo {}
-            TokenKind::Keyword(Keyword::New) => self.parse_struct_initialization()?,
-            other => return Err(format!("Expected Expression, found `{other}`")),
+            TokenKind::Keyword(Keyword::New) => self.parse_struct_initialization()?.kind,
+            other => {
+                let token = self.peek()?;
+                return Err(self.error_at(&token, format!("Expected expression, found `{other}`")));
+            }
         };
 
-        // Handle field access after primary
-        if self.has_more() {
-            if let TokenKind::Dot = self.peek()?.kind {
-                return self.parse_field_access(expr);
-            }
-        }
+        let expr = HExpression::new(kind, self.span_from(start));
+        self.parse_postfix(expr)
+    }
 
+    /// Parses a chain of field accesses, method calls, indexing operations
+    /// and casts following a primary expression, e.g. `.users[0].name` or `[i] as string`.
+    fn parse_postfix(&mut self, mut expr: HExpression) -> Result<HExpression, Diagnostic> {
+        while self.has_more() {
+            expr = match self.peek()?.kind {
+                TokenKind::Dot => self.parse_field_access(expr)?,
+                TokenKind::SquareBraceOpen => self.parse_array_access(expr)?,
+                TokenKind::Keyword(Keyword::As) => {
+                    self.match_keyword(Keyword::As)?;
+                    let to = self.parse_type_name()?;
+                    let span = self.span_from(expr.span.start);
+                    HExpression::new(
+                        HExpressionKind::Cast {
+                            expr: Box::new(expr),
+                            from: None,
+                            to,
+                        },
+                        span,
+                    )
+                }
+                _ => break,
+            };
+        }
         Ok(expr)
     }
 
-    fn parse_field_access(&mut self, lhs: HExpression) -> Result<HExpression, String> {
+    fn parse_field_access(&mut self, lhs: HExpression) -> Result<HExpression, Diagnostic> {
         self.match_token(TokenKind::Dot)?;
 
         // Only possible options are identifier or function call,
         // So it's safe to assume that the next token should be an identifier
+        let start = self.start();
         let id = self.match_identifier()?;
-        let next = self.peek()?;
 
-        let field = match next.kind {
-            TokenKind::BraceOpen => self.parse_function_call(Some(id))?,
-            _ => HExpression::Variable(id),
+        let field = match self.has_more() && self.peek()?.kind == TokenKind::BraceOpen {
+            true => self.parse_function_call(Some(id), start)?,
+            false => HExpression::new(HExpressionKind::Variable(id), self.span_from(start)),
         };
-        let expr = HExpression::FieldAccess {
-            expr: Box::new(lhs),
-            field: Box::new(field),
-        };
-
-        // Look ahead to determine next steps
-        match self.peek()?.kind {
-            TokenKind::Dot => self.parse_field_access(expr),
-            TokenKind::SquareBraceOpen => {
-                self.match_token(TokenKind::SquareBraceOpen)?;
-                let index_expr = self.parse_expression()?;
-                self.match_token(TokenKind::SquareBraceClose)?;
-
-                let array_expr = HExpression::ArrayAccess {
-                    name: format!("{:?}", expr),
-                    index: Box::new(index_expr),
-                };
-
-                // Handle any further chaining (like .foo[0] or [0][0])
-                match self.peek()?.kind {
-                    TokenKind::Dot => self.parse_field_access(array_expr),
-                    TokenKind::SquareBraceOpen => {
-                        // Handle nested array access
-                        let mut current_expr = array_expr;
-                        while self.peek_token(TokenKind::SquareBraceOpen).is_ok() {
-                            self.match_token(TokenKind::SquareBraceOpen)?;
-                            let index = self.parse_expression()?;
-                            self.match_token(TokenKind::SquareBraceClose)?;
-
-                            current_expr = HExpression::ArrayAccess {
-                                name: format!("{:?}", current_expr),
-                                index: Box::new(index),
-                            };
-                        }
 
-                        // If there's a dot after the nested array access
-                        if self.peek_token(TokenKind::Dot).is_ok() {
-                            self.parse_field_access(current_expr)
-                        } else if HBinOp::try_from(self.peek()?.kind).is_ok() {
-                            self.parse_bin_op(Some(current_expr))
-                        } else {
-                            Ok(current_expr)
-                        }
-                    }
-                    kind if HBinOp::try_from(kind.clone()).is_ok() => {
-                        self.parse_bin_op(Some(array_expr))
-                    }
-                    _ => Ok(array_expr),
-                }
-            }
-            kind if HBinOp::try_from(kind.clone()).is_ok() => self.parse_bin_op(Some(expr)),
-            _ => Ok(expr),
-        }
+        let span = self.span_from(lhs.span.start);
+        Ok(HExpression::new(
+            HExpressionKind::FieldAccess {
+                expr: Box::new(lhs),
+                field: Box::new(field),
+            },
+            span,
+        ))
     }
 
-    fn parse_struct_initialization(&mut self) -> Result<HExpression, String> {
+    fn parse_struct_initialization(&mut self) -> Result<HExpression, Diagnostic> {
+        let start = self.start();
         self.match_token(TokenKind::Keyword(Keyword::New))?;
         let name = self.match_identifier()?;
         self.match_token(TokenKind::CurlyBracesOpen)?;
         let fields = self.parse_struct_fields()?;
         self.match_token(TokenKind::CurlyBracesClose)?;
 
-        Ok(HExpression::StructInitialization { name, fields })
+        Ok(HExpression::new(
+            HExpressionKind::StructInitialization { name, fields },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_struct_fields(&mut self) -> Result<HashMap<String, Box<HExpression>>, String> {
+    fn parse_struct_fields(&mut self) -> Result<HashMap<String, Box<HExpression>>, Diagnostic> {
         let mut map = HashMap::new();
 
         // If there is a field
@@ -564,31 +738,34 @@ impl Parser {
 
             let last = self.peek()?;
             if last.kind != TokenKind::CurlyBracesClose {
-                let mut error = self.make_error_msg(
-                    last.pos,
-                    "Expected a struct field initialization or a closing curly brace (`}`)".into(),
-                );
-                let hint =
-                    self.make_hint_msg(format!("remove the following symbol `{}`", last.raw));
-                error.push_str(&hint);
-                return Err(error);
+                return Err(self
+                    .error_at(
+                        &last,
+                        "Expected a struct field initialization or a closing curly brace (`}`)"
+                            .into(),
+                    )
+                    .with_help(format!("remove `{}`", last.raw)));
             }
         }
 
         Ok(map)
     }
 
-    fn parse_struct_field(&mut self) -> Result<(String, Box<HExpression>), String> {
+    fn parse_struct_field(&mut self) -> Result<(String, Box<HExpression>), Diagnostic> {
         let next = self.next()?;
         if let TokenKind::Identifier(name) = next.kind {
             self.match_token(TokenKind::Colon)?;
             return Ok((name, Box::new(self.parse_expression()?)));
         }
 
-        Err(format!("Struct field could not be parsed: {}", next.raw))
+        Err(self.error_at(
+            &next,
+            format!("Expected struct field, found `{}`", next.raw),
+        ))
     }
 
-    fn parse_array(&mut self) -> Result<HExpression, String> {
+    fn parse_array(&mut self) -> Result<HExpression, Diagnostic> {
+        let start = self.start();
         self.match_token(TokenKind::SquareBraceOpen)?;
         let mut elements = Vec::new();
         loop {
@@ -596,12 +773,16 @@ impl Parser {
             match next.kind {
                 TokenKind::SquareBraceClose => {}
                 TokenKind::Literal(Value::Int) => {
-                    let value = self
-                        .next()?
+                    let element_start = self.start();
+                    let token = self.next()?;
+                    let value = token
                         .raw
                         .parse::<usize>()
-                        .map_err(|e| e.to_string())?;
-                    elements.push(HExpression::Int(value));
+                        .map_err(|e| self.error_at(&token, e.to_string()))?;
+                    elements.push(HExpression::new(
+                        HExpressionKind::Int(value),
+                        self.span_from(element_start),
+                    ));
                 }
                 _ => {
                     let expr = self.parse_expression()?;
@@ -617,70 +798,92 @@ impl Parser {
         self.match_token(TokenKind::SquareBraceClose)?;
         let capacity = elements.len();
 
-        Ok(HExpression::Array { capacity, elements })
+        Ok(HExpression::new(
+            HExpressionKind::Array { capacity, elements },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_array_access(&mut self, arr_name: Option<String>) -> Result<HExpression, String> {
-        let name = match arr_name {
-            Some(name) => name,
-            None => self.next()?.raw,
-        };
-
+    fn parse_array_access(&mut self, expr: HExpression) -> Result<HExpression, Diagnostic> {
         self.match_token(TokenKind::SquareBraceOpen)?;
-        let expr = self.parse_expression()?;
+        let index = self.parse_expression()?;
         self.match_token(TokenKind::SquareBraceClose)?;
 
-        Ok(HExpression::ArrayAccess {
-            name,
-            index: Box::new(expr),
-        })
+        let span = self.span_from(expr.span.start);
+        Ok(HExpression::new(
+            HExpressionKind::ArrayAccess {
+                expr: Box::new(expr),
+                index: Box::new(index),
+            },
+            span,
+        ))
     }
 
-    fn parse_while_loop(&mut self) -> Result<HStatement, String> {
+    fn parse_while_loop(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::While)?;
         let condition = self.parse_expression()?;
         let body = self.parse_block()?;
 
-        Ok(HStatement::While {
-            condition,
-            body: Box::new(body),
-        })
+        Ok(HStatement::new(
+            HStatementKind::While {
+                condition,
+                body: Box::new(body),
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_break(&mut self) -> Result<HStatement, String> {
+    fn parse_break(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Break)?;
-        Ok(HStatement::Break)
+        Ok(HStatement::new(
+            HStatementKind::Break,
+            self.span_from(start),
+        ))
     }
 
-    fn parse_continue(&mut self) -> Result<HStatement, String> {
+    fn parse_continue(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Continue)?;
-        Ok(HStatement::Continue)
+        Ok(HStatement::new(
+            HStatementKind::Continue,
+            self.span_from(start),
+        ))
     }
 
-    fn parse_for_loop(&mut self) -> Result<HStatement, String> {
+    fn parse_for_loop(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::For)?;
 
+        let ident_start = self.start();
         let ident = self.match_identifier()?;
         let ident_ty = match self.peek()?.kind {
             TokenKind::Colon => Some(self.parse_type()?),
             _ => None,
         };
+        let ident_span = self.span_from(ident_start);
         self.match_keyword(Keyword::In)?;
         let expr = self.parse_expression()?;
 
         let body = self.parse_block()?;
 
-        Ok(HStatement::For {
-            ident: HVariable {
-                name: ident,
-                ty: ident_ty,
+        Ok(HStatement::new(
+            HStatementKind::For {
+                ident: HVariable {
+                    name: ident,
+                    ty: ident_ty,
+                    span: ident_span,
+                },
+                expr,
+                body: Box::new(body),
             },
-            expr,
-            body: Box::new(body),
-        })
+            self.span_from(start),
+        ))
     }
 
-    fn parse_match_statement(&mut self) -> Result<HStatement, String> {
+    fn parse_match_statement(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::Match)?;
         let subject = self.parse_expression()?;
         self.match_token(TokenKind::CurlyBracesOpen)?;
@@ -696,10 +899,9 @@ impl Parser {
                 }
                 TokenKind::Identifier(ref id) if id == "_" => {
                     if has_else {
-                        return Err(self.make_error_msg(
-                            next.pos,
-                            "Multiple else arms are not allowed".to_string(),
-                        ));
+                        return Err(
+                            self.error_at(&next, "Multiple else arms are not allowed".into())
+                        );
                     }
                     has_else = true;
                     arms.push(self.parse_match_arm()?);
@@ -707,23 +909,29 @@ impl Parser {
                 TokenKind::Identifier(_) => arms.push(self.parse_match_arm()?),
                 TokenKind::Keyword(Keyword::Else) => {
                     if has_else {
-                        return Err(self.make_error_msg(
-                            next.pos,
-                            "Multiple else arms are not allowed".to_string(),
-                        ));
+                        return Err(
+                            self.error_at(&next, "Multiple else arms are not allowed".into())
+                        );
                     }
                     has_else = true;
                     arms.push(self.parse_match_arm()?);
                 }
                 TokenKind::CurlyBracesClose => break,
-                _ => return Err(self.make_error_msg(next.pos, "Illegal token".to_string())),
+                _ => {
+                    return Err(
+                        self.error_at(&next, format!("Expected match arm, found `{}`", next.raw))
+                    )
+                }
             }
         }
         self.match_token(TokenKind::CurlyBracesClose)?;
-        Ok(HStatement::Match { subject, arms })
+        Ok(HStatement::new(
+            HStatementKind::Match { subject, arms },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_match_arm(&mut self) -> Result<HMatchArm, String> {
+    fn parse_match_arm(&mut self) -> Result<HMatchArm, Diagnostic> {
         let next = self.peek()?;
 
         match next.kind {
@@ -747,7 +955,8 @@ impl Parser {
         }
     }
 
-    fn parse_conditional_statement(&mut self) -> Result<HStatement, String> {
+    fn parse_conditional_statement(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         self.match_keyword(Keyword::If)?;
         let condition = self.parse_expression()?;
 
@@ -768,65 +977,88 @@ impl Parser {
                     Some(branch) => branch,
                     None => self.parse_conditional_statement()?,
                 };
-                Ok(HStatement::If {
+                Ok(HStatement::new(
+                    HStatementKind::If {
+                        condition,
+                        body: Box::new(body),
+                        else_branch: Some(Box::new(else_branch)),
+                    },
+                    self.span_from(start),
+                ))
+            }
+            _ => Ok(HStatement::new(
+                HStatementKind::If {
                     condition,
                     body: Box::new(body),
-                    else_branch: Some(Box::new(else_branch)),
-                })
-            }
-            _ => Ok(HStatement::If {
-                condition,
-                body: Box::new(body),
-                else_branch: None,
-            }),
+                    else_branch: None,
+                },
+                self.span_from(start),
+            )),
         }
     }
 
-    fn parse_bin_op(&mut self, lhs: Option<HExpression>) -> Result<HExpression, String> {
-        let left = match lhs {
-            Some(lhs) => lhs,
-            None => self.parse_primary()?,
+    fn parse_declare(&mut self) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
+        let mutable = match self.next()?.kind {
+            TokenKind::Keyword(Keyword::Var) => true,
+            _ => {
+                let mutable = self.peek_token(TokenKind::Keyword(Keyword::Mut)).is_ok();
+                if mutable {
+                    self.match_keyword(Keyword::Mut)?;
+                }
+                mutable
+            }
         };
-        self.parse_binop_rhs(left, 0)
-    }
-
-    fn parse_declare(&mut self) -> Result<HStatement, String> {
-        self.match_keyword(Keyword::Let)?;
+        let name_start = self.start();
         let name = self.match_identifier()?;
         let ty = match self.peek()?.kind {
             TokenKind::Colon => Some(self.parse_type()?),
             _ => None,
         };
+        let variable = HVariable {
+            name,
+            ty,
+            span: self.span_from(name_start),
+        };
 
-        match self.peek()?.kind {
+        let value = match self.peek()?.kind {
             TokenKind::Assign => {
                 self.match_token(TokenKind::Assign)?;
-                let expr = self.parse_expression()?;
-                Ok(HStatement::Declare {
-                    variable: HVariable { name, ty },
-                    value: Some(expr),
-                })
+                Some(self.parse_expression()?)
             }
-            _ => Ok(HStatement::Declare {
-                variable: HVariable { name, ty },
-                value: None,
-            }),
-        }
+            _ => None,
+        };
+        Ok(HStatement::new(
+            HStatementKind::Declare {
+                variable,
+                value,
+                mutable,
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_assignent(&mut self, name: Option<HExpression>) -> Result<HStatement, String> {
+    fn parse_assignent(&mut self, name: Option<HExpression>) -> Result<HStatement, Diagnostic> {
+        let start = self.start();
         let name = match name {
             Some(name) => name,
-            None => HExpression::Variable(self.match_identifier()?),
+            None => {
+                let kind = HExpressionKind::Variable(self.match_identifier()?);
+                HExpression::new(kind, self.span_from(start))
+            }
         };
 
         self.match_token(TokenKind::Assign)?;
 
         let expr = self.parse_expression()?;
 
-        Ok(HStatement::Assign {
-            lhs: Box::new(name),
-            rhs: Box::new(expr),
-        })
+        let span = self.span_from(name.span.start);
+        Ok(HStatement::new(
+            HStatementKind::Assign {
+                lhs: Box::new(name),
+                rhs: Box::new(expr),
+            },
+            span,
+        ))
     }
 }
@@ -0,0 +1,334 @@
+/**
+ * Copyright 2021 Garrit Franke
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::ast::hast::{
+    HExpression, HExpressionKind, HFunction, HMatchArm, HModule, HStatement, HStatementKind,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Renames the variables of a function so that every declaration has a
+/// name of its own
+struct Renamer<'a> {
+    /// Functions and structs, which variables must not hide in the
+    /// generated code
+    globals: &'a HashSet<String>,
+    /// Every name that is used in the function, or that was given to a
+    /// variable
+    taken: HashSet<String>,
+    /// Names of the source that a variable of the function was declared with
+    declared: HashSet<String>,
+    /// Original name -> unique name of the variables of the enclosing
+    /// blocks, innermost last
+    scopes: Vec<HashMap<String, String>>,
+}
+
+/// Gives every variable declaration in a function a unique name.
+///
+/// A variable is visible from its declaration to the end of the block that
+/// contains it. A declaration may reuse the name of a variable that is
+/// still visible, which hides that variable until the end of the block, or
+/// of a variable that went out of scope. The first declaration of a name
+/// keeps it, later ones are renamed, e.g. to `x_1`. Backends therefore
+/// never see two variables with the same name in a function, and do not
+/// depend on the scoping rules of their target language.
+///
+/// Runs after all checks, so diagnostics show the names of the source.
+pub fn rename_shadowed(program: &mut HModule) {
+    let globals: HashSet<String> = program
+        .func
+        .iter()
+        .map(|func| func.name.clone())
+        .chain(program.structs.iter().map(|def| def.name.clone()))
+        .collect();
+    for func in &mut program.func {
+        Renamer::function(&globals, func);
+    }
+    for def in &mut program.structs {
+        for method in &mut def.methods {
+            Renamer::function(&globals, method);
+        }
+    }
+}
+
+/// Adds the names that `stmt` declares or reads to `names`
+fn collect_statement(stmt: &HStatement, names: &mut HashSet<String>) {
+    match &stmt.kind {
+        HStatementKind::Block { statements, .. } => {
+            for s in statements {
+                collect_statement(s, names);
+            }
+        }
+        HStatementKind::Declare {
+            variable, value, ..
+        } => {
+            names.insert(variable.name.clone());
+            if let Some(value) = value {
+                collect_expression(value, names);
+            }
+        }
+        HStatementKind::Assign { lhs, rhs } => {
+            collect_expression(lhs, names);
+            collect_expression(rhs, names);
+        }
+        HStatementKind::Return(value) => {
+            if let Some(value) = value {
+                collect_expression(value, names);
+            }
+        }
+        HStatementKind::If {
+            condition,
+            body,
+            else_branch,
+        } => {
+            collect_expression(condition, names);
+            collect_statement(body, names);
+            if let Some(else_branch) = else_branch {
+                collect_statement(else_branch, names);
+            }
+        }
+        HStatementKind::While { condition, body } => {
+            collect_expression(condition, names);
+            collect_statement(body, names);
+        }
+        HStatementKind::For { ident, expr, body } => {
+            names.insert(ident.name.clone());
+            collect_expression(expr, names);
+            collect_statement(body, names);
+        }
+        HStatementKind::Match { subject, arms } => {
+            collect_expression(subject, names);
+            for arm in arms {
+                match arm {
+                    HMatchArm::Case(case, body) => {
+                        collect_expression(case, names);
+                        collect_statement(body, names);
+                    }
+                    HMatchArm::Else(body) => collect_statement(body, names),
+                }
+            }
+        }
+        HStatementKind::Exp(e) => collect_expression(e, names),
+        HStatementKind::Break | HStatementKind::Continue => {}
+    }
+}
+
+fn collect_expression(expr: &HExpression, names: &mut HashSet<String>) {
+    match &expr.kind {
+        HExpressionKind::Variable(name) => {
+            names.insert(name.clone());
+        }
+        HExpressionKind::FunctionCall { args, named, .. } => {
+            for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                collect_expression(arg, names);
+            }
+        }
+        HExpressionKind::Array { elements, .. } => {
+            for element in elements {
+                collect_expression(element, names);
+            }
+        }
+        HExpressionKind::ArrayAccess { expr, index } => {
+            collect_expression(expr, names);
+            collect_expression(index, names);
+        }
+        HExpressionKind::BinOp { lhs, rhs, .. } => {
+            collect_expression(lhs, names);
+            collect_expression(rhs, names);
+        }
+        HExpressionKind::StructInitialization { fields, .. } => {
+            for value in fields.values() {
+                collect_expression(value, names);
+            }
+        }
+        HExpressionKind::FieldAccess { expr, field } => {
+            collect_expression(expr, names);
+            // The field itself is not a variable
+            if let HExpressionKind::FunctionCall { args, named, .. } = &field.kind {
+                for arg in args.iter().chain(named.iter().map(|(_, arg)| arg)) {
+                    collect_expression(arg, names);
+                }
+            }
+        }
+        HExpressionKind::Cast { expr, .. } => collect_expression(expr, names),
+        HExpressionKind::Int(_)
+        | HExpressionKind::Str(_)
+        | HExpressionKind::Bool(_)
+        | HExpressionKind::Selff => {}
+    }
+}
+
+impl Renamer<'_> {
+    fn function(globals: &HashSet<String>, func: &mut HFunction) {
+        let mut taken = globals.clone();
+        taken.extend(func.arguments.iter().map(|arg| arg.name.clone()));
+        collect_statement(&func.body, &mut taken);
+        let params = func
+            .arguments
+            .iter()
+            .map(|arg| (arg.name.clone(), arg.name.clone()))
+            .collect();
+        // Parameters keep their names
+        let declared = func.arguments.iter().map(|arg| arg.name.clone()).collect();
+        let mut renamer = Renamer {
+            globals,
+            taken,
+            declared,
+            scopes: vec![params],
+        };
+        renamer.statement(&mut func.body);
+    }
+
+    /// Declares a variable in the innermost scope. Its name is changed if
+    /// a variable of the function was already declared with it, or if it
+    /// would hide a function or struct.
+    fn declare(&mut self, name: &mut String) {
+        let original = name.clone();
+        if !self.declared.insert(original.clone()) || self.globals.contains(&original) {
+            *name = (1..)
+                .map(|n| format!("{}_{}", original, n))
+                .find(|candidate| !self.taken.contains(candidate))
+                .expect("there are infinitely many candidates");
+            self.taken.insert(name.clone());
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(original, name.clone());
+        }
+    }
+
+    /// Replaces a name that is read with the name of the variable it refers to
+    fn resolve(&self, name: &mut String) {
+        let unique = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        if let Some(unique) = unique {
+            *name = unique.clone();
+        }
+    }
+
+    fn statement(&mut self, stmt: &mut HStatement) {
+        match &mut stmt.kind {
+            HStatementKind::Block { statements, scope } => {
+                self.scopes.push(HashMap::new());
+                for s in statements.iter_mut() {
+                    self.statement(s);
+                }
+                self.scopes.pop();
+                // The variables declared in the block, as the parser lists them
+                *scope = statements
+                    .iter()
+                    .filter_map(|s| match &s.kind {
+                        HStatementKind::Declare { variable, .. } => Some(variable.clone()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                // The value is computed before the variable exists, so it
+                // can read a variable of the same name
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.declare(&mut variable.name);
+            }
+            HStatementKind::Assign { lhs, rhs } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            HStatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            HStatementKind::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.statement(body);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            HStatementKind::While { condition, body } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            HStatementKind::For { ident, expr, body } => {
+                self.expression(expr);
+                self.scopes.push(HashMap::new());
+                self.declare(&mut ident.name);
+                self.statement(body);
+                self.scopes.pop();
+            }
+            HStatementKind::Match { subject, arms } => {
+                self.expression(subject);
+                for arm in arms {
+                    match arm {
+                        HMatchArm::Case(case, body) => {
+                            self.expression(case);
+                            self.statement(body);
+                        }
+                        HMatchArm::Else(body) => self.statement(body),
+                    }
+                }
+            }
+            HStatementKind::Exp(e) => self.expression(e),
+            HStatementKind::Break | HStatementKind::Continue => {}
+        }
+    }
+
+    fn arguments<'e>(
+        &self,
+        args: impl IntoIterator<Item = &'e mut HExpression>,
+        named: &'e mut [(String, HExpression)],
+    ) {
+        for arg in args.into_iter().chain(named.iter_mut().map(|(_, arg)| arg)) {
+            self.expression(arg);
+        }
+    }
+
+    fn expression(&self, expr: &mut HExpression) {
+        match &mut expr.kind {
+            HExpressionKind::Variable(name) => self.resolve(name),
+            HExpressionKind::FunctionCall { args, named, .. } => self.arguments(args, named),
+            HExpressionKind::Array { elements, .. } => self.arguments(elements, &mut []),
+            HExpressionKind::ArrayAccess { expr, index } => {
+                self.expression(expr);
+                self.expression(index);
+            }
+            HExpressionKind::BinOp { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            HExpressionKind::StructInitialization { fields, .. } => {
+                for value in fields.values_mut() {
+                    self.expression(value);
+                }
+            }
+            HExpressionKind::FieldAccess { expr, field } => {
+                self.expression(expr);
+                if let HExpressionKind::FunctionCall { args, named, .. } = &mut field.kind {
+                    self.arguments(args, named);
+                }
+            }
+            HExpressionKind::Cast { expr, .. } => self.expression(expr),
+            HExpressionKind::Int(_)
+            | HExpressionKind::Str(_)
+            | HExpressionKind::Bool(_)
+            | HExpressionKind::Selff => {}
+        }
+    }
+}
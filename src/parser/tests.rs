@@ -1,5 +1,10 @@
-use crate::ast::hast::{HBinOp, HExpression, HMatchArm, HStatement};
+use crate::ast::hast::{
+    HBinOp, HExpression, HExpressionKind, HMatchArm, HModule, HStatement, HStatementKind,
+};
+use crate::ast::span::FileId;
+use crate::ast::transform::AstTransformer;
 use crate::ast::types::Type;
+use crate::ast::{Span, StatementKind};
 /**
  * Copyright 2020 Garrit Franke
  *
@@ -15,14 +20,64 @@ use crate::ast::types::Type;
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::diagnostic::{codes, Diagnostic, Level, Severity};
 use crate::lexer::*;
-use crate::parser::parse;
+use crate::parser::{parse, parse_file, Import};
+
+/// Runs the passes between parsing and code generation
+fn analyze(tree: &mut HModule) -> Result<(), Vec<Diagnostic>> {
+    crate::parser::check_duplicates(tree)?;
+    crate::parser::resolve_names(tree, &[])?;
+    crate::parser::infer_types(tree)?;
+    crate::parser::check_types(tree)?;
+    crate::parser::check_flow(tree)
+}
+
+/// The source each diagnostic points at, and its message
+fn labelled<'a>(raw: &'a str, errors: &'a [Diagnostic]) -> Vec<(&'a str, &'a str)> {
+    errors
+        .iter()
+        .map(|e| {
+            let span = e.primary.as_ref().expect("diagnostic without a label").span;
+            (&raw[span.start..span.end], e.message.as_str())
+        })
+        .collect()
+}
+
+/// The message of the first error that `raw` is rejected with
+fn first_error(raw: &str) -> String {
+    let errors = parse(tokenize(raw).unwrap())
+        .and_then(|mut tree| analyze(&mut tree))
+        .expect_err("expected an error");
+    errors[0].message.clone()
+}
+
+/// The source each suggestion replaces, and its replacement
+fn suggested<'a>(raw: &'a str, errors: &'a [Diagnostic]) -> Vec<Option<(&'a str, &'a str)>> {
+    errors
+        .iter()
+        .map(|e| {
+            let suggestion = e.suggestion.as_ref()?;
+            let span = suggestion.span;
+            Some((&raw[span.start..span.end], suggestion.replacement.as_str()))
+        })
+        .collect()
+}
+
+/// The source each secondary label of a diagnostic points at, and its message
+fn secondary<'a>(raw: &'a str, error: &'a Diagnostic) -> Vec<(&'a str, &'a str)> {
+    error
+        .secondary
+        .iter()
+        .map(|l| (&raw[l.span.start..l.span.end], l.message.as_str()))
+        .collect()
+}
 
 #[test]
 fn test_parse_empty_function() {
     let raw = "fn main() {}";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -34,7 +89,7 @@ fn test_parse_function_with_return() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -47,7 +102,7 @@ fn test_parse_inline_function() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -63,7 +118,7 @@ fn test_parse_inline_function_as_last_statement() {
     fn greet(name: string) = \"Hello \" + name
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -75,7 +130,7 @@ fn test_parse_redundant_semicolon() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_err())
 }
 
@@ -85,7 +140,7 @@ fn test_parse_no_function_context() {
     let x = 1
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_err())
 }
 
@@ -103,7 +158,7 @@ fn test_parse_multiple_functions() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -116,7 +171,7 @@ fn test_parse_variable_declaration() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -130,7 +185,7 @@ fn test_parse_variable_reassignment() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -144,7 +199,7 @@ fn test_parse_variable_declaration_added() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -156,7 +211,7 @@ fn test_parse_function_with_args() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -172,7 +227,7 @@ fn test_parse_function_call() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -188,7 +243,7 @@ fn test_parse_return_function_call() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -204,7 +259,7 @@ fn test_parse_function_call_multiple_arguments() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -220,7 +275,7 @@ fn test_parse_nexted_function_call() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -232,7 +287,7 @@ fn test_parse_basic_ops() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -244,7 +299,7 @@ fn test_parse_compound_ops() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -256,7 +311,7 @@ fn test_parse_compound_ops_with_function_call() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -272,7 +327,7 @@ fn test_parse_function_call_binary_op() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse 'foo(1) * 2': {:?}",
@@ -296,7 +351,7 @@ fn test_parse_complex_function_call_binary_op() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse nested function calls with binary ops: {:?}",
@@ -320,7 +375,7 @@ fn test_parse_function_call_binary_op_in_return() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse binary op in return statement: {:?}",
@@ -344,7 +399,7 @@ fn test_parse_function_call_binary_op_nested() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse nested binary operations: {:?}",
@@ -365,7 +420,7 @@ fn test_parse_function_call_binary_op_with_variables() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse binary op with variables: {:?}",
@@ -392,7 +447,7 @@ fn test_parse_function_call_comparison_op() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse comparison with function calls: {:?}",
@@ -416,7 +471,7 @@ fn test_parse_function_call_binary_op_precedence() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(
         tree.is_ok(),
         "Failed to parse operator precedence with function calls: {:?}",
@@ -432,7 +487,7 @@ fn test_parse_compound_ops_with_strings() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -444,7 +499,7 @@ fn test_parse_compound_ops_with_identifier() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -456,7 +511,7 @@ fn test_parse_compound_ops_with_identifier_first() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -468,7 +523,7 @@ fn test_parse_compound_ops_return() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -482,7 +537,7 @@ fn test_parse_basic_conditional() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -497,7 +552,7 @@ fn test_parse_basic_conditional_with_multiple_statements() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -514,7 +569,7 @@ fn test_parse_conditional_else_if_branch() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -533,7 +588,7 @@ fn test_parse_conditional_multiple_else_if_branch_branches() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -550,7 +605,7 @@ fn test_parse_conditional_else_branch() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -571,7 +626,7 @@ fn test_parse_conditional_elseif_else_branch() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -584,7 +639,7 @@ fn test_int_array() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -596,7 +651,7 @@ fn test_string_array() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -611,7 +666,7 @@ fn test_basic_while_loop() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -626,7 +681,7 @@ fn test_while_loop_boolean_expression() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -643,7 +698,7 @@ fn test_boolean_arithmetic() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -662,7 +717,7 @@ fn test_array_access_in_loop() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -676,7 +731,7 @@ fn test_array_access_standalone() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -691,7 +746,7 @@ fn test_array_access_assignment() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -707,7 +762,7 @@ fn test_array_access_in_if() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -720,7 +775,7 @@ fn test_uninitialized_variables() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -732,10 +787,35 @@ fn test_function_call_math() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
+#[test]
+fn test_function_call_parenthesized_argument() {
+    let raw = "
+    fn main(x: int, y: int) {
+        println((x + y) as string, (1))
+    }
+    ";
+    let tree = parse(tokenize(raw).unwrap()).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    let HStatementKind::Exp(HExpression {
+        kind: HExpressionKind::FunctionCall { args, .. },
+        ..
+    }) = &statements[0].kind
+    else {
+        panic!("expected a call");
+    };
+    assert!(matches!(
+        &args[0].kind,
+        HExpressionKind::Cast { expr, .. } if matches!(expr.kind, HExpressionKind::BinOp { .. })
+    ));
+    assert_eq!(args[1].kind, HExpressionKind::Int(1));
+}
+
 #[test]
 fn test_function_multiple_args() {
     let raw = "
@@ -744,7 +824,7 @@ fn test_function_multiple_args() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -756,7 +836,7 @@ fn test_array_position_assignment() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -770,7 +850,7 @@ fn test_typed_declare() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok())
 }
 
@@ -782,7 +862,7 @@ fn test_no_function_args_without_type() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_err())
 }
 
@@ -794,11 +874,79 @@ fn test_function_with_return_type() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
     assert_eq!(tree.unwrap().func[0].ret_type, Some(Type::Int));
 }
 
+#[test]
+fn test_inferred_return_types() {
+    let raw = "struct Point {
+        x: int
+        fn describe() {
+            if self.x > 0 {
+                return \"positive\"
+            }
+            return \"other\"
+        }
+    }
+    fn factorial(n: int) {
+        if n < 2 {
+            return 1
+        }
+        return n * factorial(n - 1)
+    }
+    fn is_even(n: int) {
+        if n == 0 {
+            return true
+        }
+        return is_odd(n - 1)
+    }
+    fn is_odd(n: int) {
+        if n == 0 {
+            return false
+        }
+        return is_even(n - 1)
+    }
+    fn twice(n: int) {
+        return double(n)
+    }
+    fn double(n: int) {
+        while true {
+            match n {
+                0 => return 0
+                else => return n * 2
+            }
+        }
+    }
+    fn main() {
+        let p = new Point { x: 1 }
+        p.describe()
+        factorial(5)
+        is_even(4)
+        twice(3)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+    let ret_types: Vec<(&str, Option<Type>)> = tree
+        .func
+        .iter()
+        .map(|f| (f.name.as_str(), f.ret_type.clone()))
+        .collect();
+    assert_eq!(
+        ret_types,
+        vec![
+            ("factorial", Some(Type::Int)),
+            ("is_even", Some(Type::Bool)),
+            ("is_odd", Some(Type::Bool)),
+            ("twice", Some(Type::Int)),
+            ("double", Some(Type::Int)),
+            ("main", None),
+        ]
+    );
+    assert_eq!(tree.structs[0].methods[0].ret_type, Some(Type::Str));
+}
+
 #[test]
 fn test_booleans_in_function_call() {
     let raw = "
@@ -811,7 +959,7 @@ fn test_booleans_in_function_call() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -831,7 +979,7 @@ fn test_late_initializing_variable() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -847,7 +995,7 @@ fn test_simple_for_loop() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -865,7 +1013,7 @@ fn test_nested_for_loop() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -883,7 +1031,7 @@ fn test_nested_array() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -896,7 +1044,7 @@ fn test_simple_nested_expression() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -916,7 +1064,7 @@ fn test_continue() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -936,7 +1084,7 @@ fn test_break() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -954,7 +1102,7 @@ fn test_complex_nested_expressions() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -966,7 +1114,7 @@ fn test_array_as_argument() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -988,7 +1136,7 @@ fn test_struct_initialization() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -996,28 +1144,28 @@ fn test_struct_initialization() {
 fn test_arithmetic() {
     // These should pass
     let raw = "fn main() {1*1}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_ok());
 
     let raw = "fn main() {2+3*4}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_ok());
 
     let raw = "fn main() {(2+2)*3}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_ok());
 
     // These should fail
     let raw = "fn main() {(22+)+1}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_err());
 
     let raw = "fn main() {1++1}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_err());
 
     let raw = "fn main() {3)+1}";
-    let tree = parse(tokenize(raw).unwrap(), Some(raw.to_string()));
+    let tree = parse(tokenize(raw).unwrap());
     assert!(tree.is_err());
 }
 
@@ -1032,7 +1180,7 @@ fn test_array_capacity() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok());
 }
 
@@ -1043,7 +1191,7 @@ fn test_errors_for_struct_decl() {
         value: int
     }";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_err());
 }
 
@@ -1054,7 +1202,7 @@ fn test_errors_for_struct_literal() {
         value: 200
     }";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_err());
 }
 
@@ -1066,7 +1214,7 @@ fn test_array_access() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
     assert!(tree.is_ok(), "{:?}", tree.err());
 }
 
@@ -1078,7 +1226,56 @@ fn test_nested_array_access() {
     }
     ";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string()));
+    let tree = parse(tokens);
+    assert!(tree.is_ok(), "{:?}", tree.err());
+}
+
+#[test]
+fn test_multi_dimensional_array_type() {
+    let raw = "
+    fn main() {
+        let grid: int[][] = [[1, 2], [3, 4]]
+        let fixed: int[2][3]
+    }
+    ";
+    let tokens = tokenize(raw).unwrap();
+    let tree = parse(tokens);
+    assert!(tree.is_ok(), "{:?}", tree.err());
+}
+
+#[test]
+fn test_nested_array_assignment() {
+    let raw = "
+    fn main() {
+        let grid = [[1, 2], [3, 4]]
+        grid[1][0] = 5
+    }
+    ";
+    let tokens = tokenize(raw).unwrap();
+    let tree = parse(tokens).unwrap();
+
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected block body");
+    };
+    let HStatementKind::Assign { lhs, .. } = &statements[1].kind else {
+        panic!("expected assignment");
+    };
+    let HExpressionKind::ArrayAccess { expr, .. } = &lhs.kind else {
+        panic!("expected array access on the left hand side");
+    };
+    assert!(matches!(&expr.kind, HExpressionKind::ArrayAccess { .. }));
+}
+
+#[test]
+fn test_field_array_access() {
+    let raw = "
+    fn main() {
+        let first = user.tags[0]
+        user.tags[1] = first
+    }
+    ";
+    let tokens = tokenize(raw).unwrap();
+    let tree = parse(tokens);
     assert!(tree.is_ok(), "{:?}", tree.err());
 }
 
@@ -1087,20 +1284,20 @@ fn test_operator_precedence_modulo_equals() {
     // `x % 3 == 0` must parse as `(x % 3) == 0`, not `x % (3 == 0)`
     let raw = "fn main() { let x = 6\n if x % 3 == 0 { return 1 } }";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string())).unwrap();
+    let tree = parse(tokens).unwrap();
 
-    let HStatement::Block { statements, .. } = &tree.func[0].body else {
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
         panic!("expected block body");
     };
     // statements[0] = Declare x, statements[1] = If
-    let HStatement::If { condition, .. } = &statements[1] else {
+    let HStatementKind::If { condition, .. } = &statements[1].kind else {
         panic!("expected if statement");
     };
-    let HExpression::BinOp { op, lhs, .. } = condition else {
+    let HExpressionKind::BinOp { op, lhs, .. } = &condition.kind else {
         panic!("expected binop condition");
     };
     assert_eq!(*op, HBinOp::Equal, "outer op should be ==");
-    let HExpression::BinOp { op: inner_op, .. } = lhs.as_ref() else {
+    let HExpressionKind::BinOp { op: inner_op, .. } = &lhs.kind else {
         panic!("lhs should be a binop (x % 3)");
     };
     assert_eq!(*inner_op, HBinOp::Modulus, "inner op should be %");
@@ -1111,13 +1308,13 @@ fn test_wildcard_match_arm_parsed_as_else() {
     // `_` in a match arm must be treated as a catch-all else branch
     let raw = "fn main() { let x = 1\n match x { 1 => return 1\n _ => return 0 } }";
     let tokens = tokenize(raw).unwrap();
-    let tree = parse(tokens, Some(raw.to_string())).unwrap();
+    let tree = parse(tokens).unwrap();
 
-    let HStatement::Block { statements, .. } = &tree.func[0].body else {
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
         panic!("expected block body");
     };
     // statements[0] = Declare x, statements[1] = Match
-    let HStatement::Match { arms, .. } = &statements[1] else {
+    let HStatementKind::Match { arms, .. } = &statements[1].kind else {
         panic!("expected match statement");
     };
     assert_eq!(arms.len(), 2, "expected 2 match arms");
@@ -1127,3 +1324,1667 @@ fn test_wildcard_match_arm_parsed_as_else() {
         arms[1]
     );
 }
+
+#[test]
+fn test_type_alias_resolves_to_target() {
+    let raw = "
+    type UserId = int
+
+    fn main() {
+        let id: UserId = 5
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    crate::parser::infer_types(&mut tree).unwrap();
+
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected block body");
+    };
+    let HStatementKind::Declare { variable, .. } = &statements[0].kind else {
+        panic!("expected declaration");
+    };
+    assert_eq!(variable.ty, Some(Type::Int));
+}
+
+#[test]
+fn test_newtype_conversion() {
+    let raw = "
+    newtype Meters = int
+
+    fn main() {
+        let d = Meters(5) + Meters(3)
+        let raw: int = int(d)
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    crate::parser::infer_types(&mut tree).unwrap();
+
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected block body");
+    };
+    let HStatementKind::Declare { variable, .. } = &statements[0].kind else {
+        panic!("expected declaration");
+    };
+    assert_eq!(
+        variable.ty,
+        Some(Type::Newtype("Meters".into(), Box::new(Type::Int)))
+    );
+}
+
+#[test]
+fn test_builtin_conversions_are_known_names() {
+    let raw = "
+    newtype Meters = int
+
+    fn main() {
+        let n = Meters(5)
+        let raw = int(n)
+        let text = string(raw)
+        let flag = bool(raw)
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+}
+
+#[test]
+fn test_newtype_is_not_mixed_with_underlying_type() {
+    let sources = [
+        (
+            "newtype Meters = int\n fn main() { let d: Meters = 5 }",
+            "Mismatched types: expected `Meters`, got `int`",
+        ),
+        (
+            "newtype Meters = int\n fn main() { let d = Meters(5) + 1 }",
+            "Mismatched types: expected `Meters`, got `int`",
+        ),
+        (
+            "newtype Meters = int\n fn main() { let x: int = Meters(5) }",
+            "Mismatched types: expected `int`, got `Meters`",
+        ),
+        (
+            "newtype Meters = int\n fn walk(d: Meters) {}\n fn main() { walk(5) }",
+            "Mismatched types: expected `Meters`, got `int`",
+        ),
+        (
+            "newtype Meters = int\n fn len(): Meters { return 5 }",
+            "Mismatched types: expected `Meters`, got `int`",
+        ),
+        (
+            "newtype Meters = int\n fn main() { let d = Meters([1, 2]) }",
+            "Cannot cast `int[2]` to `Meters`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_recursive_type_alias() {
+    let raw = "type A = B\n type B = A[]\n fn main() { let x: A }";
+    assert_eq!(first_error(raw), "Type `A` is defined in terms of itself");
+}
+
+#[test]
+fn test_integer_literal_suffix() {
+    let raw = "fn main() { let x = 255u8 }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    crate::parser::infer_types(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    let HStatementKind::Declare {
+        variable, value, ..
+    } = &statements[0].kind
+    else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(variable.ty, Some(Type::U8));
+    let Some(HExpression {
+        kind: HExpressionKind::Cast { expr, from, to },
+        ..
+    }) = value
+    else {
+        panic!("expected a cast, got {:?}", value);
+    };
+    assert_eq!(expr.kind, HExpressionKind::Int(255));
+    assert_eq!(from, &Some(Type::Int));
+    assert_eq!(to, &Type::U8);
+}
+
+#[test]
+fn test_integer_literal_out_of_range() {
+    let sources = [
+        (
+            "fn main() { let x = 256u8 }",
+            "Literal `256` does not fit into `u8`",
+        ),
+        (
+            "fn main() { let x = 128i8 }",
+            "Literal `128` does not fit into `i8`",
+        ),
+        ("fn main() { let x = 5u7 }", "Invalid integer suffix `u7`"),
+        (
+            "fn main() { let x: u16 = 65536 }",
+            "Literal `65536` does not fit into `u16`",
+        ),
+        (
+            "fn main() { let x: i8[] = [1, 200] }",
+            "Literal `200` does not fit into `i8`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_sized_integers_are_not_mixed() {
+    let sources = [
+        (
+            "fn main() { let x: i64 = 5\n let y: int = x }",
+            "Mismatched types: expected `int`, got `i64`",
+        ),
+        (
+            "fn main() { let a = 1u8\n let b = 1u16\n let c = a + b }",
+            "Mismatched types: expected `u8`, got `u16`",
+        ),
+        (
+            "fn half(x: u32): u32 { return x / 2 }\n fn main() { let x: int = 4\n half(x) }",
+            "Mismatched types: expected `u32`, got `int`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_sized_integer_conversions() {
+    let raw = "fn main() {
+        let big: i64 = 5000000000
+        let small = big as u8 + 1
+        let text = i16(small) as string
+        let flag = u64(1) as bool
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    assert!(crate::parser::infer_types(&mut tree).is_ok());
+}
+
+#[test]
+fn test_method_call_syntax_on_builtin_types() {
+    let raw = "fn str_len(s: string): int { return 0 }
+    fn contains(arr: int[], x: int): bool { return true }
+    fn main() {
+        let n = \"abc\".len()
+        let found = [1, 2].contains(2)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    crate::parser::infer_types(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[2].body.kind else {
+        panic!("expected a block");
+    };
+    let calls: Vec<(&str, usize)> = statements
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            HStatementKind::Declare {
+                value:
+                    Some(HExpression {
+                        kind: HExpressionKind::FunctionCall { fn_name, args, .. },
+                        ..
+                    }),
+                ..
+            } => (fn_name.as_str(), args.len()),
+            other => panic!("expected a function call, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(calls, vec![("str_len", 1), ("contains", 2)]);
+}
+
+#[test]
+fn test_method_call_syntax_without_matching_function() {
+    let sources = [
+        ("fn main() { let n = 5\n n.len() }", "No function `len` can be called on `int`"),
+        ("fn len(arr: int[]): int { return 0 }\n fn main() { let n = true\n n.len() }", "No function `len` can be called on `bool`"),
+        ("newtype Meters = int\n fn int_double(n: int): int { return n }\n fn main() { let m = Meters(2)\n m.double() }", "No function `double` can be called on `Meters`"),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_struct_embedding() {
+    let raw = "
+    struct User {
+        name: string
+        fn greet(): string { return self.name }
+    }
+    struct AdminUser {
+        User
+        level: int
+    }
+    fn main(admin: AdminUser) {
+        let name = admin.name
+        let greeting = admin.greet()
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    assert_eq!(tree.structs[1].embeds, vec!["User".to_string()]);
+    assert_eq!(
+        tree.structs[1].fields[0].ty,
+        Some(Type::Struct("User".to_string()))
+    );
+
+    crate::parser::infer_types(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    for stmt in statements {
+        match &stmt.kind {
+            HStatementKind::Declare {
+                variable,
+                value:
+                    Some(HExpression {
+                        kind: HExpressionKind::FieldAccess { expr, .. },
+                        ..
+                    }),
+                ..
+            } => {
+                let HExpressionKind::FieldAccess { expr, field } = &expr.kind else {
+                    panic!("expected `admin.User`, got {:?}", expr);
+                };
+                assert_eq!(expr.kind, HExpressionKind::Variable("admin".into()));
+                assert_eq!(field.kind, HExpressionKind::Variable("User".into()));
+                assert_eq!(variable.ty, Some(Type::Str));
+            }
+            other => panic!("expected a field access, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_struct_embedding_errors() {
+    let sources = [
+        // Only structs can be embedded
+        ("struct Foo {\n int\n }", "Unknown type `int`"),
+        // A struct cannot contain itself
+        (
+            "struct A {\n B\n }\n struct B {\n A\n }",
+            "Struct `A` embeds itself",
+        ),
+        // Two embedded structs at the same depth both have `id`
+        (
+            "struct A {\n id: int\n }\n struct B {\n id: int\n }
+        struct C {\n A\n B\n }
+        fn main(c: C) {\n let id = c.id\n }",
+            "`id` is ambiguous on struct `C`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_default_and_named_arguments() {
+    let raw = "
+    fn open(path: string, mode: string = \"r\", create: bool = false): int { return 0 }
+    fn main() {
+        let a = open(\"a.txt\")
+        let b = open(\"b.txt\", create: true)
+        let c = open(path: \"c.txt\", mode: \"w\")
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    assert_eq!(
+        tree.func[0].arguments[1].default.as_ref().map(|d| &d.kind),
+        Some(&HExpressionKind::Str("r".into()))
+    );
+
+    crate::parser::infer_types(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[1].body.kind else {
+        panic!("expected a block");
+    };
+    let calls: Vec<Vec<HExpressionKind>> = statements
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            HStatementKind::Declare {
+                value:
+                    Some(HExpression {
+                        kind: HExpressionKind::FunctionCall { args, named, .. },
+                        ..
+                    }),
+                ..
+            } => {
+                assert!(named.is_empty());
+                args.iter().map(|arg| arg.kind.clone()).collect()
+            }
+            other => panic!("expected a function call, got {:?}", other),
+        })
+        .collect();
+    let str_arg = |s: &str| HExpressionKind::Str(s.into());
+    assert_eq!(
+        calls,
+        vec![
+            vec![str_arg("a.txt"), str_arg("r"), HExpressionKind::Bool(false)],
+            vec![str_arg("b.txt"), str_arg("r"), HExpressionKind::Bool(true)],
+            vec![str_arg("c.txt"), str_arg("w"), HExpressionKind::Bool(false)],
+        ]
+    );
+}
+
+#[test]
+fn test_named_argument_errors() {
+    let sources = [
+        // Unknown parameter name
+        (
+            "fn f(a: int) {}\n fn main() { f(1, b: 2) }",
+            "`f` has no parameter named `b`",
+        ),
+        // Passed both positionally and by name
+        (
+            "fn f(a: int) {}\n fn main() { f(1, a: 2) }",
+            "Argument `a` of `f` is given more than once",
+        ),
+        // Missing argument without a default
+        (
+            "fn f(a: int, b: int = 1) {}\n fn main() { f(b: 2) }",
+            "Missing argument `a` in call to `f`",
+        ),
+        // Too many arguments
+        (
+            "fn f(a: int) {}\n fn main() { f(1, 2) }",
+            "`f` takes 1 argument, but 2 were given",
+        ),
+        // Default of the wrong type
+        (
+            "fn f(a: int = \"x\") {}\n fn main() { f() }",
+            "Mismatched types: expected `int`, got `string`",
+        ),
+        // Default that is not a literal
+        (
+            "fn f(a: int, b: int = a) {}\n fn main() { f(1) }",
+            "Unknown variable `a`",
+        ),
+        // Parameter without a default after one with a default
+        (
+            "fn f(a: int = 1, b: int) {}\n fn main() { f(b: 1) }",
+            "Parameter `b` needs a default value, because it follows `a` which has one",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+
+    // Positional arguments cannot follow named ones
+    let raw = "fn f(a: int, b: int) {}\n fn main() { f(a: 1, 2) }";
+    assert_eq!(
+        first_error(raw),
+        "Positional arguments must come before named arguments"
+    );
+
+    // Every named argument that does not fit is reported
+    let raw = "fn f(a: int, b: int = 1) {}
+    fn g(a: ...int) {}
+    fn main() {
+        f(1, c: 2, a: 3)
+        g(a: 1)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("2", "`f` has no parameter named `c`"),
+            ("3", "Argument `a` of `f` is given more than once"),
+            (
+                "1",
+                "Variadic parameter `a` of `g` cannot be passed by name"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_call_arguments() {
+    let raw = "fn clamp(v: int, lo: int, hi: int): int {
+        return v
+    }
+    fn main() {
+        clamp(1, 2)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![("clamp(1, 2)", "Missing argument `hi` in call to `clamp`")]
+    );
+    assert_eq!(
+        secondary(raw, &errors[0]),
+        vec![("hi: int", "parameter without a default value")]
+    );
+
+    let raw = "struct Point {
+        x: int
+        fn shift(by: int): int {
+            return self.x + by
+        }
+    }
+    fn main() {
+        let p = new Point { x: 1 }
+        p.shift(1, 2)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![(
+            "p.shift(1, 2)",
+            "`Point.shift` takes 1 argument, but 2 were given"
+        )]
+    );
+    assert_eq!(
+        secondary(raw, &errors[0]),
+        vec![(
+            "fn shift(by: int): int {\n            return self.x + by\n        }",
+            "defined here"
+        )]
+    );
+
+    let raw = "struct Point {
+        x: int
+        fn shift(by: int): int {
+            return self.x + by
+        }
+    }
+    fn scale(value: int, factor: int): int {
+        return value * factor
+    }
+    fn main() {
+        let p = new Point { x: 1 }
+        p.shift(\"1\")
+        scale(1, scale(true, 2))
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("\"1\"", "Mismatched types: expected `int`, got `string`"),
+            ("true", "Mismatched types: expected `int`, got `bool`"),
+        ]
+    );
+    assert_eq!(
+        secondary(raw, &errors[0]),
+        vec![("by: int", "parameter of `Point.shift`")]
+    );
+    assert_eq!(
+        secondary(raw, &errors[1]),
+        vec![("value: int", "parameter of `scale`")]
+    );
+
+    // Every call with wrong arguments is reported, in all functions
+    let raw = "struct S {
+        fn m(a: int) {}
+    }
+    fn f(a: int, b: int = 1) {}
+    fn main() {
+        let s = new S {}
+        f()
+        f(1, 2, 3, 4)
+        s.m(1, 2)
+    }
+    fn other() {
+        _printf()
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("f()", "Missing argument `a` in call to `f`"),
+            ("f(1, 2, 3, 4)", "`f` takes 2 arguments, but 4 were given"),
+            ("s.m(1, 2)", "`S.m` takes 1 argument, but 2 were given"),
+            ("_printf()", "`_printf` takes 1 argument, but 0 were given"),
+        ]
+    );
+}
+
+#[test]
+fn test_builtin_arguments() {
+    let raw = "fn main() {
+        _printf(1, 2)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![(
+            "_printf(1, 2)",
+            "`_printf` takes 1 argument, but 2 were given"
+        )]
+    );
+    assert_eq!(errors[0].code, Some(codes::ARGUMENTS));
+
+    let raw = "fn main() {
+        _printf(1)
+        _str_substr(\"abc\", 0, \"1\")
+        _unknown(1, 2)
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("1", "Mismatched types: expected `string`, got `int`"),
+            ("\"1\"", "Mismatched types: expected `int`, got `string`"),
+        ]
+    );
+}
+
+#[test]
+fn test_variadic_arguments() {
+    let raw = "
+    fn count(label: string, items: ...int): int { return 0 }
+    fn show(values: ...any) {}
+    fn println(args: ...string) {}
+    fn main() {
+        count(\"none\")
+        count(\"two\", 1, 2)
+        show(\"x\", 1, true)
+        println(\"x\", 1, true)
+    }
+    ";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    assert!(tree.func[0].arguments[1].variadic);
+    assert_eq!(
+        tree.func[0].arguments[1].ty,
+        Some(Type::Array(Box::new(Type::Int), None))
+    );
+
+    crate::parser::infer_types(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[3].body.kind else {
+        panic!("expected a block");
+    };
+    // Casts are listed as (operand, type it is cast to)
+    let rest: Vec<Vec<(HExpressionKind, Option<Type>)>> = statements
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            HStatementKind::Exp(HExpression {
+                kind: HExpressionKind::FunctionCall { args, .. },
+                ..
+            }) => match args.last().map(|arg| &arg.kind) {
+                Some(HExpressionKind::Array { elements, .. }) => elements
+                    .iter()
+                    .map(|element| match &element.kind {
+                        HExpressionKind::Cast { expr, to, .. } => {
+                            (expr.kind.clone(), Some(to.clone()))
+                        }
+                        other => (other.clone(), None),
+                    })
+                    .collect(),
+                other => panic!("expected the rest to be an array, got {:?}", other),
+            },
+            other => panic!("expected a function call, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        rest,
+        vec![
+            vec![],
+            vec![
+                (HExpressionKind::Int(1), None),
+                (HExpressionKind::Int(2), None)
+            ],
+            // Every value passed as `any` is converted to it
+            vec![
+                (HExpressionKind::Str("x".into()), Some(Type::Any)),
+                (HExpressionKind::Int(1), Some(Type::Any)),
+                (HExpressionKind::Bool(true), Some(Type::Any)),
+            ],
+            // Values passed to `...string` are converted to strings
+            vec![
+                (HExpressionKind::Str("x".into()), None),
+                (HExpressionKind::Int(1), Some(Type::Str)),
+                (HExpressionKind::Bool(true), Some(Type::Str)),
+            ],
+        ]
+    );
+}
+
+#[test]
+fn test_variadic_argument_errors() {
+    let sources = [
+        // Only the last parameter can be variadic
+        ("fn f(a: ...int, b: int) {}\n fn main() { f(1) }", "Variadic parameter `a` must be the last parameter"),
+        // A variadic parameter has no default
+        ("fn f(a: ...int = [1]) {}\n fn main() { f() }", "Variadic parameter `a` cannot have a default value"),
+        // A variadic parameter cannot be passed by name
+        ("fn f(a: ...int) {}\n fn main() { f(a: 1) }", "Variadic parameter `a` of `f` cannot be passed by name"),
+        // Elements have to match the element type
+        ("fn f(a: ...int) {}\n fn main() { f(1, \"two\") }", "Mismatched types: expected `int`, got `string`"),
+        // `...string` only takes values that can be cast to a string
+        (
+            "struct P {\n x: int\n }\n fn show(a: ...string) {}\n fn main() { show(new P { x: 1 }) }",
+            "Cannot convert `P` to `string`",
+        ),
+        // The type of an `any` value is not known at runtime
+        (
+            "fn show(a: ...string) {}\n fn log(a: any) { show(a) }",
+            "Cannot convert `any` to `string`",
+        ),
+        (
+            "fn log(a: any) { _printf(a) }",
+            "Cannot pass a value of type `any` to `_printf`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_type_errors() {
+    let sources = [
+        // Operands
+        (
+            "fn main() { let x = \"a\" - 1 }",
+            "Operator `-` cannot be applied to `string`",
+        ),
+        (
+            "fn main() { let x = \"a\" * \"b\" }",
+            "Operator `*` cannot be applied to `string`",
+        ),
+        (
+            "fn main() { let x = true + false }",
+            "Operator `+` cannot be applied to `bool`",
+        ),
+        (
+            "fn main() { let x = 1 && true }",
+            "Operator `&&` cannot be applied to `int`",
+        ),
+        (
+            "fn main() { let x = 1 + \"a\" }",
+            "Mismatched types: expected `int`, got `string`",
+        ),
+        (
+            "fn main() { let x = \"a\" < \"b\" }",
+            "Operator `<` cannot be applied to `string`",
+        ),
+        // Call arguments
+        (
+            "fn f(a: int) {}\n fn main() { f(\"a\") }",
+            "Mismatched types: expected `int`, got `string`",
+        ),
+        // Return values
+        (
+            "fn f(): int { return \"a\" }",
+            "Mismatched types: expected `int`, got `string`",
+        ),
+        // Assignments
+        (
+            "fn main() { let x = 1\n x = \"a\" }",
+            "Mismatched types: expected `int`, got `string`",
+        ),
+        // Conditions
+        (
+            "fn main() { if \"yes\" {} }",
+            "Condition must be a `bool` or an integer, got `string`",
+        ),
+        (
+            "fn main() { while [1] {} }",
+            "Condition must be a `bool` or an integer, got `int[1]`",
+        ),
+        // Indexing and members
+        (
+            "fn main() { let x = 1\n let y = x[0] }",
+            "Cannot index into `int`",
+        ),
+        (
+            "fn main() { let x = [1]\n let y = x[\"a\"] }",
+            "Array index must be an integer, got `string`",
+        ),
+        (
+            "struct P {\n x: int\n }\n fn main() { let p = new P { x: 1 }\n let y = p.y }",
+            "Struct `P` has no field `y`",
+        ),
+        (
+            "struct P {\n x: int\n }\n fn main() { let p = new P { y: 1 } }",
+            "Struct `P` has no field `y`",
+        ),
+        (
+            "fn main() { let x = \"yes\" as bool }",
+            "Cannot cast `string` to `bool`",
+        ),
+    ];
+    for (raw, message) in sources {
+        assert_eq!(first_error(raw), message, "for: {}", raw);
+    }
+}
+
+#[test]
+fn test_type_errors_are_all_reported() {
+    let raw = "fn f(): int { return \"a\" }
+    fn main() {
+        let x: int = true
+        if \"yes\" {}
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("\"a\"", "Mismatched types: expected `int`, got `string`"),
+            ("true", "Mismatched types: expected `int`, got `bool`"),
+            (
+                "\"yes\"",
+                "Condition must be a `bool` or an integer, got `string`"
+            ),
+        ]
+    );
+    assert_eq!(errors[0].code, Some(codes::MISMATCHED_TYPES));
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("use `as int` to convert explicitly")
+    );
+}
+
+#[test]
+fn test_unknown_names() {
+    let raw = "struct Point {
+        x: int
+        y: int
+    }
+    fn distance(p: Point): int {
+        return p.x + p.y
+    }
+    fn main() {
+        let count = 1
+        if true {
+            let inner = 2
+        }
+        let a = cout + inner
+        let p = new Pont { x: 1 y: 2 }
+        distanse(p)
+        let o: Origin
+    }";
+    let tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = crate::parser::resolve_names(&tree, &[]).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("cout", "Unknown variable `cout`"),
+            ("inner", "Unknown variable `inner`"),
+            ("new Pont { x: 1 y: 2 }", "Unknown struct `Pont`"),
+            ("distanse(p)", "Unknown function `distanse`"),
+            ("o: Origin", "Unknown type `Origin`"),
+        ]
+    );
+    let help: Vec<Option<&str>> = errors.iter().map(|e| e.help.as_deref()).collect();
+    assert_eq!(
+        help,
+        vec![
+            Some("did you mean `count`?"),
+            None,
+            Some("did you mean `Point`?"),
+            Some("did you mean `distance`?"),
+            None,
+        ]
+    );
+    // Only names whose exact location is known are replaced
+    assert_eq!(
+        suggested(raw, &errors),
+        vec![
+            Some(("cout", "count")),
+            None,
+            None,
+            Some(("distanse", "distance")),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_names_in_standard_library() {
+    let raw = "fn main() {\n println(\"Hello\")\n _printf(\"Hello\")\n }";
+    let tree = parse(tokenize(raw).unwrap()).unwrap();
+    let stdlib = vec!["println".to_string()];
+    let errors = crate::parser::resolve_names(&tree, &stdlib).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![("println(\"Hello\")", "Unknown function `println`")]
+    );
+    assert_eq!(
+        errors[0].notes,
+        vec!["`println` is part of the standard library, which is not available for this target"]
+    );
+}
+
+#[test]
+fn test_unknown_members() {
+    let raw = "struct Point {
+        x: int
+        y: int
+        fn length(): int {
+            return self.x
+        }
+    }
+    fn main() {
+        let p = new Point { x: 1 y: 2 }
+        let a = p.z
+        let b = p.lenght()
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("z", "Struct `Point` has no field `z`"),
+            ("lenght()", "Struct `Point` has no method `lenght`"),
+        ]
+    );
+    let help: Vec<Option<&str>> = errors.iter().map(|e| e.help.as_deref()).collect();
+    assert_eq!(
+        help,
+        vec![Some("did you mean `x`?"), Some("did you mean `length`?")]
+    );
+    assert_eq!(
+        suggested(raw, &errors),
+        vec![Some(("z", "x")), Some(("lenght", "length"))]
+    );
+}
+
+#[test]
+fn test_missing_returns() {
+    let raw = "fn sign(x: int): int {
+        if x > 0 {
+            return 1
+        } else if x < 0 {
+            return 2
+        }
+    }
+    fn first(): int {
+        while true {
+            return 1
+        }
+    }
+    fn pick(x: int): int {
+        match x {
+            1 => return 1
+            else => return 0
+        }
+    }
+    fn count(): int {
+        for i in [1, 2] {
+            return i
+        }
+    }
+    fn main() {}";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Function `sign` does not return a value in all code paths",
+            "Function `count` does not return a value in all code paths",
+        ]
+    );
+    assert!(errors.iter().all(|e| e.code == Some(codes::MISSING_RETURN)));
+}
+
+#[test]
+fn test_shadowing_with_another_type() {
+    let raw = "fn show(s: string) {}
+    fn main() {
+        let x = \"s\"
+        if true {
+            let x = 5
+            let inner = x + 1
+        }
+        for x in [true] {
+            let flag = x
+        }
+        show(x)
+        let y = x
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+    let HStatementKind::Block { statements, .. } = &tree.func[1].body.kind else {
+        panic!("expected a block");
+    };
+    // The variable of the outer block keeps its type after the inner ones
+    let Some(HStatementKind::Declare { variable, .. }) = statements.last().map(|s| &s.kind) else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(variable.ty, Some(Type::Str));
+}
+
+#[test]
+fn test_rename_shadowed() {
+    let raw = "fn sum(a: int): int = a
+    fn main() {
+        let x = 1
+        if true {
+            let x = x + 1
+            sum(x)
+        }
+        let x = x + 2
+        for x in [x] {
+            sum(x)
+        }
+        let sum = x
+        let x_1 = sum
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+    crate::parser::rename_shadowed(&mut tree);
+
+    /// The variables that are declared (`let x`) and read (`x`), in order
+    fn names(stmt: &HStatement, out: &mut Vec<String>) {
+        fn reads(expr: &HExpression, out: &mut Vec<String>) {
+            match &expr.kind {
+                HExpressionKind::Variable(name) => out.push(name.clone()),
+                HExpressionKind::BinOp { lhs, rhs, .. } => {
+                    reads(lhs, out);
+                    reads(rhs, out);
+                }
+                HExpressionKind::Array { elements: args, .. }
+                | HExpressionKind::FunctionCall { args, .. } => {
+                    args.iter().for_each(|arg| reads(arg, out))
+                }
+                _ => {}
+            }
+        }
+        match &stmt.kind {
+            HStatementKind::Block { statements, .. } => {
+                statements.iter().for_each(|s| names(s, out))
+            }
+            HStatementKind::Declare {
+                variable, value, ..
+            } => {
+                value.iter().for_each(|value| reads(value, out));
+                out.push(format!("let {}", variable.name));
+            }
+            HStatementKind::If { body, .. } => names(body, out),
+            HStatementKind::For { ident, expr, body } => {
+                reads(expr, out);
+                out.push(format!("for {}", ident.name));
+                names(body, out);
+            }
+            HStatementKind::Exp(expr) => reads(expr, out),
+            _ => {}
+        }
+    }
+    let mut renamed = Vec::new();
+    names(&tree.func[1].body, &mut renamed);
+    assert_eq!(
+        renamed,
+        vec![
+            "let x",
+            "x",
+            "let x_2",
+            "x_2",
+            "x",
+            "let x_3",
+            "x_3",
+            "for x_4",
+            "x_4",
+            "x_3",
+            "let sum_1",
+            "sum_1",
+            "let x_1",
+        ]
+    );
+    let HStatementKind::Block { scope, .. } = &tree.func[1].body.kind else {
+        panic!("expected a block");
+    };
+    let scope: Vec<&str> = scope.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(scope, vec!["x", "x_3", "sum_1", "x_1"]);
+}
+
+#[test]
+fn test_unassigned_variables() {
+    let raw = "fn main() {
+        let a: int
+        let b: int
+        let c: int
+        let mut d: int[2]
+        if true {
+            a = 1
+            b = 1
+        } else {
+            a = 2
+        }
+        while true {
+            c = 1
+            break
+        }
+        d[0] = 1
+        let sum = a + b + c + b
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![("b", "Variable `b` is used before it is assigned")]
+    );
+    assert_eq!(errors[0].code, Some(codes::UNASSIGNED));
+}
+
+#[test]
+fn test_mutable_declarations() {
+    let raw = "struct Counter {
+        value: int
+        fn get(self): int = self.value
+        fn add(mut self, amount: int) {}
+    }
+    fn main() {
+        let a = 1
+        let mut b = 2
+        var c = 3
+    }";
+    let tree = parse(tokenize(raw).unwrap()).unwrap();
+    let methods = &tree.structs[0].methods;
+    assert!(!methods[0].mutable_self);
+    assert!(methods[1].mutable_self);
+    assert_eq!(methods[1].arguments.len(), 1);
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    let mutable: Vec<bool> = statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            HStatementKind::Declare { mutable, .. } => Some(*mutable),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(mutable, vec![false, true, true]);
+
+    let raw = "fn area(self): int = 1";
+    let errors = parse(tokenize(raw).unwrap()).unwrap_err();
+    assert_eq!(errors[0].message, "Only methods can take `self`");
+}
+
+#[test]
+fn test_immutable_assignments() {
+    let raw = "struct Counter {
+        value: int
+        fn add(mut self, amount: int) {
+            self.value += amount
+        }
+        fn reset() {
+            self.value = 0
+        }
+    }
+    fn bump(counter: Counter, n: int) {
+        n = 1
+        counter.value = 2
+    }
+    fn main() {
+        let a = 1
+        a = 2
+        let b: int
+        if true {
+            b = 1
+        } else {
+            b = 2
+        }
+        let c: int
+        while true {
+            c = 1
+            break
+        }
+        let d: int
+        while b < 3 {
+            d = 1
+        }
+        let counter = new Counter { value: 0 }
+        counter.add(1)
+        let mut other = new Counter { value: 0 }
+        other.add(1)
+        other.value -= 1
+        for i in [1, 2] {
+            i += 1
+        }
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("n = 1", "Cannot assign to parameter `n`"),
+            (
+                "counter.value = 2",
+                "Cannot assign to a field of parameter `counter`"
+            ),
+            ("a = 2", "Cannot assign twice to immutable variable `a`"),
+            ("d = 1", "Cannot assign twice to immutable variable `d`"),
+            (
+                "add(1)",
+                "Cannot call `mut self` method `add` on immutable variable `counter`"
+            ),
+            ("i += 1", "Cannot assign to loop variable `i`"),
+            (
+                "self.value = 0",
+                "Cannot assign to a field of `self` in a method that does not take `mut self`"
+            ),
+        ]
+    );
+    assert!(errors.iter().all(|e| e.code == Some(codes::IMMUTABLE)));
+    assert_eq!(secondary(raw, &errors[2]), vec![("a", "declared here")]);
+    assert_eq!(
+        errors[2].help.as_deref(),
+        Some("make the variable mutable: `let mut a`")
+    );
+}
+
+#[test]
+fn test_duplicate_definitions() {
+    let files = [
+        "struct Point {\n    x: int\n    x: int\n    fn len(): int {\n        return 0\n    }\n    fn len(): int {\n        return 1\n    }\n}\nfn clamp(v: int): int {\n    return v\n}\nfn main() {}",
+        "type Point = int\nfn clamp(v: int): int {\n    return v\n}",
+    ];
+    let mut program: Option<HModule> = None;
+    for (i, raw) in files.iter().enumerate() {
+        let (module, errors) = parse_file(tokenize(raw).unwrap(), FileId(i), 20);
+        assert!(errors.is_empty());
+        match &mut program {
+            Some(program) => program.merge_with(module),
+            None => program = Some(module),
+        }
+    }
+    let errors = crate::parser::check_duplicates(&program.unwrap()).unwrap_err();
+    let sites: Vec<(&str, usize, usize)> = errors
+        .iter()
+        .map(|e| {
+            let first = e.secondary[0].span;
+            let again = e.primary.as_ref().unwrap().span;
+            (e.message.as_str(), first.file.0, again.file.0)
+        })
+        .collect();
+    assert_eq!(
+        sites,
+        vec![
+            ("Function `clamp` is defined more than once", 0, 1),
+            ("Type `Point` is defined more than once", 0, 1),
+            (
+                "Field `x` of struct `Point` is defined more than once",
+                0,
+                0
+            ),
+            (
+                "Method `len` of struct `Point` is defined more than once",
+                0,
+                0
+            ),
+        ]
+    );
+    assert!(errors.iter().all(|e| e.code == Some(codes::DUPLICATE)));
+}
+
+#[test]
+fn test_array_element_types() {
+    let raw = "fn main() {
+        let mixed = [1, \"a\", true]
+        let nested = [[1], [\"a\"]]
+        let small: u8 = 3
+        let sizes = [1, small, 2]
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            (
+                "\"a\"",
+                "Mismatched types in array: expected `int`, got `string`"
+            ),
+            (
+                "[\"a\"]",
+                "Mismatched types in array: expected `int[1]`, got `string[1]`"
+            ),
+        ]
+    );
+    assert_eq!(
+        secondary(raw, &errors[0]),
+        vec![("1", "the array holds `int` because of this element")]
+    );
+}
+
+#[test]
+fn test_declared_array_element_types() {
+    let raw = "fn main() {
+        let anything: any[] = [1, \"Two\", 3]
+        let grid: any[][] = [[1, \"a\"], [true]]
+        let words: string[] = [7, \"a\"]
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![(
+            "7",
+            "Mismatched types in array: expected `string`, got `int`"
+        )]
+    );
+    assert!(secondary(raw, &errors[0]).is_empty());
+}
+
+#[test]
+fn test_empty_array_types() {
+    let raw = "fn main() {
+        let declared: string[] = []
+        let mut names = []
+        if true {
+            names[0] = \"a\"
+        }
+        let mut all = []
+        all = [true]
+    }";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+    let statements = match &tree.func[0].body.kind {
+        HStatementKind::Block { statements, .. } => statements,
+        _ => panic!("expected a block"),
+    };
+    let types: Vec<Option<Type>> = statements
+        .iter()
+        .filter_map(|s| match &s.kind {
+            HStatementKind::Declare { variable, .. } => Some(variable.ty.clone()),
+            _ => None,
+        })
+        .collect();
+    let array = |ty| Some(Type::Array(Box::new(ty), None));
+    assert_eq!(
+        types,
+        vec![
+            array(Type::Str),
+            array(Type::Str),
+            Some(Type::Array(Box::new(Type::Bool), Some(1)))
+        ]
+    );
+
+    let raw = "fn main() {\n    let names = []\n}";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![("names", "Cannot infer the type of the empty array `names`")]
+    );
+}
+
+#[test]
+fn test_spans() {
+    let raw =
+        "fn main() {\n    let total = 1 + 2\n    match total {\n        3 => total += 1\n    }\n}";
+    let tree = parse(tokenize(raw).unwrap()).unwrap();
+    let text = |span: Span| &raw[span.start..span.end];
+    assert_eq!(text(tree.func[0].span), raw);
+
+    let HStatementKind::Block { statements, .. } = &tree.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    assert_eq!(text(statements[0].span), "let total = 1 + 2");
+    let HStatementKind::Declare {
+        variable,
+        value: Some(value),
+        ..
+    } = &statements[0].kind
+    else {
+        panic!("expected a declaration");
+    };
+    assert_eq!(text(variable.span), "total");
+    assert_eq!(text(value.span), "1 + 2");
+    assert_eq!(
+        text(statements[1].span),
+        "match total {\n        3 => total += 1\n    }"
+    );
+
+    // Lowering keeps the spans, `match` becomes an `if` with the same span
+    let module = AstTransformer::transform_module(tree).unwrap();
+    let StatementKind::Block { statements, .. } = &module.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    assert_eq!(text(statements[0].span), "let total = 1 + 2");
+    let StatementKind::If { condition, .. } = &statements[1].kind else {
+        panic!("expected the match to be lowered to an if");
+    };
+    assert_eq!(
+        text(statements[1].span),
+        "match total {\n        3 => total += 1\n    }"
+    );
+    assert_eq!(text(condition.span), "3");
+}
+
+#[test]
+fn test_syntax_errors_are_all_reported() {
+    let raw = "fn main() {
+        let x = 1 +
+        let y = 2
+        if y > { println(y) }
+        println(x)
+    }
+    fn broken( {
+    }
+    fn ok(): int {
+        return 1
+    }";
+    let (module, errors) = parse_file(tokenize(raw).unwrap(), FileId::default(), 20);
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("let", "Expected expression, found `let`"),
+            ("{", "Expected expression, found `{`"),
+            ("{", "Expected `)`, found `{`"),
+        ]
+    );
+    assert!(errors.iter().all(|e| e.code == Some(codes::SYNTAX)));
+
+    // The definitions without errors are kept
+    let names: Vec<&str> = module.func.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["main", "ok"]);
+    let HStatementKind::Block { statements, .. } = &module.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn test_syntax_error_recovery_at_missing_brace() {
+    let raw = "fn main() {
+        let x = 1 +
+    struct Point {
+        x: int
+    }";
+    let (module, errors) = parse_file(tokenize(raw).unwrap(), FileId::default(), 20);
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![
+            ("struct", "Expected expression, found `struct`"),
+            ("struct", "Expected `}`, found `struct`"),
+        ]
+    );
+    assert_eq!(module.structs.len(), 1);
+}
+
+#[test]
+fn test_syntax_error_recovery_in_struct_literal() {
+    let raw = "fn main() {
+        let p = new Point { x: 1 + }
+        let y = 2
+    }
+    fn other() {
+    }";
+    let (module, errors) = parse_file(tokenize(raw).unwrap(), FileId::default(), 20);
+    assert_eq!(
+        labelled(raw, &errors),
+        vec![("}", "Expected expression, found `}`")]
+    );
+
+    // The `}` of the literal does not close the function
+    let names: Vec<&str> = module.func.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["main", "other"]);
+    let HStatementKind::Block { statements, .. } = &module.func[0].body.kind else {
+        panic!("expected a block");
+    };
+    assert_eq!(statements.len(), 1);
+}
+
+#[test]
+fn test_syntax_error_limit() {
+    let raw = "fn a( {}\nfn b( {}\nfn c( {}\nfn d( {}";
+    let (_, errors) = parse_file(tokenize(raw).unwrap(), FileId::default(), 2);
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Expected `)`, found `{`",
+            "Expected `)`, found `{`",
+            "Too many syntax errors",
+        ]
+    );
+}
+
+/// Lints a single file that passes the other checks
+fn lint(raw: &str, options: &crate::diagnostic::Options) -> Vec<Diagnostic> {
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    analyze(&mut tree).unwrap();
+    let files = std::iter::once(FileId::default()).collect();
+    crate::parser::lint(&tree, &files, &[], options)
+}
+
+#[test]
+fn test_lints() {
+    let raw = "fn helper(a: int, _b: int): int {
+        return 1
+    }
+    fn show(_value: int) {}
+    fn main() {
+        let x = 1
+        let mut total = 2
+        if true {
+            let total = 3
+            show(total)
+        }
+        for i in [1, 2] {
+            total = i
+        }
+        while true {
+            break
+            show(0)
+        }
+        let _ignored = 4
+    }";
+    let warnings = lint(raw, &Default::default());
+    assert_eq!(
+        labelled(raw, &warnings),
+        vec![
+            (
+                "fn helper(a: int, _b: int): int {\n        return 1\n    }",
+                "Function `helper` is never used"
+            ),
+            ("a: int", "Parameter `a` is never used"),
+            ("x", "Variable `x` is never used"),
+            // Only assigned to
+            ("total", "Variable `total` is never used"),
+            (
+                "total",
+                "Variable `total` shadows a variable of an outer scope"
+            ),
+            ("show(0)", "Unreachable statement"),
+        ]
+    );
+    assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+    assert_eq!(
+        warnings[1].help.as_deref(),
+        Some("prefix it with an underscore if this is intended: `_a`")
+    );
+    assert_eq!(
+        suggested(raw, &warnings[1..3]),
+        vec![Some(("a", "_a")), Some(("x", "_x"))]
+    );
+    let codes: Vec<_> = warnings.iter().map(|w| w.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            Some(codes::UNUSED_FUNCTION),
+            Some(codes::UNUSED_PARAMETER),
+            Some(codes::UNUSED_VARIABLE),
+            Some(codes::UNUSED_VARIABLE),
+            Some(codes::SHADOWED_VARIABLE),
+            Some(codes::UNREACHABLE_CODE),
+        ]
+    );
+}
+
+#[test]
+fn test_match_arm_lints() {
+    let raw = "fn name(x: int): string {
+        match x {
+            1 => return \"one\"
+            1 => return \"uno\"
+            _ => return \"many\"
+            2 => return \"two\"
+        }
+    }
+    fn show(b: bool) {
+        match b {
+            true => name(1)
+            false => name(0)
+            else => name(2)
+        }
+        match b {
+            false => name(0)
+        }
+    }
+    fn main() {
+        show(true)
+    }";
+    let warnings = lint(raw, &Default::default());
+    assert_eq!(
+        labelled(raw, &warnings),
+        vec![
+            ("1", "Unreachable match arm"),
+            ("2", "Unreachable match arm"),
+            ("name(2)", "Unreachable match arm"),
+            ("b", "`match` does not cover `true`"),
+        ]
+    );
+    assert_eq!(
+        secondary(raw, &warnings[0]),
+        vec![("1", "`1` is matched here first")]
+    );
+    assert_eq!(
+        secondary(raw, &warnings[1]),
+        vec![(
+            "return \"many\"",
+            "the `else` arm matches every value first"
+        )]
+    );
+}
+
+#[test]
+fn test_match_exhaustiveness() {
+    let raw = "fn both(b: bool): int {
+        match b {
+            true => return 1
+            false => return 0
+        }
+    }
+    fn one(b: bool): int {
+        match b {
+            true => return 1
+        }
+    }
+    fn some(x: int): int {
+        match x {
+            1 => return 1
+        }
+    }
+    fn main() {}";
+    let mut tree = parse(tokenize(raw).unwrap()).unwrap();
+    let errors = analyze(&mut tree).unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Function `one` does not return a value in all code paths",
+            "Function `some` does not return a value in all code paths",
+        ]
+    );
+    assert_eq!(
+        secondary(raw, &errors[0]),
+        vec![("b", "this `match` does not cover `false`")]
+    );
+    assert_eq!(
+        secondary(raw, &errors[1]),
+        vec![("x", "this `match` has no `else` arm")]
+    );
+}
+
+#[test]
+fn test_lint_levels() {
+    let raw =
+        "fn main() {\n    let x = 1\n    while true {\n        break\n        let y = 2\n    }\n}";
+    let options = |lints: &[(Level, &str)]| crate::diagnostic::Options {
+        lints: lints
+            .iter()
+            .map(|(level, name)| (*level, name.to_string()))
+            .collect(),
+        ..Default::default()
+    };
+    let levels = |options| -> Vec<(Severity, String)> {
+        lint(raw, &options)
+            .into_iter()
+            .map(|d| (d.severity, d.message))
+            .collect()
+    };
+
+    assert_eq!(
+        levels(options(&[(Level::Allow, "unused_variables")])),
+        vec![(Severity::Warning, "Unreachable statement".to_string())]
+    );
+    // The last level that applies wins
+    assert_eq!(
+        levels(options(&[
+            (Level::Deny, "warnings"),
+            (Level::Allow, "unreachable_code"),
+            (Level::Warn, "unused_variables"),
+            (Level::Allow, "warnings"),
+            (Level::Deny, "unused_variables"),
+        ])),
+        vec![
+            (Severity::Error, "Variable `x` is never used".to_string()),
+            (Severity::Error, "Variable `y` is never used".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_unused_imports() {
+    let files = [
+        "import \"used\"\nimport \"unused\"\nimport \"nested\"\nfn main() {\n    first()\n    third()\n}",
+        "fn first() {}",
+        "fn second() {}",
+        "import \"deeper\"",
+        "fn third() {}",
+    ];
+    let mut program: Option<HModule> = None;
+    for (i, raw) in files.iter().enumerate() {
+        let (module, errors) = parse_file(tokenize(raw).unwrap(), FileId(i), 20);
+        assert!(errors.is_empty());
+        match &mut program {
+            Some(program) => program.merge_with(module),
+            None => program = Some(module),
+        }
+    }
+    let mut program = program.unwrap();
+    let imports: Vec<Import> = [
+        (0, "used", 1),
+        (0, "unused", 2),
+        (0, "nested", 3),
+        (3, "deeper", 4),
+    ]
+    .iter()
+    .map(|&(file, path, module)| {
+        let start = files[file].find(&format!("import \"{}\"", path)).unwrap();
+        Import {
+            path: path.to_string(),
+            span: Span::new(FileId(file), start, start + path.len() + 9),
+            module: FileId(module),
+        }
+    })
+    .collect();
+    analyze(&mut program).unwrap();
+    let all_files = (0..files.len()).map(FileId).collect();
+    let warnings = crate::parser::lint(&program, &all_files, &imports, &Default::default());
+    // `deeper` is used by the file that imports `nested`
+    let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec!["Unused import `unused`", "Function `second` is never used"]
+    );
+}
@@ -499,3 +499,62 @@ fn test_qbe_execution_tests() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Compile every .sb file in tests/c/ with the C backend and gcc, and run it.
+/// The C target has no standard library, so each program reports failures
+/// with a non-zero exit code.
+#[test]
+fn test_c_execution_tests() -> Result<(), Error> {
+    let dir = std::env::current_dir().unwrap();
+    let dir_out = dir.join("tests_c_out");
+    let _ = fs::create_dir(&dir_out);
+
+    for entry in std::fs::read_dir(dir.join("tests/c"))? {
+        let in_file = entry?.path();
+        if in_file.extension().is_none_or(|e| e != "sb") {
+            continue;
+        }
+        let base_name = in_file.file_stem().unwrap().to_string_lossy().into_owned();
+        let c_file = dir_out.join(format!("{}.c", base_name));
+        let bin_file = dir_out.join(&base_name);
+
+        let compile = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("--target")
+            .arg("c")
+            .arg("build")
+            .arg(&in_file)
+            .arg("-o")
+            .arg(&c_file)
+            .output()?;
+        assert!(
+            compile.status.success(),
+            "C compile failed for {:?}: {}",
+            in_file,
+            String::from_utf8_lossy(&compile.stderr)
+        );
+
+        let gcc = Command::new("gcc")
+            .arg("-o")
+            .arg(&bin_file)
+            .arg(&c_file)
+            .output()?;
+        assert!(
+            gcc.status.success(),
+            "gcc failed for {:?}: {}",
+            &c_file,
+            String::from_utf8_lossy(&gcc.stderr)
+        );
+
+        let execution = Command::new(&bin_file).output()?;
+        assert_eq!(
+            execution.status.code(),
+            Some(0),
+            "Binary {:?} exited with non-zero code {:?}",
+            &bin_file,
+            execution.status.code()
+        );
+    }
+    Ok(())
+}